@@ -0,0 +1,100 @@
+//! Benchmark pack and unpack kernels of transposes
+//!
+//! Compares the kernels against a plain elementwise copy, for blocks
+//! whose memory layout matches (``c``) or mismatches (``f``) the
+//! buffer order, and for strided blocks cut out of a larger array.
+//!
+//! cargo bench --bench pack
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use ndarray::{s, Array3, ArrayView3, ArrayViewMut3, Order, ShapeBuilder};
+use pencil_decomp::pack::{pack_block, unpack_block};
+use pencil_decomp::{Decomp3, ThreadComm};
+
+const SIZES: [usize; 3] = [32, 64, 128];
+
+/// Elementwise copy in row-major order
+fn pack_naive(block: ArrayView3<f64>, buf: &mut [f64]) {
+    for (b, x) in buf.iter_mut().zip(block.iter()) {
+        *b = *x;
+    }
+}
+
+/// Elementwise copy in row-major order
+fn unpack_naive(buf: &[f64], mut block: ArrayViewMut3<f64>) {
+    for (x, b) in block.iter_mut().zip(buf.iter()) {
+        *x = *b;
+    }
+}
+
+/// Arrays of size n^3 with row-major and column-major layout
+fn layouts(n: usize) -> [(&'static str, Array3<f64>); 2] {
+    [
+        (
+            "c",
+            Array3::from_shape_fn((n, n, n), |(i, j, k)| (i + j + k) as f64),
+        ),
+        (
+            "f",
+            Array3::from_shape_fn((n, n, n).f(), |(i, j, k)| (i + j + k) as f64),
+        ),
+    ]
+}
+
+pub fn bench_pack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Pack");
+    for n in SIZES {
+        for (layout, data) in layouts(n) {
+            // Half of the array along the first axis, as cut out by a transpose
+            let block = data.slice(s![..n / 2, .., ..]);
+            let mut buf = vec![0.; block.len()];
+            let name = format!("{layout} {n}");
+            group.bench_function(BenchmarkId::new("naive", &name), |b| {
+                b.iter(|| pack_naive(black_box(block), &mut buf));
+            });
+            group.bench_function(BenchmarkId::new("kernel", &name), |b| {
+                b.iter(|| pack_block(black_box(block), &mut buf, Order::RowMajor));
+            });
+        }
+    }
+    group.finish();
+}
+
+pub fn bench_unpack(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Unpack");
+    for n in SIZES {
+        for (layout, mut data) in layouts(n) {
+            let buf = vec![1.; n * n * n / 2];
+            let name = format!("{layout} {n}");
+            group.bench_function(BenchmarkId::new("naive", &name), |b| {
+                b.iter(|| unpack_naive(black_box(&buf), data.slice_mut(s![..n / 2, .., ..])));
+            });
+            group.bench_function(BenchmarkId::new("kernel", &name), |b| {
+                b.iter(|| {
+                    unpack_block(
+                        black_box(&buf),
+                        data.slice_mut(s![..n / 2, .., ..]),
+                        Order::RowMajor,
+                    )
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+pub fn bench_transpose(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Transpose");
+    let comm = ThreadComm::run(1, |comm| comm).pop().unwrap();
+    for n in SIZES {
+        let decomp = Decomp3::from_comm(&comm, [n, n, n], [1, 1], [false, false]);
+        let x = Array3::<f64>::ones(decomp.x_pencil.shape());
+        let mut y = Array3::<f64>::zeros(decomp.y_pencil.shape());
+        group.bench_function(BenchmarkId::new("x to y", n), |b| {
+            b.iter(|| decomp.transpose_x_to_y(black_box(&x), &mut y));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_pack, bench_unpack, bench_transpose);
+criterion_main!(benches);
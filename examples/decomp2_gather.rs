@@ -3,10 +3,11 @@ use mpi::topology::Communicator;
 use ndarray::Array2;
 use pencil_decomp::pencil::recv_counts_gather_axis;
 use pencil_decomp::{Decomp2, Pencil};
+use std::sync::Arc;
 
 fn main() {
     // Init Mpi
-    let universe = mpi::initialize().unwrap();
+    let universe = Arc::new(mpi::initialize().unwrap());
     let world = universe.world();
     assert!(world.size() == 2, "Run with 2 processors");
 
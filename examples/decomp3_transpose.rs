@@ -2,10 +2,11 @@
 use mpi::topology::Communicator;
 use ndarray::Array3;
 use pencil_decomp::{Decomp3, Pencil};
+use std::sync::Arc;
 
 fn main() {
     // Init Mpi
-    let universe = mpi::initialize().unwrap();
+    let universe = Arc::new(mpi::initialize().unwrap());
     let world = universe.world();
     assert!(world.size() == 6, "Run with 6 processors");
 
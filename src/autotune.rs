@@ -0,0 +1,252 @@
+//! # Autotuning of three dimensional decompositions
+//!
+//! Times round-trip transposes x -> y -> z -> y -> x for every
+//! processor grid and [`Exchange`] algorithm of the backend, see
+//! [`Comm::supported_exchanges`], and picks the fastest.
+//!
+//! # Example
+//! ```
+//! use pencil_decomp::autotune::autotune;
+//! use pencil_decomp::ThreadComm;
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let tuning = autotune::<f64, _>(&comm, [8, 8, 8], [false, false], 2, None);
+//!     assert_eq!(tuning.cart_dims[0] * tuning.cart_dims[1], 4);
+//!     let decomp = tuning.decomp(&comm, [8, 8, 8], [false, false]);
+//! });
+//! ```
+use crate::comm::{Comm, Element, Exchange};
+use crate::simple_comms::{all_gather_apply, broadcast_scalar};
+use crate::Decomp3;
+use ndarray::Array3;
+use num_traits::Zero;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::time::Instant;
+
+/// Fastest configuration found by [`autotune`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tuning {
+    /// Number of processors along each dimension of the cartesian topology
+    pub cart_dims: [i32; 2],
+    /// Algorithm of all-to-all exchanges
+    pub exchange: Exchange,
+    /// Wall time of one round-trip transpose in seconds (max over processors)
+    pub seconds: f64,
+}
+
+impl Tuning {
+    /// Construct the tuned decomposition
+    #[must_use]
+    pub fn decomp<C: Comm>(
+        &self,
+        comm: &C,
+        n_global: [usize; 3],
+        cart_periodic: [bool; 2],
+    ) -> Decomp3<C> {
+        Decomp3::from_comm(comm, n_global, self.cart_dims, cart_periodic)
+            .with_exchange(self.exchange)
+    }
+}
+
+/// Find the fastest processor grid and exchange algorithm for
+/// transposes of arrays of type ``T`` on ``comm``
+///
+/// Each candidate is timed over ``repeats`` round-trip transposes after
+/// one warm-up round trip. All processors return the same result.
+///
+/// If ``cache`` is given, it is searched for a result with the same
+/// number of processors, grid size and data type first, and new
+/// results are appended to it. Cached results with a processor grid
+/// which does not fit or an exchange which ``comm`` does not support,
+/// see [`Comm::supported_exchanges`], are ignored. The file is accessed
+/// on rank 0 only.
+///
+/// # Panics
+/// - No processor grid fits ``n_global``
+/// - Cache file can not be written
+pub fn autotune<T, C>(
+    comm: &C,
+    n_global: [usize; 3],
+    cart_periodic: [bool; 2],
+    repeats: usize,
+    cache: Option<&Path>,
+) -> Tuning
+where
+    T: Zero + Element,
+    C: Comm,
+{
+    let key = CacheKey {
+        nprocs: comm.size(),
+        n_global,
+        dtype: std::any::type_name::<T>(),
+    };
+    let is_root = comm.rank() == 0;
+    let candidates = cart_dims_candidates(comm.size(), n_global);
+    let exchanges = comm.supported_exchanges();
+
+    // Look up cache on root and share the result
+    let mut cached = [0; 3];
+    let mut seconds = 0.;
+    if is_root {
+        let valid =
+            |t: &Tuning| candidates.contains(&t.cart_dims) && exchanges.contains(&t.exchange);
+        if let Some(tuning) = cache.and_then(|path| read_cache(path, &key, valid)) {
+            cached = [
+                tuning.cart_dims[0],
+                tuning.cart_dims[1],
+                exchange_index(tuning.exchange),
+            ];
+            seconds = tuning.seconds;
+        }
+    }
+    comm.broadcast(0, &mut cached);
+    broadcast_scalar(comm, &mut seconds);
+    if cached[0] > 0 {
+        return Tuning {
+            cart_dims: [cached[0], cached[1]],
+            exchange: Exchange::ALL[usize::try_from(cached[2]).unwrap()],
+            seconds,
+        };
+    }
+
+    assert!(
+        !candidates.is_empty(),
+        "No processor grid of {} procs fits grid {n_global:?}.",
+        comm.size()
+    );
+    let mut best: Option<Tuning> = None;
+    for cart_dims in candidates {
+        let mut decomp = Decomp3::from_comm(comm, n_global, cart_dims, cart_periodic);
+        for &exchange in exchanges {
+            decomp = decomp.with_exchange(exchange);
+            let seconds = time_round_trip::<T, C>(&decomp, repeats);
+            if !best.is_some_and(|b| b.seconds <= seconds) {
+                best = Some(Tuning {
+                    cart_dims,
+                    exchange,
+                    seconds,
+                });
+            }
+        }
+    }
+    let best = best.unwrap();
+    if is_root {
+        if let Some(path) = cache {
+            write_cache(path, &key, &best);
+        }
+    }
+    best
+}
+
+/// Processor grids [p0, p1] with p0 * p1 = ``nprocs``, for which
+/// every pencil has at least one grid point per processor
+fn cart_dims_candidates(nprocs: i32, n_global: [usize; 3]) -> Vec<[i32; 2]> {
+    let fits = |p: i32, n: usize| usize::try_from(p).unwrap() <= n;
+    (1..=nprocs)
+        .filter(|p0| nprocs % p0 == 0)
+        .map(|p0| [p0, nprocs / p0])
+        // p0 splits y (x-pencil) and x (y- and z-pencil),
+        // p1 splits z (x- and y-pencil) and y (z-pencil)
+        .filter(|&[p0, p1]| {
+            fits(p0, n_global[0].min(n_global[1])) && fits(p1, n_global[1].min(n_global[2]))
+        })
+        .collect()
+}
+
+/// Wall time of one round-trip transpose, max over processors
+fn time_round_trip<T, C>(decomp: &Decomp3<C>, repeats: usize) -> f64
+where
+    T: Zero + Element,
+    C: Comm,
+{
+    let mut x = Array3::<T>::zeros(decomp.x_pencil.shape());
+    let mut y = Array3::<T>::zeros(decomp.y_pencil.shape());
+    let mut z = Array3::<T>::zeros(decomp.z_pencil.shape());
+    let mut round_trip = || {
+        decomp.transpose_x_to_y(&x, &mut y);
+        decomp.transpose_y_to_z(&y, &mut z);
+        decomp.transpose_z_to_y(&z, &mut y);
+        decomp.transpose_y_to_x(&y, &mut x);
+    };
+    round_trip();
+    let start = Instant::now();
+    for _ in 0..repeats {
+        round_trip();
+    }
+    #[allow(clippy::cast_precision_loss)]
+    let local = start.elapsed().as_secs_f64() / repeats.max(1) as f64;
+    let mut seconds = 0.;
+    let max = |x: &[f64]| x.iter().copied().fold(0., f64::max);
+    all_gather_apply(&decomp.x_pencil.comm, &local, &mut seconds, max);
+    seconds
+}
+
+/// Key of cached results
+struct CacheKey {
+    nprocs: i32,
+    n_global: [usize; 3],
+    dtype: &'static str,
+}
+
+impl CacheKey {
+    /// Leading fields of a cache line
+    fn prefix(&self) -> String {
+        let [n0, n1, n2] = self.n_global;
+        format!("{} {n0} {n1} {n2} {}", self.nprocs, self.dtype)
+    }
+}
+
+/// Index of ``exchange`` in [`Exchange::ALL`]
+fn exchange_index(exchange: Exchange) -> i32 {
+    let index = Exchange::ALL.iter().position(|&e| e == exchange).unwrap();
+    index.try_into().unwrap()
+}
+
+/// Last matching and ``valid`` result in cache file
+///
+/// Lines read ``nprocs nx ny nz dtype p0 p1 exchange seconds``.
+/// Missing files and malformed lines are ignored.
+fn read_cache<F: Fn(&Tuning) -> bool>(path: &Path, key: &CacheKey, valid: F) -> Option<Tuning> {
+    let file = File::open(path).ok()?;
+    let prefix = key.prefix();
+    BufReader::new(file)
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| {
+            let rest = line.strip_prefix(&prefix)?.strip_prefix(' ')?;
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            match fields[..] {
+                [p0, p1, exchange, seconds] => Some(Tuning {
+                    cart_dims: [p0.parse().ok()?, p1.parse().ok()?],
+                    exchange: exchange.parse().ok()?,
+                    seconds: seconds.parse().ok()?,
+                }),
+                _ => None,
+            }
+        })
+        .filter(valid)
+        .last()
+}
+
+/// Append result to cache file
+///
+/// # Panics
+/// File can not be opened or written
+fn write_cache(path: &Path, key: &CacheKey, tuning: &Tuning) {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .unwrap_or_else(|e| panic!("Can not open cache {}: {e}", path.display()));
+    let [p0, p1] = tuning.cart_dims;
+    writeln!(
+        file,
+        "{} {p0} {p1} {} {:e}",
+        key.prefix(),
+        tuning.exchange,
+        tuning.seconds
+    )
+    .unwrap_or_else(|e| panic!("Can not write cache {}: {e}", path.display()));
+}
@@ -0,0 +1,365 @@
+//! Inspect a pencil decomposition without launching MPI
+//!
+//! cargo run --bin pencil_inspect -- --n 256,256,128 --procs 4x8
+//!
+//! Prints extents and shapes of all pencils on each processor, load
+//! imbalance and number of messages of transposes, see
+//! [`pencil_decomp::plan`].
+use pencil_decomp::plan::Plan;
+use pencil_decomp::render;
+use std::fmt::Write;
+use std::process::exit;
+
+const USAGE: &str = "\
+Usage: pencil_inspect --n NX,NY[,NZ] --procs P0[xP1] [OPTIONS]
+
+Options:
+    --n          Total number of grid points, e.g. 256,256,128
+    --procs      Processors along each dimension of the cartesian
+                 topology, one less than grid dimensions, e.g. 4x8
+    --elem-size  Size of one element in bytes [default: 8]
+    --format     Output format: table, json (serde feature), csv, svg,
+                 or ascii for two dimensional grids [default: table]
+    --help       Print this message";
+
+/// Names of pencils and axes
+const AXES: [&str; 3] = ["x", "y", "z"];
+
+/// Output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Table,
+    Json,
+    Csv,
+    Svg,
+    Ascii,
+}
+
+/// Command line arguments
+#[derive(Debug)]
+struct Args {
+    n_global: Vec<usize>,
+    procs: Vec<i32>,
+    elem_size: usize,
+    format: Format,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{USAGE}");
+        return;
+    }
+    let output = parse_args(&args).and_then(|args| inspect(&args));
+    match output {
+        Ok(output) => print!("{output}"),
+        Err(msg) => {
+            eprintln!("error: {msg}\n\n{USAGE}");
+            exit(2);
+        }
+    }
+}
+
+/// Parse command line arguments
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let mut n_global = None;
+    let mut procs = None;
+    let mut elem_size = 8;
+    let mut format = Format::Table;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("Missing value of {arg}"));
+        match arg.as_str() {
+            "--n" => n_global = Some(parse_list(value()?, ',')?),
+            "--procs" => procs = Some(parse_list(value()?, 'x')?),
+            "--elem-size" => {
+                let v = value()?;
+                elem_size = v.parse().map_err(|_| format!("Invalid element size {v}"))?;
+            }
+            "--format" => {
+                format = match value()?.as_str() {
+                    "table" => Format::Table,
+                    "json" if cfg!(feature = "serde") => Format::Json,
+                    "json" => return Err("JSON output needs the serde feature".into()),
+                    "csv" => Format::Csv,
+                    "svg" => Format::Svg,
+                    "ascii" => Format::Ascii,
+                    v => return Err(format!("Unknown format {v}")),
+                }
+            }
+            _ => return Err(format!("Unknown argument {arg}")),
+        }
+    }
+    Ok(Args {
+        n_global: n_global.ok_or("Missing --n")?,
+        procs: procs.ok_or("Missing --procs")?,
+        elem_size,
+        format,
+    })
+}
+
+/// Parse list of positive numbers separated by ``sep``
+fn parse_list<T: std::str::FromStr + Default + PartialOrd>(
+    list: &str,
+    sep: char,
+) -> Result<Vec<T>, String> {
+    list.split(sep)
+        .map(|v| match v.trim().parse::<T>() {
+            Ok(v) if v > T::default() => Ok(v),
+            _ => Err(format!("Invalid entry {v:?} in {list}")),
+        })
+        .collect()
+}
+
+/// Inspect decomposition of any supported dimensionality
+fn inspect(args: &Args) -> Result<String, String> {
+    match (&args.n_global[..], &args.procs[..]) {
+        (&[n0, n1], &[p0]) => {
+            let plan = Plan::new([n0, n1], [p0], args.elem_size);
+            check(&plan)?;
+            if args.format == Format::Ascii {
+                return Ok(render::ascii(&plan));
+            }
+            Ok(report(&plan, args.format))
+        }
+        (&[n0, n1, n2], &[p0, p1]) => {
+            let plan = Plan::new([n0, n1, n2], [p0, p1], args.elem_size);
+            check(&plan)?;
+            if args.format == Format::Ascii {
+                return Err("ASCII output needs a two dimensional grid".to_string());
+            }
+            Ok(report(&plan, args.format))
+        }
+        _ => Err(format!(
+            "Expect 2 or 3 grid dimensions and one processor dimension less, got {:?} and {:?}",
+            args.n_global, args.procs
+        )),
+    }
+}
+
+/// Check that every pencil of ``plan`` has grid points on all processors
+fn check<const M: usize, const N: usize>(plan: &Plan<M, N>) -> Result<(), String> {
+    // Cartesian dimension d splits axis d or d + 1, depending on the pencil
+    for (d, &p) in plan.cart_dims.iter().enumerate() {
+        let n = plan.n_global[d].min(plan.n_global[d + 1]);
+        if usize::try_from(p).unwrap() > n {
+            return Err(format!(
+                "Can't distribute {n} grid points on {p} processors along dimension {d}"
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Format report of ``plan``
+fn report<const M: usize, const N: usize>(plan: &Plan<M, N>, format: Format) -> String {
+    let inspection = Inspection::new(plan);
+    match format {
+        Format::Table => inspection.table(),
+        #[cfg(feature = "serde")]
+        Format::Json => inspection.json(),
+        #[cfg(not(feature = "serde"))]
+        Format::Json => unreachable!("JSON is rejected by parse_args"),
+        Format::Csv => inspection.csv(),
+        Format::Svg => render::svg(plan),
+        Format::Ascii => unreachable!("ASCII is rendered by inspect"),
+    }
+}
+
+/// Pencils and transposes of one processor
+struct Rank {
+    rank: i32,
+    coords: Vec<i32>,
+    /// Global index ranges and shape of each pencil
+    pencils: Vec<(Vec<[usize; 2]>, Vec<usize>)>,
+    /// Messages and bytes sent to other processors in each transpose
+    transposes: Vec<(usize, usize)>,
+}
+
+/// Summary of a decomposition
+struct Inspection {
+    n_global: Vec<usize>,
+    cart_dims: Vec<i32>,
+    /// Names of transposes
+    transposes: Vec<String>,
+    ranks: Vec<Rank>,
+    /// Largest over average pencil size, for each pencil
+    imbalance: Vec<f64>,
+}
+
+impl Inspection {
+    fn new<const M: usize, const N: usize>(plan: &Plan<M, N>) -> Self {
+        let pairs: Vec<(usize, usize)> = (0..M)
+            .flat_map(|a| (a + 1..M).map(move |b| (a, b)))
+            .collect();
+        let transposes: Vec<_> = pairs.iter().map(|&(a, b)| plan.transpose(a, b)).collect();
+        let pencils: Vec<_> = (0..M).map(|axis| plan.pencils(axis)).collect();
+        let ranks = (0..plan.nprocs())
+            .map(|rank| {
+                let r = usize::try_from(rank).unwrap();
+                Rank {
+                    rank,
+                    coords: plan.cart_coords(rank).to_vec(),
+                    pencils: pencils
+                        .iter()
+                        .map(|p| {
+                            let ranges = p[r].ranges.iter().map(|x| [x.start, x.end]).collect();
+                            (ranges, p[r].shape.to_vec())
+                        })
+                        .collect(),
+                    transposes: transposes
+                        .iter()
+                        .map(|t| (t.messages(rank), t.bytes_sent(rank)))
+                        .collect(),
+                }
+            })
+            .collect();
+        #[allow(clippy::cast_precision_loss)]
+        let imbalance = pencils
+            .iter()
+            .map(|p| {
+                let max = p.iter().map(|x| x.bytes).max().unwrap_or(0) as f64;
+                let avg = p.iter().map(|x| x.bytes).sum::<usize>() as f64 / p.len() as f64;
+                if avg > 0. {
+                    max / avg
+                } else {
+                    1.
+                }
+            })
+            .collect();
+        Self {
+            n_global: plan.n_global.to_vec(),
+            cart_dims: plan.cart_dims.to_vec(),
+            transposes: pairs
+                .iter()
+                .map(|&(a, b)| format!("{}->{}", AXES[a], AXES[b]))
+                .collect(),
+            ranks,
+            imbalance,
+        }
+    }
+
+    /// Column names of per-processor tables
+    fn header(&self) -> Vec<String> {
+        let mut header = vec!["rank".to_string(), "coords".to_string()];
+        for axis in AXES.iter().take(self.n_global.len()) {
+            header.push(format!("{axis} extent"));
+            header.push(format!("{axis} shape"));
+        }
+        for t in &self.transposes {
+            header.push(format!("{t} msgs"));
+            header.push(format!("{t} bytes"));
+        }
+        header
+    }
+
+    /// Cells of per-processor tables
+    fn rows(&self) -> Vec<Vec<String>> {
+        self.ranks
+            .iter()
+            .map(|r| {
+                let mut row = vec![r.rank.to_string(), join(&r.coords, "x")];
+                for (ranges, shape) in &r.pencils {
+                    let ranges: Vec<String> = ranges
+                        .iter()
+                        .map(|[st, en]| format!("{st}..{en}"))
+                        .collect();
+                    row.push(ranges.join(" "));
+                    row.push(join(shape, "x"));
+                }
+                for (msgs, bytes) in &r.transposes {
+                    row.push(msgs.to_string());
+                    row.push(bytes.to_string());
+                }
+                row
+            })
+            .collect()
+    }
+
+    fn table(&self) -> String {
+        let header = self.header();
+        let rows = self.rows();
+        let widths: Vec<usize> = (0..header.len())
+            .map(|j| {
+                rows.iter()
+                    .map(|r| r[j].len())
+                    .chain([header[j].len()])
+                    .max()
+                    .unwrap()
+            })
+            .collect();
+        let mut out = String::new();
+        let nprocs = self.ranks.len();
+        let (n, p) = (join(&self.n_global, " x "), join(&self.cart_dims, " x "));
+        writeln!(out, "Grid {n} on {p} = {nprocs} processors\n").unwrap();
+        for row in [header].iter().chain(rows.iter()) {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths.iter())
+                .map(|(c, w)| format!("{c:>w$}"))
+                .collect();
+            writeln!(out, "{}", cells.join("  ").trim_end()).unwrap();
+        }
+        writeln!(out, "\nLoad imbalance (largest / average pencil)").unwrap();
+        for (axis, imbalance) in AXES.iter().zip(self.imbalance.iter()) {
+            writeln!(out, "{axis}-pencil: {imbalance:.3}").unwrap();
+        }
+        out
+    }
+
+    fn csv(&self) -> String {
+        let mut out = String::new();
+        for row in [self.header()].iter().chain(self.rows().iter()) {
+            writeln!(out, "{}", row.join(",")).unwrap();
+        }
+        out
+    }
+
+    #[cfg(feature = "serde")]
+    fn json(&self) -> String {
+        use serde_json::{json, Map, Value};
+        let named = |values: Vec<Value>, names: &[String]| -> Map<String, Value> {
+            names.iter().cloned().zip(values).collect()
+        };
+        let axes: Vec<String> = AXES.iter().map(ToString::to_string).collect();
+        let ranks: Vec<Value> = self
+            .ranks
+            .iter()
+            .map(|r| {
+                let pencils = r
+                    .pencils
+                    .iter()
+                    .map(|(ranges, shape)| json!({"ranges": ranges, "shape": shape}))
+                    .collect();
+                let transposes = r
+                    .transposes
+                    .iter()
+                    .map(|(msgs, bytes)| json!({"messages": msgs, "bytes_sent": bytes}))
+                    .collect();
+                json!({
+                    "rank": r.rank,
+                    "coords": r.coords,
+                    "pencils": named(pencils, &axes),
+                    "transposes": named(transposes, &self.transposes),
+                })
+            })
+            .collect();
+        let imbalance = self.imbalance.iter().map(|&x| json!(x)).collect();
+        let inspection = json!({
+            "n_global": self.n_global,
+            "cart_dims": self.cart_dims,
+            "imbalance": named(imbalance, &axes),
+            "ranks": ranks,
+        });
+        format!("{inspection}\n")
+    }
+}
+
+/// Join ``values`` with ``sep``
+fn join<T: ToString>(values: &[T], sep: &str) -> String {
+    values
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(sep)
+}
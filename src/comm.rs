@@ -0,0 +1,1025 @@
+//! # Communication backends
+//!
+//! The collective routines required by the pencil distributions
+//! are abstracted in the [`Comm`] trait. It is implemented for
+//! mpi communicators, [`MpiComm`] which keeps mpi alive as long as
+//! it is used, and for [`crate::ThreadComm`], an in-process backend
+//! which simulates several ranks with threads.
+//!
+//! The algorithm of the all-to-all exchange in transposes can be
+//! selected with [`Exchange`], per pencil or with the environment
+//! variable ``PENCIL_DECOMP_EXCHANGE``.
+use mpi::collective::{CommunicatorCollectives, Root, UserOperation};
+use mpi::datatype::{DynBuffer, DynBufferMut, Partition, PartitionMut, UserDatatype};
+use mpi::environment::Universe;
+use mpi::ffi;
+use mpi::point_to_point::{send_receive_into, Destination, Source};
+use mpi::raw::{AsRaw, FromRaw};
+use mpi::request::{self, WaitGuard};
+use mpi::topology::{Color, Communicator, Rank, UserCommunicator};
+use mpi::traits::Equivalence;
+use mpi::Count;
+use ndarray::{ArrayViewD, ArrayViewMutD, IxDyn, Order, ShapeBuilder, Slice};
+use std::fmt;
+use std::ops::Range;
+use std::os::raw::c_int;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// Environment variable which selects the default [`Exchange`]
+pub const EXCHANGE_ENV: &str = "PENCIL_DECOMP_EXCHANGE";
+
+/// Data types which can be exchanged by a [`Comm`]
+pub trait Element: Copy + Equivalence + Send + Sync + 'static {}
+
+impl<T: Copy + Equivalence + Send + Sync + 'static> Element for T {}
+
+/// Algorithm of all-to-all exchanges in transposes
+///
+/// All algorithms give identical results, but their performance
+/// depends on the mpi implementation and the network.
+///
+/// # Example
+/// ```
+/// use pencil_decomp::comm::Exchange;
+///
+/// let exchange: Exchange = "pairwise".parse().unwrap();
+/// assert_eq!(exchange, Exchange::Pairwise);
+/// assert_eq!(exchange.to_string(), "pairwise");
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Exchange {
+    /// Collective ``mpi_alltoallv``
+    #[default]
+    Collective,
+    /// ``size`` rounds of ``mpi_sendrecv``, in round *i* each processor
+    /// sends to *rank + i* and receives from *rank - i*
+    Pairwise,
+    /// All ``mpi_isend`` and ``mpi_irecv`` posted at once
+    Nonblocking,
+    /// ``mpi_neighbor_alltoallv`` on a graph topology, which only
+    /// connects processors that exchange data. Needs an [`MpiComm`],
+    /// which creates the graph once and reuses it.
+    Neighbor,
+}
+
+impl Exchange {
+    /// All algorithms
+    pub const ALL: [Exchange; 4] = [
+        Exchange::Collective,
+        Exchange::Pairwise,
+        Exchange::Nonblocking,
+        Exchange::Neighbor,
+    ];
+
+    /// Algorithm from environment variable ``PENCIL_DECOMP_EXCHANGE``,
+    /// defaults to ``Exchange::Collective`` if it is unset
+    ///
+    /// # Panics
+    /// Variable holds an unknown algorithm
+    #[must_use]
+    pub fn from_env() -> Self {
+        match std::env::var(EXCHANGE_ENV) {
+            Ok(value) => value
+                .parse()
+                .unwrap_or_else(|e| panic!("{EXCHANGE_ENV}: {e}")),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+impl fmt::Display for Exchange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Exchange::Collective => "collective",
+            Exchange::Pairwise => "pairwise",
+            Exchange::Nonblocking => "nonblocking",
+            Exchange::Neighbor => "neighbor",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Exchange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|e| e.to_string().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown exchange {s:?}, expect collective, pairwise, nonblocking or neighbor."
+                )
+            })
+    }
+}
+
+/// Block of a multidimensional array, which is stored
+/// contiguously in memory (``mpi_type_create_subarray``)
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Subarray {
+    /// Shape of the full array
+    pub sizes: Vec<usize>,
+    /// Shape of the block
+    pub subsizes: Vec<usize>,
+    /// Start of the block in the full array
+    pub starts: Vec<usize>,
+    /// Memory order of the full array. Elements of the block
+    /// are sent and received in the same order.
+    pub order: Order,
+}
+
+impl Subarray {
+    /// View of block in ``data``, with axes reversed for
+    /// ``Order::ColumnMajor``, such that the elements are
+    /// iterated in the order in which they are exchanged
+    ///
+    /// # Panics
+    /// Block exceeds the full array, or the full array
+    /// does not match the size of ``data``
+    pub(crate) fn view<'a, T>(&self, data: &'a [T]) -> ArrayViewD<'a, T> {
+        let shape = IxDyn(&self.sizes).set_f(self.order == Order::ColumnMajor);
+        let mut view = ArrayViewD::from_shape(shape, data).expect("Subarray does not match data.");
+        view.slice_each_axis_inplace(|ax| self.slice(ax.axis.index()));
+        if self.order == Order::ColumnMajor {
+            view.reversed_axes()
+        } else {
+            view
+        }
+    }
+
+    /// Mutable view of block in ``data``, see [`Subarray::view`]
+    ///
+    /// # Panics
+    /// Block exceeds the full array, or the full array
+    /// does not match the size of ``data``
+    pub(crate) fn view_mut<'a, T>(&self, data: &'a mut [T]) -> ArrayViewMutD<'a, T> {
+        let shape = IxDyn(&self.sizes).set_f(self.order == Order::ColumnMajor);
+        let mut view =
+            ArrayViewMutD::from_shape(shape, data).expect("Subarray does not match data.");
+        view.slice_each_axis_inplace(|ax| self.slice(ax.axis.index()));
+        if self.order == Order::ColumnMajor {
+            view.reversed_axes()
+        } else {
+            view
+        }
+    }
+
+    /// Assert that block lies inside of a full array of length ``len``
+    ///
+    /// # Panics
+    /// Block exceeds the full array, or the full array has
+    /// a length different from ``len``
+    fn assert_fits(&self, len: usize) {
+        let n = self.sizes.len();
+        assert!(
+            self.subsizes.len() == n && self.starts.len() == n,
+            "Subarray dimensions mismatch."
+        );
+        assert_eq!(
+            self.sizes.iter().product::<usize>(),
+            len,
+            "Subarray does not match data."
+        );
+        for ((&size, &subsize), &start) in self.sizes.iter().zip(&self.subsizes).zip(&self.starts) {
+            assert!(start + subsize <= size, "Subarray exceeds array.");
+        }
+    }
+
+    /// Range of block along ``axis``
+    fn slice(&self, axis: usize) -> Slice {
+        Slice::from(self.starts[axis]..self.starts[axis] + self.subsizes[axis])
+    }
+
+    /// Committed mpi datatype of block
+    ///
+    /// # Panics
+    /// usize to i32 conversion fails
+    // Type of the order constants depends on the mpi implementation
+    #[allow(clippy::cast_possible_wrap)]
+    fn datatype<T: Equivalence>(&self) -> UserDatatype {
+        let to_counts =
+            |x: &[usize]| -> Vec<Count> { x.iter().map(|&v| v.try_into().unwrap()).collect() };
+        let sizes = to_counts(&self.sizes);
+        let subsizes = to_counts(&self.subsizes);
+        let starts = to_counts(&self.starts);
+        let order = if self.order == Order::ColumnMajor {
+            ffi::MPI_ORDER_FORTRAN as c_int
+        } else {
+            ffi::MPI_ORDER_C as c_int
+        };
+        // Safety: array arguments have length ndims, newtype
+        // is committed before it is wrapped into a UserDatatype
+        unsafe {
+            let mut newtype = ffi::RSMPI_DATATYPE_NULL;
+            ffi::MPI_Type_create_subarray(
+                sizes.len().try_into().unwrap(),
+                sizes.as_ptr(),
+                subsizes.as_ptr(),
+                starts.as_ptr(),
+                order,
+                T::equivalent_datatype().as_raw(),
+                &raw mut newtype,
+            );
+            ffi::MPI_Type_commit(&raw mut newtype);
+            UserDatatype::from_raw(newtype)
+        }
+    }
+}
+
+/// Collective communication routines used by pencil distributions
+///
+/// Counts and displacements have the same meaning as in the
+/// corresponding mpi routines.
+pub trait Comm: Sized {
+    /// Rank of current processor
+    fn rank(&self) -> Rank;
+
+    /// Number of processors in communicator
+    fn size(&self) -> Rank;
+
+    /// Split communicator into disjoint sub-groups
+    ///
+    /// Processors with the same ``color`` end up in the same
+    /// sub-group, where they are ordered by ``key``.
+    /// (``mpi_comm_split``)
+    #[must_use]
+    fn split(&self, color: Rank, key: Rank) -> Self;
+
+    /// Send ``send_counts[i]`` elements at ``send_displs[i]`` to
+    /// processor *i* and receive ``recv_counts[i]`` elements at
+    /// ``recv_displs[i]`` from processor *i* (``mpi_alltoallv``)
+    fn all_to_all_varcount<T: Element>(
+        &self,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    );
+
+    /// Algorithms of [`Comm::all_to_all_varcount_with`] implemented by
+    /// this backend
+    fn supported_exchanges(&self) -> &'static [Exchange] {
+        &[Exchange::Collective]
+    }
+
+    /// Same as [`Comm::all_to_all_varcount`], with the given algorithm
+    ///
+    /// Backends without alternative algorithms use the collective,
+    /// see [`Comm::supported_exchanges`].
+    #[allow(clippy::too_many_arguments)]
+    fn all_to_all_varcount_with<T: Element>(
+        &self,
+        _exchange: Exchange,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        self.all_to_all_varcount(
+            send,
+            send_counts,
+            send_displs,
+            recv,
+            recv_counts,
+            recv_displs,
+        );
+    }
+
+    /// Gather ``send`` of all processors into ``recv`` on ``root``
+    /// (``mpi_gatherv``)
+    ///
+    /// ``recv``, ``counts`` and ``displs`` are only referenced on root.
+    fn gather_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    );
+
+    /// Scatter ``send`` on ``root`` into ``recv`` of all processors
+    /// (``mpi_scatterv``)
+    ///
+    /// ``send``, ``counts`` and ``displs`` are only referenced on root.
+    fn scatter_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        counts: &[Count],
+        displs: &[Count],
+        recv: &mut [T],
+    );
+
+    /// Send block ``send_blocks[i]`` of ``send`` to processor *i* and
+    /// receive block ``recv_blocks[i]`` of ``recv`` from processor *i*,
+    /// without intermediate buffers (``mpi_alltoallw``)
+    ///
+    /// ``None`` marks an empty block. Each pair of send and recv
+    /// blocks must hold the same number of elements.
+    fn all_to_all_w<T: Element>(
+        &self,
+        send: &[T],
+        send_blocks: &[Option<Subarray>],
+        recv: &mut [T],
+        recv_blocks: &[Option<Subarray>],
+    );
+
+    /// Broadcast ``data`` from ``root`` to all processors (``mpi_bcast``)
+    fn broadcast<T: Element>(&self, root: Rank, data: &mut [T]);
+
+    /// Gather ``send`` of all processors into ``recv`` on all
+    /// processors (``mpi_allgather``)
+    fn all_gather<T: Element>(&self, send: &[T], recv: &mut [T]);
+
+    /// Gather ``send`` of processor *i* into block ``counts[i]``,
+    /// ``displs[i]`` of ``recv`` on all processors (``mpi_allgatherv``)
+    fn all_gather_varcount<T: Element>(
+        &self,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    );
+
+    /// Combine ``send`` of all lower ranks element-wise with the
+    /// associative ``op`` into ``recv`` (``mpi_exscan``)
+    ///
+    /// ``recv`` of rank 0 is left unchanged.
+    fn exclusive_scan<T, F>(&self, send: &[T], recv: &mut [T], op: F)
+    where
+        T: Element,
+        F: Fn(T, T) -> T + Sync;
+}
+
+impl Comm for UserCommunicator {
+    fn rank(&self) -> Rank {
+        Communicator::rank(self)
+    }
+
+    fn size(&self) -> Rank {
+        Communicator::size(self)
+    }
+
+    fn split(&self, color: Rank, key: Rank) -> Self {
+        self.split_by_color_with_key(Color::with_value(color), key)
+            .expect("Split must return a communicator for a defined color.")
+    }
+
+    fn all_to_all_varcount<T: Element>(
+        &self,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        let send_buffer = Partition::new(send, send_counts, send_displs);
+        let mut recv_buffer = PartitionMut::new(recv, recv_counts, recv_displs);
+        self.all_to_all_varcount_into(&send_buffer, &mut recv_buffer);
+    }
+
+    fn supported_exchanges(&self) -> &'static [Exchange] {
+        &Exchange::ALL[..3]
+    }
+
+    fn all_to_all_varcount_with<T: Element>(
+        &self,
+        exchange: Exchange,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        let sends = ranges(send_counts, send_displs);
+        let recvs = ranges(recv_counts, recv_displs);
+        match exchange {
+            Exchange::Collective => {
+                self.all_to_all_varcount(
+                    send,
+                    send_counts,
+                    send_displs,
+                    recv,
+                    recv_counts,
+                    recv_displs,
+                );
+            }
+            Exchange::Pairwise => {
+                let schedule =
+                    pairwise_schedule(Communicator::rank(self), Communicator::size(self));
+                for (dst, src) in schedule {
+                    let (d, s) = (to_index(dst), to_index(src));
+                    send_receive_into(
+                        &send[sends[d].clone()],
+                        &self.process_at_rank(dst),
+                        &mut recv[recvs[s].clone()],
+                        &self.process_at_rank(src),
+                    );
+                }
+            }
+            Exchange::Nonblocking => {
+                let chunks = split_disjoint_mut(recv, &recvs);
+                request::scope(|scope| {
+                    let mut guards = Vec::new();
+                    for (src, chunk) in chunks.into_iter().enumerate() {
+                        if !chunk.is_empty() {
+                            let process = self.process_at_rank(src.try_into().unwrap());
+                            let req = process.immediate_receive_into(scope, chunk);
+                            guards.push(WaitGuard::from(req));
+                        }
+                    }
+                    for (dst, range) in sends.iter().enumerate() {
+                        if !range.is_empty() {
+                            let process = self.process_at_rank(dst.try_into().unwrap());
+                            let req = process.immediate_send(scope, &send[range.clone()]);
+                            guards.push(WaitGuard::from(req));
+                        }
+                    }
+                    // Guards wait for completion when dropped
+                    drop(guards);
+                });
+            }
+            Exchange::Neighbor => {
+                panic!("Neighbor exchange needs an MpiComm, which caches its graph communicators.")
+            }
+        }
+    }
+
+    fn gather_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        let root_process = self.process_at_rank(root);
+        if Communicator::rank(self) == root {
+            let mut partition = PartitionMut::new(recv, counts, displs);
+            root_process.gather_varcount_into_root(send, &mut partition);
+        } else {
+            root_process.gather_varcount_into(send);
+        }
+    }
+
+    fn scatter_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        counts: &[Count],
+        displs: &[Count],
+        recv: &mut [T],
+    ) {
+        let root_process = self.process_at_rank(root);
+        if Communicator::rank(self) == root {
+            let partition = Partition::new(send, counts, displs);
+            root_process.scatter_varcount_into_root(&partition, recv);
+        } else {
+            root_process.scatter_varcount_into(recv);
+        }
+    }
+
+    fn all_to_all_w<T: Element>(
+        &self,
+        send: &[T],
+        send_blocks: &[Option<Subarray>],
+        recv: &mut [T],
+        recv_blocks: &[Option<Subarray>],
+    ) {
+        let size: usize = Communicator::size(self).try_into().unwrap();
+        assert_eq!(
+            send_blocks.len(),
+            size,
+            "Expect one send block per processor."
+        );
+        assert_eq!(
+            recv_blocks.len(),
+            size,
+            "Expect one recv block per processor."
+        );
+        // Check bounds, mpi does not
+        for block in send_blocks.iter().flatten() {
+            block.assert_fits(send.len());
+        }
+        for block in recv_blocks.iter().flatten() {
+            block.assert_fits(recv.len());
+        }
+        let send_types: Vec<Option<UserDatatype>> = send_blocks
+            .iter()
+            .map(|b| b.as_ref().map(Subarray::datatype::<T>))
+            .collect();
+        let recv_types: Vec<Option<UserDatatype>> = recv_blocks
+            .iter()
+            .map(|b| b.as_ref().map(Subarray::datatype::<T>))
+            .collect();
+        let counts = |types: &[Option<UserDatatype>]| -> Vec<Count> {
+            types.iter().map(|t| Count::from(t.is_some())).collect()
+        };
+        let raw = |types: &[Option<UserDatatype>]| -> Vec<ffi::MPI_Datatype> {
+            types
+                .iter()
+                .map(|t| {
+                    t.as_ref()
+                        .map_or(T::equivalent_datatype().as_raw(), AsRaw::as_raw)
+                })
+                .collect()
+        };
+        let (send_counts, send_raw) = (counts(&send_types), raw(&send_types));
+        let (recv_counts, recv_raw) = (counts(&recv_types), raw(&recv_types));
+        let displs = vec![0; size];
+        // Safety: blocks are inside of send and recv, see bounds check
+        unsafe {
+            ffi::MPI_Alltoallw(
+                send.as_ptr().cast(),
+                send_counts.as_ptr(),
+                displs.as_ptr(),
+                send_raw.as_ptr(),
+                recv.as_mut_ptr().cast(),
+                recv_counts.as_ptr(),
+                displs.as_ptr(),
+                recv_raw.as_ptr(),
+                self.as_raw(),
+            );
+        }
+    }
+
+    fn broadcast<T: Element>(&self, root: Rank, data: &mut [T]) {
+        self.process_at_rank(root).broadcast_into(data);
+    }
+
+    fn all_gather<T: Element>(&self, send: &[T], recv: &mut [T]) {
+        self.all_gather_into(send, recv);
+    }
+
+    fn all_gather_varcount<T: Element>(
+        &self,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        let mut partition = PartitionMut::new(recv, counts, displs);
+        self.all_gather_varcount_into(send, &mut partition);
+    }
+
+    fn exclusive_scan<T, F>(&self, send: &[T], recv: &mut [T], op: F)
+    where
+        T: Element,
+        F: Fn(T, T) -> T + Sync,
+    {
+        // Operands from lower ranks come first
+        let op = UserOperation::associative(|lower: DynBuffer, acc: DynBufferMut| {
+            let lower = lower.downcast::<T>().unwrap();
+            for (a, &l) in acc.downcast::<T>().unwrap().iter_mut().zip(lower) {
+                *a = op(l, *a);
+            }
+        });
+        self.exclusive_scan_into(send, recv, &op);
+    }
+}
+
+/// Mpi communicator which keeps the mpi environment alive
+///
+/// Shares ownership of the [`Universe`], so that mpi is finalised
+/// only after all communicators of the pencil distributions are
+/// freed. Pencil distributions which own this communicator can be
+/// stored and moved freely.
+///
+/// # Example
+/// Run with mpi, i.e. ``cargo mpirun --np 2 ...``
+/// ```ignore
+/// use pencil_decomp::comm::MpiComm;
+/// use pencil_decomp::Decomp3;
+/// use std::sync::Arc;
+///
+/// let universe = Arc::new(mpi::initialize().unwrap());
+/// let world = MpiComm::world(&universe);
+/// let decomp = Decomp3::from_comm(&world, [8, 8, 8], [2, 1], [false, false]);
+/// // universe may be dropped here, mpi is finalised with decomp
+/// drop(universe);
+/// ```
+pub struct MpiComm {
+    /// Must be freed before the universe is dropped
+    comm: UserCommunicator,
+    /// Graph communicators of neighbor exchanges, see [`Exchange::Neighbor`]
+    graphs: Mutex<Vec<GraphComm>>,
+    universe: Arc<Universe>,
+}
+
+impl MpiComm {
+    /// Duplicate of the world communicator of ``universe``
+    #[must_use]
+    pub fn world(universe: &Arc<Universe>) -> Self {
+        Self {
+            comm: universe.world().duplicate(),
+            graphs: Mutex::default(),
+            universe: Arc::clone(universe),
+        }
+    }
+
+    /// Underlying mpi communicator
+    #[must_use]
+    pub fn communicator(&self) -> &UserCommunicator {
+        &self.comm
+    }
+
+    /// ``mpi_neighbor_alltoallv`` on a graph topology connecting
+    /// processors with non-zero counts
+    ///
+    /// The graph communicator is created on first use and reused by
+    /// all later exchanges with the same neighbors, e.g. each
+    /// transpose between the same pair of pencils.
+    ///
+    /// # Panics
+    /// - Counts and displacements exceed ``send`` or ``recv``
+    /// - An mpi routine fails
+    fn neighbor_all_to_all_varcount<T: Element>(
+        &self,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        // Check bounds, mpi does not
+        for r in ranges(send_counts, send_displs) {
+            assert!(r.end <= send.len(), "Send buffer too small.");
+        }
+        for r in ranges(recv_counts, recv_displs) {
+            assert!(r.end <= recv.len(), "Recv buffer too small.");
+        }
+        // Neighbors, their counts and displacements
+        let neighbors = |counts: &[Count], displs: &[Count]| {
+            let mut ranks: Vec<Rank> = Vec::new();
+            let (mut c, mut d) = (Vec::new(), Vec::new());
+            for (r, (&count, &displ)) in counts.iter().zip(displs).enumerate() {
+                if count > 0 {
+                    ranks.push(r.try_into().unwrap());
+                    c.push(count);
+                    d.push(displ);
+                }
+            }
+            (ranks, c, d)
+        };
+        let (dsts, send_counts, send_displs) = neighbors(send_counts, send_displs);
+        let (srcs, recv_counts, recv_displs) = neighbors(recv_counts, recv_displs);
+
+        let mut graphs = self.graphs.lock().unwrap_or_else(PoisonError::into_inner);
+        let pos = graphs.iter().position(|g| g.srcs == srcs && g.dsts == dsts);
+        let graph = if let Some(pos) = pos {
+            &graphs[pos]
+        } else {
+            graphs.push(GraphComm::new(&self.comm, srcs, dsts));
+            graphs.last().unwrap()
+        };
+        let datatype = T::equivalent_datatype().as_raw();
+        // Safety: counts and displacements lie inside of send and recv
+        let code = unsafe {
+            ffi::MPI_Neighbor_alltoallv(
+                send.as_ptr().cast(),
+                send_counts.as_ptr(),
+                send_displs.as_ptr(),
+                datatype,
+                recv.as_mut_ptr().cast(),
+                recv_counts.as_ptr(),
+                recv_displs.as_ptr(),
+                datatype,
+                graph.raw,
+            )
+        };
+        check(code, "MPI_Neighbor_alltoallv");
+    }
+}
+
+/// Distributed graph communicator of a neighbor exchange, freed when
+/// dropped (``mpi_dist_graph_create_adjacent``)
+struct GraphComm {
+    /// Processors which send to current processor
+    srcs: Vec<Rank>,
+    /// Processors which receive from current processor
+    dsts: Vec<Rank>,
+    raw: ffi::MPI_Comm,
+}
+
+impl GraphComm {
+    /// Graph of ``comm`` connecting current processor with ``srcs``
+    /// and ``dsts``, collective over ``comm``
+    ///
+    /// # Panics
+    /// The mpi routine fails
+    fn new(comm: &UserCommunicator, srcs: Vec<Rank>, dsts: Vec<Rank>) -> Self {
+        let (src_weights, dst_weights) = (vec![1; srcs.len()], vec![1; dsts.len()]);
+        let mut raw = ffi::RSMPI_COMM_NULL;
+        // Safety: arrays hold indegree and outdegree entries
+        let code = unsafe {
+            ffi::MPI_Dist_graph_create_adjacent(
+                comm.as_raw(),
+                srcs.len().try_into().unwrap(),
+                srcs.as_ptr(),
+                src_weights.as_ptr(),
+                dsts.len().try_into().unwrap(),
+                dsts.as_ptr(),
+                dst_weights.as_ptr(),
+                ffi::RSMPI_INFO_NULL,
+                0,
+                &raw mut raw,
+            )
+        };
+        check(code, "MPI_Dist_graph_create_adjacent");
+        Self { srcs, dsts, raw }
+    }
+}
+
+impl Drop for GraphComm {
+    fn drop(&mut self) {
+        // Safety: raw is a valid communicator, which is freed only here
+        let code = unsafe { ffi::MPI_Comm_free(&raw mut self.raw) };
+        check(code, "MPI_Comm_free");
+    }
+}
+
+// Safety: mpi communicators are plain handles, all calls on a graph
+// communicator are serialised by the lock of ``MpiComm::graphs``
+unsafe impl Send for GraphComm {}
+
+/// Assert that an mpi ``routine`` returned ``MPI_SUCCESS``
+///
+/// # Panics
+/// ``code`` is an error code
+// Type of the error constants depends on the mpi implementation
+#[allow(clippy::cast_possible_wrap)]
+fn check(code: c_int, routine: &str) {
+    assert!(
+        code == ffi::MPI_SUCCESS as c_int,
+        "{routine} failed with error code {code}."
+    );
+}
+
+impl Comm for MpiComm {
+    fn rank(&self) -> Rank {
+        Comm::rank(&self.comm)
+    }
+
+    fn size(&self) -> Rank {
+        Comm::size(&self.comm)
+    }
+
+    fn split(&self, color: Rank, key: Rank) -> Self {
+        Self {
+            comm: Comm::split(&self.comm, color, key),
+            graphs: Mutex::default(),
+            universe: Arc::clone(&self.universe),
+        }
+    }
+
+    fn all_to_all_varcount<T: Element>(
+        &self,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        self.comm.all_to_all_varcount(
+            send,
+            send_counts,
+            send_displs,
+            recv,
+            recv_counts,
+            recv_displs,
+        );
+    }
+
+    fn supported_exchanges(&self) -> &'static [Exchange] {
+        &Exchange::ALL
+    }
+
+    fn all_to_all_varcount_with<T: Element>(
+        &self,
+        exchange: Exchange,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        if exchange == Exchange::Neighbor {
+            self.neighbor_all_to_all_varcount(
+                send,
+                send_counts,
+                send_displs,
+                recv,
+                recv_counts,
+                recv_displs,
+            );
+            return;
+        }
+        self.comm.all_to_all_varcount_with(
+            exchange,
+            send,
+            send_counts,
+            send_displs,
+            recv,
+            recv_counts,
+            recv_displs,
+        );
+    }
+
+    fn gather_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        self.comm.gather_varcount(root, send, recv, counts, displs);
+    }
+
+    fn scatter_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        counts: &[Count],
+        displs: &[Count],
+        recv: &mut [T],
+    ) {
+        self.comm.scatter_varcount(root, send, counts, displs, recv);
+    }
+
+    fn all_to_all_w<T: Element>(
+        &self,
+        send: &[T],
+        send_blocks: &[Option<Subarray>],
+        recv: &mut [T],
+        recv_blocks: &[Option<Subarray>],
+    ) {
+        self.comm.all_to_all_w(send, send_blocks, recv, recv_blocks);
+    }
+
+    fn broadcast<T: Element>(&self, root: Rank, data: &mut [T]) {
+        Comm::broadcast(&self.comm, root, data);
+    }
+
+    fn all_gather<T: Element>(&self, send: &[T], recv: &mut [T]) {
+        Comm::all_gather(&self.comm, send, recv);
+    }
+
+    fn all_gather_varcount<T: Element>(
+        &self,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        Comm::all_gather_varcount(&self.comm, send, recv, counts, displs);
+    }
+
+    fn exclusive_scan<T, F>(&self, send: &[T], recv: &mut [T], op: F)
+    where
+        T: Element,
+        F: Fn(T, T) -> T + Sync,
+    {
+        Comm::exclusive_scan(&self.comm, send, recv, op);
+    }
+}
+
+/// Assert that ``root`` is a rank of ``comm``
+///
+/// # Panics
+/// ``root`` is outside of the communicator
+pub(crate) fn assert_root<C: Comm>(comm: &C, root: Rank) {
+    let size = comm.size();
+    assert!(
+        0 <= root && root < size,
+        "Root rank {root} outside of communicator of size {size}."
+    );
+}
+
+/// Ranges of ``counts`` elements starting at ``displs``
+///
+/// # Panics
+/// i32 to usize conversion fails
+pub(crate) fn ranges(counts: &[Count], displs: &[Count]) -> Vec<Range<usize>> {
+    counts
+        .iter()
+        .zip(displs)
+        .map(|(&c, &d)| {
+            let (c, d) = (to_index(c), to_index(d));
+            d..d + c
+        })
+        .collect()
+}
+
+/// Convert count or rank to index
+///
+/// # Panics
+/// i32 to usize conversion fails
+fn to_index(x: i32) -> usize {
+    x.try_into().unwrap()
+}
+
+/// Peers ``(dst, src)`` of each round of [`Exchange::Pairwise`]
+///
+/// In round *i* processor ``rank`` sends to *rank + i* and receives
+/// from *rank - i*, so every pair of processors meets exactly once.
+pub(crate) fn pairwise_schedule(rank: Rank, size: Rank) -> impl Iterator<Item = (Rank, Rank)> {
+    (0..size).map(move |step| ((rank + step) % size, (rank - step + size) % size))
+}
+
+/// Split ``data`` into mutable chunks at ``ranges``, which may be
+/// empty or unordered, e.g. the receive blocks of an all-to-all exchange
+///
+/// # Panics
+/// Non-empty ranges overlap or exceed ``data``
+pub(crate) fn split_disjoint_mut<'a, T>(
+    data: &'a mut [T],
+    ranges: &[Range<usize>],
+) -> Vec<&'a mut [T]> {
+    // Empty ranges get empty chunks, wherever they start
+    let mut order: Vec<usize> = (0..ranges.len())
+        .filter(|&i| !ranges[i].is_empty())
+        .collect();
+    order.sort_by_key(|&i| ranges[i].start);
+    let mut chunks: Vec<Option<&'a mut [T]>> = ranges.iter().map(|_| None).collect();
+    let (mut rest, mut offset) = (data, 0);
+    for i in order {
+        let range = &ranges[i];
+        assert!(range.start >= offset, "Overlapping buffer ranges.");
+        let tail = std::mem::take(&mut rest)
+            .split_at_mut(range.start - offset)
+            .1;
+        let (chunk, tail) = tail.split_at_mut(range.len());
+        chunks[i] = Some(chunk);
+        (rest, offset) = (tail, range.end);
+    }
+    chunks.into_iter().map(Option::unwrap_or_default).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{pairwise_schedule, split_disjoint_mut};
+
+    #[test]
+    fn test_pairwise_schedule() {
+        let rounds: Vec<_> = pairwise_schedule(1, 3).collect();
+        assert_eq!(rounds, [(1, 1), (2, 0), (0, 2)]);
+        for size in 1..8 {
+            let rounds: Vec<Vec<(i32, i32)>> = (0..size)
+                .map(|r| pairwise_schedule(r, size).collect())
+                .collect();
+            for (rank, schedule) in (0..size).zip(&rounds) {
+                // Every processor is sent to and received from exactly once
+                let mut dsts: Vec<i32> = schedule.iter().map(|&(d, _)| d).collect();
+                let mut srcs: Vec<i32> = schedule.iter().map(|&(_, s)| s).collect();
+                dsts.sort_unstable();
+                srcs.sort_unstable();
+                assert_eq!(dsts, (0..size).collect::<Vec<_>>());
+                assert_eq!(srcs, (0..size).collect::<Vec<_>>());
+                // The destination of each round receives from this processor
+                for (step, &(dst, _)) in schedule.iter().enumerate() {
+                    assert_eq!(rounds[usize::try_from(dst).unwrap()][step].1, rank);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_split_disjoint_mut() {
+        let mut data = [0, 1, 2, 3, 4];
+        let chunks = split_disjoint_mut(&mut data, &[3..5, 0..1, 1..1]);
+        assert_eq!(chunks, [&mut [3, 4][..], &mut [0], &mut []]);
+
+        let mut data: Vec<i32> = (0..10).collect();
+        let chunks = split_disjoint_mut(&mut data, &[6..9, 0..2, 7..7, 9..10, 3..6, 20..20]);
+        let lens: Vec<usize> = chunks.iter().map(|c| c.len()).collect();
+        assert_eq!(lens, [3, 2, 0, 1, 3, 0]);
+        assert_eq!(chunks[0], [6, 7, 8]);
+        assert_eq!(chunks[4], [3, 4, 5]);
+        for chunk in chunks {
+            chunk.fill(-1);
+        }
+        // Gap at index 2 is untouched
+        assert_eq!(data, [-1, -1, 2, -1, -1, -1, -1, -1, -1, -1]);
+        assert!(split_disjoint_mut(&mut data, &[]).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Overlapping buffer ranges.")]
+    fn test_split_disjoint_mut_overlap() {
+        let mut data = [0; 4];
+        let _ = split_disjoint_mut(&mut data, &[0..2, 1..3]);
+    }
+}
@@ -1,26 +1,36 @@
 //! Pencil decomposition in two dimensions
-use crate::pencil::{gather_into_root_along_axis, scatter_along_axis, transpose, Pencil};
-use mpi::{environment::Universe, traits::Equivalence};
-use ndarray::{ArrayBase, Data, DataMut, Ix2};
+use crate::comm::{Comm, Element, Exchange, MpiComm};
+use crate::layout::DecompLayout;
+use crate::pencil::{
+    all_gather, gather_into_root_along_axis, gather_strided, scatter_along_axis, transpose, Pencil,
+};
+use crate::plan::Plan;
+use crate::render;
+use crate::spectral;
+use crate::stats::{Report, Stats};
+use mpi::environment::Universe;
+use mpi::topology::Rank;
+use ndarray::{ArrayBase, Data, DataMut, Ix2, Order};
 use num_traits::Zero;
+use std::sync::Arc;
 
-/// Pencil decomposition in three dimensions
-pub struct Decomp2<'a> {
-    /// Mpi universe
-    pub universe: &'a Universe,
+/// Pencil decomposition in two dimensions
+///
+/// *C* is the communication backend, see [`crate::comm`].
+pub struct Decomp2<C = MpiComm> {
     /// Total number of grid points [nx global, ny global]
     pub n_global: [usize; 2],
     /// Size, indices, counts and displacements for x-pencil
-    pub x_pencil: Pencil<'a, 2, 1>,
+    pub x_pencil: Pencil<2, 1, C>,
     /// Size, indices, counts and displacements for y-pencil
-    pub y_pencil: Pencil<'a, 2, 1>,
+    pub y_pencil: Pencil<2, 1, C>,
 }
 
-impl<'a> Decomp2<'a> {
+impl Decomp2 {
     /// Construct pencil distribution
     ///
     /// # Arguments
-    /// * `universe`     : Mpi Universe
+    /// * `universe`     : Mpi Universe, shared by the decomposition
     /// * `n_global`     : Total number of grid points [nx global, ny global]
     /// * `cart_ndims`   : Number of dimensions of cartesian grid
     /// * `cart_periodic`: Logical array of size ``cart_ndims`` specifying whether the grid is periodic
@@ -29,7 +39,7 @@ impl<'a> Decomp2<'a> {
     /// - Mismatch of *ndims* and number of processors
     #[must_use]
     pub fn new(
-        universe: &'a Universe,
+        universe: &Arc<Universe>,
         n_global: [usize; 2],
         cart_dims: [i32; 1],
         cart_periodic: [bool; 1],
@@ -37,12 +47,164 @@ impl<'a> Decomp2<'a> {
         let x_pencil = Pencil::new(universe, n_global, 0, cart_dims, cart_periodic);
         let y_pencil = Pencil::new(universe, n_global, 1, cart_dims, cart_periodic);
         Self {
-            universe,
             n_global,
             x_pencil,
             y_pencil,
         }
     }
+}
+
+impl<C: Comm> Decomp2<C> {
+    /// Construct pencil distribution on any communication backend
+    ///
+    /// # Arguments
+    /// * `comm`         : Communicator, see [`crate::comm`]
+    /// * `n_global`     : Total number of grid points [nx global, ny global]
+    /// * `cart_ndims`   : Number of dimensions of cartesian grid
+    /// * `cart_periodic`: Logical array of size ``cart_ndims`` specifying whether the grid is periodic
+    ///
+    /// # Panics
+    /// - Mismatch of *ndims* and number of processors
+    /// - Exchange from ``PENCIL_DECOMP_EXCHANGE`` is not supported by ``comm``
+    #[must_use]
+    pub fn from_comm(
+        comm: &C,
+        n_global: [usize; 2],
+        cart_dims: [i32; 1],
+        cart_periodic: [bool; 1],
+    ) -> Self {
+        let x_pencil = Pencil::from_comm(comm, n_global, 0, cart_dims, cart_periodic);
+        let y_pencil = Pencil::from_comm(comm, n_global, 1, cart_dims, cart_periodic);
+        Self {
+            n_global,
+            x_pencil,
+            y_pencil,
+        }
+    }
+
+    /// Construct decomposition from a stored layout, see [`crate::layout`]
+    ///
+    /// # Panics
+    /// - Mismatch of processor grid and number of processors
+    /// - Decomposition does not reproduce ``layout``
+    #[must_use]
+    pub fn from_layout(comm: &C, layout: &DecompLayout<2>) -> Self {
+        let cart_dims = layout.cart_dims().try_into().unwrap();
+        let cart_periodic = layout.cart_periodic().try_into().unwrap();
+        let decomp = Self::from_comm(comm, layout.n_global, cart_dims, cart_periodic);
+        decomp.check_layout(layout);
+        decomp
+    }
+
+    /// Communicator-free layout of all pencils on current processor,
+    /// see [`crate::layout`]
+    #[must_use]
+    pub fn layout(&self) -> DecompLayout<2> {
+        DecompLayout {
+            n_global: self.n_global,
+            pencils: vec![
+                self.x_pencil.layout().clone(),
+                self.y_pencil.layout().clone(),
+            ],
+        }
+    }
+
+    /// Check that this decomposition matches a ``stored`` layout,
+    /// e.g. at restart. The layout may come from any processor.
+    ///
+    /// # Panics
+    /// Decompositions differ
+    pub fn check_layout(&self, stored: &DecompLayout<2>) {
+        self.layout().assert_matches(stored);
+    }
+
+    /// Set preferred memory order of the arrays of all pencils,
+    /// see [`Pencil::with_order`]
+    ///
+    /// # Example
+    /// Transpose Fortran ordered arrays
+    /// ```
+    /// use ndarray::{Array2, Order, ShapeBuilder};
+    /// use pencil_decomp::{Decomp2, ThreadComm};
+    ///
+    /// ThreadComm::run(2, |comm| {
+    ///     let decomp = Decomp2::from_comm(&comm, [6, 5], [2], [false])
+    ///         .with_order(Order::ColumnMajor);
+    ///     let x_data = Array2::<f64>::zeros(decomp.x_pencil.shape().f());
+    ///     let mut y_data = Array2::<f64>::zeros(decomp.y_pencil.shape().f());
+    ///     decomp.transpose_x_to_y(&x_data, &mut y_data);
+    /// });
+    /// ```
+    #[must_use]
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.x_pencil = self.x_pencil.with_order(order);
+        self.y_pencil = self.y_pencil.with_order(order);
+        self
+    }
+
+    /// Set algorithm of all-to-all exchanges in transposes of all pencils,
+    /// see [`Pencil::with_exchange`]
+    #[must_use]
+    pub fn with_exchange(mut self, exchange: Exchange) -> Self {
+        self.x_pencil = self.x_pencil.with_exchange(exchange);
+        self.y_pencil = self.y_pencil.with_exchange(exchange);
+        self
+    }
+
+    /// Communicator-free plan of this decomposition with elements
+    /// of ``elem_size`` bytes, see [`crate::plan`]
+    ///
+    /// # Panics
+    /// Dimensionality mismatch of the cartesian topology
+    #[must_use]
+    pub fn plan(&self, elem_size: usize) -> Plan<2, 1> {
+        let cart_dims = self.x_pencil.cart_dims().try_into().unwrap();
+        Plan::new(self.n_global, cart_dims, elem_size)
+    }
+
+    /// Render the layout of all pencils as SVG, see [`render::svg`]
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        render::svg(&self.plan(1))
+    }
+
+    /// Render the layout of all pencils as ASCII, see [`render::ascii`]
+    #[must_use]
+    pub fn to_ascii(&self) -> String {
+        render::ascii(&self.plan(1))
+    }
+
+    /// Record statistics of communication routines of all pencils,
+    /// see [`crate::stats`]
+    #[must_use]
+    pub fn with_stats(mut self) -> Self {
+        let stats = Arc::new(Stats::new());
+        self.x_pencil = self.x_pencil.with_stats(Arc::clone(&stats));
+        self.y_pencil = self.y_pencil.with_stats(Arc::clone(&stats));
+        self
+    }
+
+    /// Statistics of communication routines on current processor,
+    /// if enabled
+    #[must_use]
+    pub fn stats(&self) -> Option<&Arc<Stats>> {
+        self.x_pencil.stats()
+    }
+
+    /// Summarize statistics over all processors and print them on rank 0
+    ///
+    /// Must be called by all processors.
+    ///
+    /// # Panics
+    /// Statistics are not enabled, see [`Self::with_stats`]
+    pub fn report(&self) -> Report {
+        let stats = self.stats().expect("Enable statistics with with_stats.");
+        let report = stats.report(&self.x_pencil.comm);
+        if self.x_pencil.comm.rank() == 0 {
+            println!("{report}");
+        }
+        report
+    }
 
     /// Transpose from x to y pencil
     ///
@@ -55,11 +217,11 @@ impl<'a> Decomp2<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.x_pencil, "transpose_x_to_y");
         assert_eq_shape!(rcv, self.y_pencil, "transpose_x_to_y");
-        transpose(&self.x_pencil, &self.y_pencil, snd, rcv, split_xy, merge_xy);
+        transpose(&self.x_pencil, &self.y_pencil, snd, rcv);
     }
 
     /// Transpose from y to x pencil
@@ -73,14 +235,14 @@ impl<'a> Decomp2<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.y_pencil, "transpose_y_to_x");
         assert_eq_shape!(rcv, self.x_pencil, "transpose_y_to_x");
-        transpose(&self.y_pencil, &self.x_pencil, snd, rcv, split_yx, merge_yx);
+        transpose(&self.y_pencil, &self.x_pencil, snd, rcv);
     }
 
-    /// Gather data from x-pencil to root processor
+    /// Gather data from x-pencil to root processor 0
     ///
     /// # Panics
     /// Shape mismatch of snd or rcv with send/recv pencil
@@ -88,15 +250,45 @@ impl<'a> Decomp2<'a> {
     where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
+    {
+        self.gather_x_to(0, snd, rcv);
+    }
+
+    /// Gather data from x-pencil to processor ``root``,
+    /// ``rcv`` is only used on ``root``, other processors may pass
+    /// an empty array
+    ///
+    /// # Panics
+    /// - Shape mismatch of snd with x-pencil, or of rcv with global grid on ``root``
+    /// - ``root`` is outside of the communicator
+    pub fn gather_x_to<S1, S2, T>(
+        &self,
+        root: Rank,
+        snd: &ArrayBase<S1, Ix2>,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.x_pencil, "gather_x");
-        assert_eq!(rcv.shape(), self.n_global);
+        if self.x_pencil.subcomm_along_axis(1).rank() == root {
+            assert_eq!(rcv.shape(), self.n_global, "Shape mismatch of rcv.");
+        }
 
-        gather_into_root_along_axis(&self.x_pencil, snd, rcv, 1, split_gather_x, merge_gather_x);
+        gather_into_root_along_axis(
+            &self.x_pencil,
+            root,
+            snd,
+            rcv,
+            1,
+            split_gather_x,
+            merge_gather_x,
+        );
     }
 
-    /// Gather data from y-pencil to root processor
+    /// Gather data from y-pencil to root processor 0
     ///
     /// # Panics
     /// Shape mismatch of snd or rcv with send/recv pencil
@@ -104,15 +296,45 @@ impl<'a> Decomp2<'a> {
     where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
+    {
+        self.gather_y_to(0, snd, rcv);
+    }
+
+    /// Gather data from y-pencil to processor ``root``,
+    /// ``rcv`` is only used on ``root``, other processors may pass
+    /// an empty array
+    ///
+    /// # Panics
+    /// - Shape mismatch of snd with y-pencil, or of rcv with global grid on ``root``
+    /// - ``root`` is outside of the communicator
+    pub fn gather_y_to<S1, S2, T>(
+        &self,
+        root: Rank,
+        snd: &ArrayBase<S1, Ix2>,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.y_pencil, "gather_y");
-        assert_eq!(rcv.shape(), self.n_global);
+        if self.y_pencil.subcomm_along_axis(0).rank() == root {
+            assert_eq!(rcv.shape(), self.n_global, "Shape mismatch of rcv.");
+        }
 
-        gather_into_root_along_axis(&self.y_pencil, snd, rcv, 0, split_gather_y, merge_gather_y);
+        gather_into_root_along_axis(
+            &self.y_pencil,
+            root,
+            snd,
+            rcv,
+            0,
+            split_gather_y,
+            merge_gather_y,
+        );
     }
 
-    /// Scatter data from root to x-pencil
+    /// Scatter data from root processor 0 to x-pencil
     ///
     /// # Panics
     /// Shape mismatch of snd or rcv with send/recv pencil
@@ -120,15 +342,45 @@ impl<'a> Decomp2<'a> {
     where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
-        assert_eq_shape!(rcv, self.x_pencil, "gather_x");
-        assert_eq!(snd.shape(), self.n_global);
+        self.scatter_x_from(0, snd, rcv);
+    }
 
-        scatter_along_axis(&self.x_pencil, snd, rcv, 1, split_gather_x, merge_gather_x);
+    /// Scatter data from processor ``root`` to x-pencil,
+    /// ``snd`` is only read on ``root``, other processors may pass
+    /// an empty array
+    ///
+    /// # Panics
+    /// - Shape mismatch of rcv with x-pencil, or of snd with global grid on ``root``
+    /// - ``root`` is outside of the communicator
+    pub fn scatter_x_from<S1, S2, T>(
+        &self,
+        root: Rank,
+        snd: &ArrayBase<S1, Ix2>,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(rcv, self.x_pencil, "scatter_x");
+        if self.x_pencil.subcomm_along_axis(1).rank() == root {
+            assert_eq!(snd.shape(), self.n_global, "Shape mismatch of snd.");
+        }
+
+        scatter_along_axis(
+            &self.x_pencil,
+            root,
+            snd,
+            rcv,
+            1,
+            split_gather_x,
+            merge_gather_x,
+        );
     }
 
-    /// Scatter data from root to y-pencil
+    /// Scatter data from root processor 0 to y-pencil
     ///
     /// # Panics
     /// Shape mismatch of snd or rcv with send/recv pencil
@@ -136,106 +388,183 @@ impl<'a> Decomp2<'a> {
     where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
+    {
+        self.scatter_y_from(0, snd, rcv);
+    }
+
+    /// Scatter data from processor ``root`` to y-pencil,
+    /// ``snd`` is only read on ``root``, other processors may pass
+    /// an empty array
+    ///
+    /// # Panics
+    /// - Shape mismatch of rcv with y-pencil, or of snd with global grid on ``root``
+    /// - ``root`` is outside of the communicator
+    pub fn scatter_y_from<S1, S2, T>(
+        &self,
+        root: Rank,
+        snd: &ArrayBase<S1, Ix2>,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
     {
-        assert_eq_shape!(rcv, self.y_pencil, "gather_y");
-        assert_eq!(snd.shape(), self.n_global);
+        assert_eq_shape!(rcv, self.y_pencil, "scatter_y");
+        if self.y_pencil.subcomm_along_axis(0).rank() == root {
+            assert_eq!(snd.shape(), self.n_global, "Shape mismatch of snd.");
+        }
 
-        scatter_along_axis(&self.y_pencil, snd, rcv, 0, split_gather_y, merge_gather_y);
+        scatter_along_axis(
+            &self.y_pencil,
+            root,
+            snd,
+            rcv,
+            0,
+            split_gather_y,
+            merge_gather_y,
+        );
     }
-}
 
-/// Prepare send buffer for `transpose_x_to_y`
-fn split_xy<S, T>(
-    data: &ArrayBase<S, Ix2>,
-    buf: &mut [T],
-    x_pencil: &Pencil<2, 1>,
-    y_pencil: &Pencil<2, 1>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    for (d, b) in data.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Gather data from x-pencil to all processors
+    ///
+    /// # Panics
+    /// Shape mismatch of snd with x-pencil or of rcv with global grid
+    pub fn allgather_x<S1, S2, T>(&self, snd: &ArrayBase<S1, Ix2>, rcv: &mut ArrayBase<S2, Ix2>)
+    where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(snd, self.x_pencil, "allgather_x");
+        assert_eq!(rcv.shape(), self.n_global);
+
+        all_gather(&self.x_pencil, snd, rcv);
     }
-}
 
-/// Prepare send buffer for `transpose_y_to_x`
-fn split_yx<S, T>(
-    data: &ArrayBase<S, Ix2>,
-    buf: &mut [T],
-    y_pencil: &Pencil<2, 1>,
-    x_pencil: &Pencil<2, 1>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut data_view = data.view();
-    data_view.swap_axes(0, 1);
-    for (d, b) in data_view.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Gather data from y-pencil to all processors
+    ///
+    /// # Panics
+    /// Shape mismatch of snd with y-pencil or of rcv with global grid
+    pub fn allgather_y<S1, S2, T>(&self, snd: &ArrayBase<S1, Ix2>, rcv: &mut ArrayBase<S2, Ix2>)
+    where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(snd, self.y_pencil, "allgather_y");
+        assert_eq!(rcv.shape(), self.n_global);
+
+        all_gather(&self.y_pencil, snd, rcv);
     }
-}
 
-/// Redistribute recv buffer for `transpose_x_to_y`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_xy<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix2>,
-    x_pencil: &Pencil<2, 1>,
-    y_pencil: &Pencil<2, 1>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut pos = 0;
-    let nprocs = x_pencil.nprocs_along_axis(1);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let j1 = x_pencil.dists[1].st_procs[proc];
-        let j2 = x_pencil.dists[1].en_procs[proc];
-        for i in 0..y_pencil.dists[0].sz {
-            for j in j1..=j2 {
-                data[[i, j]] = buf[pos];
-                pos += 1;
-            }
-        }
+    /// Gather every ``strides[i]``-th grid point along axis *i* of
+    /// data of ``pencil``, one of the pencils of this decomposition,
+    /// on processor ``root``
+    ///
+    /// Processors send only the strided grid points they hold, the
+    /// full field is never gathered. ``rcv`` has the shape
+    /// ``n_global[i].div_ceil(strides[i])`` and is only used on ``root``.
+    ///
+    /// # Example
+    /// Every fourth grid point along x of y-pencil data on processor 0
+    /// ```
+    /// use ndarray::Array2;
+    /// use pencil_decomp::{Decomp2, ThreadComm};
+    ///
+    /// ThreadComm::run(3, |comm| {
+    ///     let decomp = Decomp2::from_comm(&comm, [16, 9], [3], [false]);
+    ///     let y_data = Array2::<f64>::ones(decomp.y_pencil.shape());
+    ///     let mut coarse = Array2::zeros([4, 9]);
+    ///     decomp.gather_strided(0, &decomp.y_pencil, &y_data, [4, 1], &mut coarse);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// - ``pencil`` does not belong to this decomposition
+    /// - See [`crate::pencil::gather_strided`]
+    pub fn gather_strided<S1, S2, T>(
+        &self,
+        root: Rank,
+        pencil: &Pencil<2, 1, C>,
+        snd: &ArrayBase<S1, Ix2>,
+        strides: [usize; 2],
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        let own = self.pencil(pencil.axis_contig);
+        assert!(
+            pencil.layout() == own.layout(),
+            "Pencil does not belong to this decomposition."
+        );
+        gather_strided(pencil, root, snd, strides, rcv);
     }
-}
 
-/// Redistribute recv buffer for `transpose_y_to_x`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_yx<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix2>,
-    y_pencil: &Pencil<2, 1>,
-    x_pencil: &Pencil<2, 1>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut pos = 0;
-    let nprocs = y_pencil.nprocs_along_axis(0);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let i1 = y_pencil.dists[0].st_procs[proc];
-        let i2 = y_pencil.dists[0].en_procs[proc];
-        for j in 0..x_pencil.dists[1].sz {
-            for i in i1..=i2 {
-                data[[i, j]] = buf[pos];
-                pos += 1;
-            }
-        }
+    /// Pad spectral data ``snd`` of the pencil contiguous in ``snd_axis``
+    /// with zeros into ``rcv`` of the pencil contiguous in ``rcv_axis``
+    /// of the ``large`` decomposition, see [`crate::spectral::pad`]
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray::Array2;
+    /// use pencil_decomp::{Decomp2, ThreadComm};
+    ///
+    /// ThreadComm::run(2, |comm| {
+    ///     let small = Decomp2::from_comm(&comm, [8, 6], [2], [false]);
+    ///     let large = Decomp2::from_comm(&comm, [12, 9], [2], [false]);
+    ///     let y_hat = Array2::<f64>::ones(small.y_pencil.shape());
+    ///     let mut x_hat = Array2::zeros(large.x_pencil.shape());
+    ///     small.pad(1, &y_hat, &large, 0, &mut x_hat);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// - ``snd_axis`` or ``rcv_axis`` > 1
+    /// - See [`crate::spectral::pad`]
+    pub fn pad<S1, S2, T>(
+        &self,
+        snd_axis: usize,
+        snd: &ArrayBase<S1, Ix2>,
+        large: &Self,
+        rcv_axis: usize,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        spectral::pad(self.pencil(snd_axis), snd, large.pencil(rcv_axis), rcv);
+    }
+
+    /// Truncate spectral data ``snd`` of the pencil contiguous in
+    /// ``snd_axis`` into ``rcv`` of the pencil contiguous in ``rcv_axis``
+    /// of the ``small`` decomposition, the inverse of [`Self::pad`]
+    ///
+    /// # Panics
+    /// - ``snd_axis`` or ``rcv_axis`` > 1
+    /// - See [`crate::spectral::truncate`]
+    pub fn truncate<S1, S2, T>(
+        &self,
+        snd_axis: usize,
+        snd: &ArrayBase<S1, Ix2>,
+        small: &Self,
+        rcv_axis: usize,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        spectral::truncate(self.pencil(snd_axis), snd, small.pencil(rcv_axis), rcv);
+    }
+
+    /// Pencil contiguous in ``axis``
+    fn pencil(&self, axis: usize) -> &Pencil<2, 1, C> {
+        assert!(axis < 2, "Axis {axis} outside array dimensions 2.");
+        [&self.x_pencil, &self.y_pencil][axis]
     }
 }
 
@@ -1,28 +1,37 @@
 //! Pencil decomposition in three dimensions
-use crate::pencil::{transpose, Pencil};
-use mpi::{environment::Universe, traits::Equivalence};
-use ndarray::{ArrayBase, Data, DataMut, Ix3};
+use crate::comm::{Comm, Element, Exchange, MpiComm};
+use crate::layout::DecompLayout;
+use crate::pencil::{all_gather, gather_strided, gather_subvolume, transpose, transpose_w, Pencil};
+use crate::plan::Plan;
+use crate::render;
+use crate::spectral;
+use crate::stats::{Report, Stats};
+use mpi::environment::Universe;
+use mpi::topology::Rank;
+use ndarray::{ArrayBase, Axis, Data, DataMut, Ix2, Ix3, Order};
 use num_traits::Zero;
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Pencil decomposition in three dimensions
-pub struct Decomp3<'a> {
-    /// Mpi universe
-    pub universe: &'a Universe,
-    /// Total number of grid points [nx global, ny global, nz_global]
+///
+/// *C* is the communication backend, see [`crate::comm`].
+pub struct Decomp3<C = MpiComm> {
+    /// Total number of grid points [nx global, ny global, nz global]
     pub n_global: [usize; 3],
     /// Size, indices, counts and displacements for x-pencil
-    pub x_pencil: Pencil<'a, 3, 2>,
+    pub x_pencil: Pencil<3, 2, C>,
     /// Size, indices, counts and displacements for y-pencil
-    pub y_pencil: Pencil<'a, 3, 2>,
+    pub y_pencil: Pencil<3, 2, C>,
     /// Size, indices, counts and displacements for z-pencil
-    pub z_pencil: Pencil<'a, 3, 2>,
+    pub z_pencil: Pencil<3, 2, C>,
 }
 
-impl<'a> Decomp3<'a> {
+impl Decomp3 {
     /// Construct pencil distribution
     ///
     /// # Arguments
-    /// * `universe`     : Mpi Universe
+    /// * `universe`     : Mpi Universe, shared by the decomposition
     /// * `n_global`     : Total number of grid points [nx global, ny global]
     /// * `cart_ndims`   : Number of dimensions of cartesian grid
     /// * `cart_periodic`: Logical array of size ``cart_ndims`` specifying whether the grid is periodic
@@ -31,7 +40,7 @@ impl<'a> Decomp3<'a> {
     /// - Mismatch of *ndims* and number of processors
     #[must_use]
     pub fn new(
-        universe: &'a Universe,
+        universe: &Arc<Universe>,
         n_global: [usize; 3],
         cart_dims: [i32; 2],
         cart_periodic: [bool; 2],
@@ -40,13 +49,203 @@ impl<'a> Decomp3<'a> {
         let y_pencil = Pencil::new(universe, n_global, 1, cart_dims, cart_periodic);
         let z_pencil = Pencil::new(universe, n_global, 2, cart_dims, cart_periodic);
         Self {
-            universe,
             n_global,
             x_pencil,
             y_pencil,
             z_pencil,
         }
     }
+}
+
+impl<C: Comm> Decomp3<C> {
+    /// Construct pencil distribution on any communication backend
+    ///
+    /// # Arguments
+    /// * `comm`         : Communicator, see [`crate::comm`]
+    /// * `n_global`     : Total number of grid points [nx global, ny global]
+    /// * `cart_ndims`   : Number of dimensions of cartesian grid
+    /// * `cart_periodic`: Logical array of size ``cart_ndims`` specifying whether the grid is periodic
+    ///
+    /// # Panics
+    /// - Mismatch of *ndims* and number of processors
+    /// - Exchange from ``PENCIL_DECOMP_EXCHANGE`` is not supported by ``comm``
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(4, |comm| {
+    ///     let decomp3 = Decomp3::from_comm(&comm, [6, 7, 9], [2, 2], [false, false]);
+    ///     assert_eq!(decomp3.x_pencil.shape_global(), [6, 7, 9]);
+    /// });
+    /// ```
+    #[must_use]
+    pub fn from_comm(
+        comm: &C,
+        n_global: [usize; 3],
+        cart_dims: [i32; 2],
+        cart_periodic: [bool; 2],
+    ) -> Self {
+        let x_pencil = Pencil::from_comm(comm, n_global, 0, cart_dims, cart_periodic);
+        let y_pencil = Pencil::from_comm(comm, n_global, 1, cart_dims, cart_periodic);
+        let z_pencil = Pencil::from_comm(comm, n_global, 2, cart_dims, cart_periodic);
+        Self {
+            n_global,
+            x_pencil,
+            y_pencil,
+            z_pencil,
+        }
+    }
+
+    /// Construct decomposition from a stored layout, see [`crate::layout`]
+    ///
+    /// # Panics
+    /// - Mismatch of processor grid and number of processors
+    /// - Decomposition does not reproduce ``layout``
+    #[must_use]
+    pub fn from_layout(comm: &C, layout: &DecompLayout<3>) -> Self {
+        let cart_dims = layout.cart_dims().try_into().unwrap();
+        let cart_periodic = layout.cart_periodic().try_into().unwrap();
+        let decomp = Self::from_comm(comm, layout.n_global, cart_dims, cart_periodic);
+        decomp.check_layout(layout);
+        decomp
+    }
+
+    /// Communicator-free layout of all pencils on current processor,
+    /// see [`crate::layout`]
+    #[must_use]
+    pub fn layout(&self) -> DecompLayout<3> {
+        DecompLayout {
+            n_global: self.n_global,
+            pencils: vec![
+                self.x_pencil.layout().clone(),
+                self.y_pencil.layout().clone(),
+                self.z_pencil.layout().clone(),
+            ],
+        }
+    }
+
+    /// Pencil contiguous in ``axis``
+    fn pencil(&self, axis: usize) -> &Pencil<3, 2, C> {
+        assert!(axis < 3, "Axis {axis} outside array dimensions 3.");
+        [&self.x_pencil, &self.y_pencil, &self.z_pencil][axis]
+    }
+
+    /// Check that ``pencil`` has the layout of the pencil of this
+    /// decomposition with the same contiguous axis
+    fn assert_own_pencil(&self, pencil: &Pencil<3, 2, C>) {
+        let own = self.pencil(pencil.axis_contig);
+        assert!(
+            pencil.layout() == own.layout(),
+            "Pencil does not belong to this decomposition."
+        );
+    }
+
+    /// Check that this decomposition matches a ``stored`` layout,
+    /// e.g. at restart. The layout may come from any processor.
+    ///
+    /// # Panics
+    /// Decompositions differ
+    pub fn check_layout(&self, stored: &DecompLayout<3>) {
+        self.layout().assert_matches(stored);
+    }
+
+    /// Set preferred memory order of the arrays of all pencils,
+    /// see [`Pencil::with_order`]
+    ///
+    /// # Example
+    /// Transpose Fortran ordered arrays
+    /// ```
+    /// use ndarray::{Array3, Order, ShapeBuilder};
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(2, |comm| {
+    ///     let decomp = Decomp3::from_comm(&comm, [6, 5, 4], [2, 1], [false, false])
+    ///         .with_order(Order::ColumnMajor);
+    ///     let x_data = Array3::<f64>::zeros(decomp.x_pencil.shape().f());
+    ///     let mut y_data = Array3::<f64>::zeros(decomp.y_pencil.shape().f());
+    ///     decomp.transpose_x_to_y(&x_data, &mut y_data);
+    /// });
+    /// ```
+    #[must_use]
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.x_pencil = self.x_pencil.with_order(order);
+        self.y_pencil = self.y_pencil.with_order(order);
+        self.z_pencil = self.z_pencil.with_order(order);
+        self
+    }
+
+    /// Set algorithm of all-to-all exchanges in transposes of all pencils,
+    /// see [`Pencil::with_exchange`]
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::comm::Exchange;
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(2, |comm| {
+    ///     let decomp = Decomp3::from_comm(&comm, [6, 5, 4], [2, 1], [false, false])
+    ///         .with_exchange(Exchange::Pairwise);
+    ///     assert_eq!(decomp.x_pencil.exchange, Exchange::Pairwise);
+    /// });
+    /// ```
+    #[must_use]
+    pub fn with_exchange(mut self, exchange: Exchange) -> Self {
+        self.x_pencil = self.x_pencil.with_exchange(exchange);
+        self.y_pencil = self.y_pencil.with_exchange(exchange);
+        self.z_pencil = self.z_pencil.with_exchange(exchange);
+        self
+    }
+
+    /// Communicator-free plan of this decomposition with elements
+    /// of ``elem_size`` bytes, see [`crate::plan`]
+    ///
+    /// # Panics
+    /// Dimensionality mismatch of the cartesian topology
+    #[must_use]
+    pub fn plan(&self, elem_size: usize) -> Plan<3, 2> {
+        let cart_dims = self.x_pencil.cart_dims().try_into().unwrap();
+        Plan::new(self.n_global, cart_dims, elem_size)
+    }
+
+    /// Render the layout of all pencils as SVG, see [`render::svg`]
+    #[must_use]
+    pub fn to_svg(&self) -> String {
+        render::svg(&self.plan(1))
+    }
+
+    /// Record statistics of communication routines of all pencils,
+    /// see [`crate::stats`]
+    #[must_use]
+    pub fn with_stats(mut self) -> Self {
+        let stats = Arc::new(Stats::new());
+        self.x_pencil = self.x_pencil.with_stats(Arc::clone(&stats));
+        self.y_pencil = self.y_pencil.with_stats(Arc::clone(&stats));
+        self.z_pencil = self.z_pencil.with_stats(Arc::clone(&stats));
+        self
+    }
+
+    /// Statistics of communication routines on current processor,
+    /// if enabled
+    #[must_use]
+    pub fn stats(&self) -> Option<&Arc<Stats>> {
+        self.x_pencil.stats()
+    }
+
+    /// Summarize statistics over all processors and print them on rank 0
+    ///
+    /// Must be called by all processors.
+    ///
+    /// # Panics
+    /// Statistics are not enabled, see [`Self::with_stats`]
+    pub fn report(&self) -> Report {
+        let stats = self.stats().expect("Enable statistics with with_stats.");
+        let report = stats.report(&self.x_pencil.comm);
+        if self.x_pencil.comm.rank() == 0 {
+            println!("{report}");
+        }
+        report
+    }
 
     /// Transpose from x to y pencil
     ///
@@ -59,11 +258,11 @@ impl<'a> Decomp3<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.x_pencil, "transpose_x_to_y");
         assert_eq_shape!(rcv, self.y_pencil, "transpose_x_to_y");
-        transpose(&self.x_pencil, &self.y_pencil, snd, rcv, split_xy, merge_xy);
+        transpose(&self.x_pencil, &self.y_pencil, snd, rcv);
     }
 
     /// Transpose from y to x pencil
@@ -77,11 +276,11 @@ impl<'a> Decomp3<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.y_pencil, "transpose_y_to_x");
         assert_eq_shape!(rcv, self.x_pencil, "transpose_y_to_x");
-        transpose(&self.y_pencil, &self.x_pencil, snd, rcv, split_yx, merge_yx);
+        transpose(&self.y_pencil, &self.x_pencil, snd, rcv);
     }
 
     /// Transpose from y to z pencil
@@ -95,11 +294,11 @@ impl<'a> Decomp3<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.y_pencil, "transpose_y_to_z");
         assert_eq_shape!(rcv, self.z_pencil, "transpose_y_to_z");
-        transpose(&self.y_pencil, &self.z_pencil, snd, rcv, split_yz, merge_yz);
+        transpose(&self.y_pencil, &self.z_pencil, snd, rcv);
     }
 
     /// Transpose from z to y pencil
@@ -113,208 +312,267 @@ impl<'a> Decomp3<'a> {
     ) where
         S1: Data<Elem = T>,
         S2: DataMut<Elem = T>,
-        T: Zero + Clone + Copy + Equivalence,
+        T: Zero + Element,
     {
         assert_eq_shape!(snd, self.z_pencil, "transpose_z_to_y");
         assert_eq_shape!(rcv, self.y_pencil, "transpose_z_to_y");
-        transpose(&self.z_pencil, &self.y_pencil, snd, rcv, split_zy, merge_zy);
+        transpose(&self.z_pencil, &self.y_pencil, snd, rcv);
     }
-}
 
-/// Prepare send buffer for `transpose_x_to_y`
-fn split_xy<S, T>(
-    data: &ArrayBase<S, Ix3>,
-    buf: &mut [T],
-    x_pencil: &Pencil<3, 2>,
-    y_pencil: &Pencil<3, 2>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    for (d, b) in data.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Transpose from x to z pencil in one step
+    ///
+    /// The pencils split the y axis over different processors,
+    /// see [`transpose_w`].
+    ///
+    /// # Panics
+    /// Shape mismatch of snd or rcv with send/recv pencil
+    pub fn transpose_x_to_z<S1, S2, T>(
+        &self,
+        snd: &ArrayBase<S1, Ix3>,
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Element,
+    {
+        assert_eq_shape!(snd, self.x_pencil, "transpose_x_to_z");
+        assert_eq_shape!(rcv, self.z_pencil, "transpose_x_to_z");
+        transpose_w(&self.x_pencil, &self.z_pencil, snd, rcv);
     }
-}
 
-/// Prepare send buffer for `transpose_y_to_x`
-fn split_yx<S, T>(
-    data: &ArrayBase<S, Ix3>,
-    buf: &mut [T],
-    y_pencil: &Pencil<3, 2>,
-    x_pencil: &Pencil<3, 2>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut data_view = data.view();
-    data_view.swap_axes(0, 1);
-    for (d, b) in data_view.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Transpose from z to x pencil in one step
+    ///
+    /// The pencils split the y axis over different processors,
+    /// see [`transpose_w`].
+    ///
+    /// # Panics
+    /// Shape mismatch of snd or rcv with send/recv pencil
+    pub fn transpose_z_to_x<S1, S2, T>(
+        &self,
+        snd: &ArrayBase<S1, Ix3>,
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Element,
+    {
+        assert_eq_shape!(snd, self.z_pencil, "transpose_z_to_x");
+        assert_eq_shape!(rcv, self.x_pencil, "transpose_z_to_x");
+        transpose_w(&self.z_pencil, &self.x_pencil, snd, rcv);
     }
-}
 
-/// Prepare send buffer for `transpose_y_to_z`
-fn split_yz<S, T>(
-    data: &ArrayBase<S, Ix3>,
-    buf: &mut [T],
-    y_pencil: &Pencil<3, 2>,
-    z_pencil: &Pencil<3, 2>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(y_pencil.axis_contig == 1);
-    assert!(z_pencil.axis_contig == 2);
-    let mut data_view = data.view();
-    data_view.swap_axes(0, 1);
-    for (d, b) in data_view.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Gather data from x-pencil to all processors
+    ///
+    /// # Panics
+    /// Shape mismatch of snd with x-pencil or of rcv with global grid
+    pub fn allgather_x<S1, S2, T>(&self, snd: &ArrayBase<S1, Ix3>, rcv: &mut ArrayBase<S2, Ix3>)
+    where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(snd, self.x_pencil, "allgather_x");
+        assert_eq!(rcv.shape(), self.n_global);
+
+        all_gather(&self.x_pencil, snd, rcv);
     }
-}
 
-/// Prepare send buffer for `transpose_z_to_y`
-fn split_zy<S, T>(
-    data: &ArrayBase<S, Ix3>,
-    buf: &mut [T],
-    z_pencil: &Pencil<3, 2>,
-    y_pencil: &Pencil<3, 2>,
-) where
-    S: Data<Elem = T>,
-    T: Copy,
-{
-    assert!(y_pencil.axis_contig == 1);
-    assert!(z_pencil.axis_contig == 2);
-    let mut data_view = data.view();
-    data_view.swap_axes(0, 2);
-    for (d, b) in data_view.iter().zip(buf.iter_mut()) {
-        *b = *d;
+    /// Gather data from y-pencil to all processors
+    ///
+    /// # Panics
+    /// Shape mismatch of snd with y-pencil or of rcv with global grid
+    pub fn allgather_y<S1, S2, T>(&self, snd: &ArrayBase<S1, Ix3>, rcv: &mut ArrayBase<S2, Ix3>)
+    where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(snd, self.y_pencil, "allgather_y");
+        assert_eq!(rcv.shape(), self.n_global);
+
+        all_gather(&self.y_pencil, snd, rcv);
     }
-}
 
-/// Redistribute recv buffer for `transpose_x_to_y`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_xy<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix3>,
-    x_pencil: &Pencil<3, 2>,
-    y_pencil: &Pencil<3, 2>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut pos = 0;
-    let nprocs = x_pencil.nprocs_along_axis(1);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let j1 = x_pencil.dists[1].st_procs[proc];
-        let j2 = x_pencil.dists[1].en_procs[proc];
-        for i in 0..y_pencil.dists[0].sz {
-            for j in j1..=j2 {
-                for k in 0..y_pencil.dists[2].sz {
-                    data[[i, j, k]] = buf[pos];
-                    pos += 1;
-                }
-            }
-        }
+    /// Gather data from z-pencil to all processors
+    ///
+    /// # Panics
+    /// Shape mismatch of snd with z-pencil or of rcv with global grid
+    pub fn allgather_z<S1, S2, T>(&self, snd: &ArrayBase<S1, Ix3>, rcv: &mut ArrayBase<S2, Ix3>)
+    where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert_eq_shape!(snd, self.z_pencil, "allgather_z");
+        assert_eq!(rcv.shape(), self.n_global);
+
+        all_gather(&self.z_pencil, snd, rcv);
     }
-}
 
-/// Redistribute recv buffer for `transpose_y_to_x`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_yx<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix3>,
-    y_pencil: &Pencil<3, 2>,
-    x_pencil: &Pencil<3, 2>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(x_pencil.axis_contig == 0);
-    assert!(y_pencil.axis_contig == 1);
-    let mut pos = 0;
-    let nprocs = y_pencil.nprocs_along_axis(0);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let i1 = y_pencil.dists[0].st_procs[proc];
-        let i2 = y_pencil.dists[0].en_procs[proc];
-        for j in 0..x_pencil.dists[1].sz {
-            for i in i1..=i2 {
-                for k in 0..x_pencil.dists[2].sz {
-                    data[[i, j, k]] = buf[pos];
-                    pos += 1;
-                }
-            }
-        }
+    /// Gather the global index ``ranges`` of data of ``pencil``,
+    /// one of the pencils of this decomposition, on processor ``root``
+    ///
+    /// Only processors which hold part of ``ranges`` send data.
+    /// ``rcv`` has the shape of ``ranges`` and is only used on ``root``.
+    ///
+    /// # Example
+    /// Gather box [2..4, 0..3, 5..9] of x-pencil data on processor 1
+    /// ```
+    /// use ndarray::Array3;
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(4, |comm| {
+    ///     let decomp = Decomp3::from_comm(&comm, [6, 7, 9], [2, 2], [false, false]);
+    ///     let x_data = Array3::<f64>::zeros(decomp.x_pencil.shape());
+    ///     let mut sub = Array3::zeros([2, 3, 4]);
+    ///     decomp.gather_subvolume(1, &decomp.x_pencil, &x_data, &[2..4, 0..3, 5..9], &mut sub);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// - ``pencil`` does not belong to this decomposition
+    /// - See [`crate::pencil::gather_subvolume`]
+    pub fn gather_subvolume<S1, S2, T>(
+        &self,
+        root: Rank,
+        pencil: &Pencil<3, 2, C>,
+        snd: &ArrayBase<S1, Ix3>,
+        ranges: &[Range<usize>; 3],
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        self.assert_own_pencil(pencil);
+        gather_subvolume(pencil, root, snd, ranges, rcv);
     }
-}
 
-/// Redistribute recv buffer for `transpose_y_to_z`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_yz<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix3>,
-    y_pencil: &Pencil<3, 2>,
-    z_pencil: &Pencil<3, 2>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(y_pencil.axis_contig == 1);
-    assert!(z_pencil.axis_contig == 2);
-    let mut pos = 0;
-    let nprocs = y_pencil.nprocs_along_axis(2);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let k1 = y_pencil.dists[2].st_procs[proc];
-        let k2 = y_pencil.dists[2].en_procs[proc];
-        for j in 0..z_pencil.dists[1].sz {
-            for i in 0..z_pencil.dists[0].sz {
-                for k in k1..=k2 {
-                    data[[i, j, k]] = buf[pos];
-                    pos += 1;
-                }
-            }
-        }
+    /// Gather the plane ``index`` normal to ``axis`` of data of
+    /// ``pencil`` on processor ``root``
+    ///
+    /// ``rcv`` has the global shape without ``axis`` and is only
+    /// used on ``root``, see [`Decomp3::gather_subvolume`].
+    ///
+    /// # Panics
+    /// - ``axis`` > 2 or ``index`` outside of the global grid
+    /// - See [`Decomp3::gather_subvolume`]
+    pub fn gather_slice<S1, S2, T>(
+        &self,
+        root: Rank,
+        pencil: &Pencil<3, 2, C>,
+        snd: &ArrayBase<S1, Ix3>,
+        axis: usize,
+        index: usize,
+        rcv: &mut ArrayBase<S2, Ix2>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        assert!(axis < 3, "Axis {axis} outside array dimensions 3.");
+        let mut ranges = self.n_global.map(|n| 0..n);
+        ranges[axis] = index..index + 1;
+        let mut plane = rcv.view_mut().insert_axis(Axis(axis));
+        self.gather_subvolume(root, pencil, snd, &ranges, &mut plane);
     }
-}
 
-/// Redistribute recv buffer for `transpose_z_to_y`
-///
-/// # Panics
-/// i32 to usize conversion fails
-fn merge_zy<S, T>(
-    buf: &[T],
-    data: &mut ArrayBase<S, Ix3>,
-    z_pencil: &Pencil<3, 2>,
-    y_pencil: &Pencil<3, 2>,
-) where
-    S: DataMut<Elem = T>,
-    T: Copy,
-{
-    assert!(y_pencil.axis_contig == 1);
-    assert!(z_pencil.axis_contig == 2);
-    let mut pos = 0;
-    let nprocs = z_pencil.nprocs_along_axis(1);
-    for proc in 0..nprocs.try_into().unwrap() {
-        let j1 = z_pencil.dists[1].st_procs[proc];
-        let j2 = z_pencil.dists[1].en_procs[proc];
-        for k in 0..y_pencil.dists[2].sz {
-            for j in j1..=j2 {
-                for i in 0..y_pencil.dists[0].sz {
-                    data[[i, j, k]] = buf[pos];
-                    pos += 1;
-                }
-            }
-        }
+    /// Gather every ``strides[i]``-th grid point along axis *i* of
+    /// data of ``pencil``, one of the pencils of this decomposition,
+    /// on processor ``root``
+    ///
+    /// Processors send only the strided grid points they hold, the
+    /// full field is never gathered. ``rcv`` has the shape
+    /// ``n_global[i].div_ceil(strides[i])`` and is only used on ``root``.
+    ///
+    /// # Example
+    /// Every second grid point of z-pencil data on processor 0
+    /// ```
+    /// use ndarray::Array3;
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(4, |comm| {
+    ///     let decomp = Decomp3::from_comm(&comm, [8, 7, 9], [2, 2], [false, false]);
+    ///     let z_data = Array3::<f64>::ones(decomp.z_pencil.shape());
+    ///     let mut coarse = Array3::zeros([4, 4, 5]);
+    ///     decomp.gather_strided(0, &decomp.z_pencil, &z_data, [2, 2, 2], &mut coarse);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// - ``pencil`` does not belong to this decomposition
+    /// - See [`crate::pencil::gather_strided`]
+    pub fn gather_strided<S1, S2, T>(
+        &self,
+        root: Rank,
+        pencil: &Pencil<3, 2, C>,
+        snd: &ArrayBase<S1, Ix3>,
+        strides: [usize; 3],
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        self.assert_own_pencil(pencil);
+        gather_strided(pencil, root, snd, strides, rcv);
+    }
+
+    /// Pad spectral data ``snd`` of the pencil contiguous in ``snd_axis``
+    /// with zeros into ``rcv`` of the pencil contiguous in ``rcv_axis``
+    /// of the ``large`` decomposition, see [`crate::spectral::pad`]
+    ///
+    /// # Example
+    /// ```
+    /// use ndarray::Array3;
+    /// use pencil_decomp::{Decomp3, ThreadComm};
+    ///
+    /// ThreadComm::run(4, |comm| {
+    ///     let small = Decomp3::from_comm(&comm, [8, 8, 8], [2, 2], [false, false]);
+    ///     let large = Decomp3::from_comm(&comm, [12, 12, 12], [2, 2], [false, false]);
+    ///     let z_hat = Array3::<f64>::ones(small.z_pencil.shape());
+    ///     let mut x_hat = Array3::zeros(large.x_pencil.shape());
+    ///     small.pad(2, &z_hat, &large, 0, &mut x_hat);
+    /// });
+    /// ```
+    ///
+    /// # Panics
+    /// - ``snd_axis`` or ``rcv_axis`` > 2
+    /// - See [`crate::spectral::pad`]
+    pub fn pad<S1, S2, T>(
+        &self,
+        snd_axis: usize,
+        snd: &ArrayBase<S1, Ix3>,
+        large: &Self,
+        rcv_axis: usize,
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        spectral::pad(self.pencil(snd_axis), snd, large.pencil(rcv_axis), rcv);
+    }
+
+    /// Truncate spectral data ``snd`` of the pencil contiguous in
+    /// ``snd_axis`` into ``rcv`` of the pencil contiguous in ``rcv_axis``
+    /// of the ``small`` decomposition, the inverse of [`Self::pad`]
+    ///
+    /// # Panics
+    /// - ``snd_axis`` or ``rcv_axis`` > 2
+    /// - See [`crate::spectral::truncate`]
+    pub fn truncate<S1, S2, T>(
+        &self,
+        snd_axis: usize,
+        snd: &ArrayBase<S1, Ix3>,
+        small: &Self,
+        rcv_axis: usize,
+        rcv: &mut ArrayBase<S2, Ix3>,
+    ) where
+        S1: Data<Elem = T>,
+        S2: DataMut<Elem = T>,
+        T: Zero + Element,
+    {
+        spectral::truncate(self.pencil(snd_axis), snd, small.pencil(rcv_axis), rcv);
     }
 }
@@ -4,9 +4,11 @@
 //! of all participating processors along a single, possibly
 //! split, dimension.
 #![allow(clippy::similar_names)]
+use std::ops::Range;
 
 /// Distribute Grid points to processors.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Distribution {
     /// Size of data of current processor
     pub sz: usize,
@@ -53,6 +55,9 @@ impl Distribution {
     /// * `n_global`: Total number of grid points [nx global, ny global]
     /// * `nprocs`: Number of processors
     /// * `nrank`: Current processor id
+    ///
+    /// # Panics
+    /// Less grid points than processors
     #[must_use]
     pub fn split(n_global: usize, nprocs: usize, nrank: usize) -> Self {
         // Distribute
@@ -73,6 +78,23 @@ impl Distribution {
         }
     }
 
+    /// Indices of every ``stride``-th global grid point of current
+    /// processor, as range of indices of the downsampled grid
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::distribution::Distribution;
+    ///
+    /// // Processor 1 holds global indices 5..10
+    /// let dist = Distribution::split(10, 2, 1);
+    /// // ... of which 6 and 9 are multiples of 3
+    /// assert_eq!(dist.strided(3), 2..4);
+    /// ```
+    #[must_use]
+    pub fn strided(&self, stride: usize) -> Range<usize> {
+        strided_range(&(self.st..self.en + 1), stride)
+    }
+
     /// Distribute grid points across processors along 1-dimension
     ///
     /// # Arguments
@@ -82,7 +104,14 @@ impl Distribution {
     /// # Return
     /// Vectors containing starting/ending index and size of each
     /// processor
+    ///
+    /// # Panics
+    /// Less grid points than processors
     fn distribute(n_global: usize, nprocs: usize) -> (Vec<usize>, Vec<usize>, Vec<usize>) {
+        assert!(
+            n_global >= nprocs,
+            "Can't distribute {n_global} grid points on {nprocs} processors"
+        );
         let size = n_global / nprocs;
         let mut st = vec![0; nprocs];
         let mut en = vec![0; nprocs];
@@ -114,3 +143,16 @@ impl Distribution {
         (st, en, sz)
     }
 }
+
+/// Global indices in ``range`` which are multiples of ``stride``,
+/// divided by ``stride``
+///
+/// # Panics
+/// ``stride`` is zero
+#[must_use]
+pub fn strided_range(range: &Range<usize>, stride: usize) -> Range<usize> {
+    assert!(stride > 0, "Stride must be positive.");
+    let st = range.start.div_ceil(stride);
+    let en = range.end.div_ceil(stride);
+    st..en.max(st)
+}
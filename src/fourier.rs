@@ -0,0 +1,272 @@
+//! # Distributed Fourier transforms on periodic grids
+//!
+//! A three dimensional transform is a sequence of one dimensional
+//! transforms along the contiguous axis of each pencil, joined by
+//! transposes: x-pencil (physical) -> y-pencil -> z-pencil (spectral).
+//! One dimensional transforms use ``rustfft``.
+//! Spectral data is stored in the order of the discrete Fourier
+//! transform, see [`wavenumbers`], and each processor finds the global
+//! wavenumbers of its local indices from the offsets of the pencil,
+//! see [`local_wavenumbers`].
+//!
+//! On top, [`derivative`] differentiates spectral data and [`poisson`]
+//! solves the periodic Poisson equation.
+//!
+//! # Example
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::fourier::poisson;
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//! use std::f64::consts::TAU;
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let decomp = Decomp3::from_comm(&comm, [8, 8, 8], [2, 2], [false, false]);
+//!     let rhs = Array3::zeros(decomp.x_pencil.shape());
+//!     let mut sol = Array3::ones(decomp.x_pencil.shape());
+//!     poisson(&decomp, [TAU; 3], &rhs, &mut sol);
+//!     assert!(sol.iter().all(|x| x.abs() < 1e-12));
+//! });
+//! ```
+use crate::comm::Comm;
+use crate::pencil::Pencil;
+use crate::Decomp3;
+use ndarray::{Array1, Array3, ArrayBase, Axis, Data, DataMut, Dimension, Ix3};
+use num_complex::Complex64;
+use rustfft::{Fft, FftPlanner};
+use std::f64::consts::TAU;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+/// Direction of a Fourier transform
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Physical to spectral space, ``exp(-i k x)``
+    Forward,
+    /// Spectral to physical space, ``exp(i k x)``, normalised by *1/n*
+    Backward,
+}
+
+/// Wavenumbers of ``n`` modes of a periodic domain of ``length``
+///
+/// The first ``(n + 1) / 2`` modes hold the non-negative wavenumbers,
+/// followed by the negative ones. For even ``n`` the Nyquist mode is
+/// treated as negative wavenumber.
+///
+/// # Example
+/// ```
+/// use pencil_decomp::fourier::wavenumbers;
+/// use std::f64::consts::TAU;
+///
+/// assert_eq!(wavenumbers(4, TAU), [0., 1., -2., -1.]);
+/// ```
+#[must_use]
+pub fn wavenumbers(n: usize, length: f64) -> Vec<f64> {
+    let npos = n.div_ceil(2);
+    (0..n)
+        .map(|i| {
+            #[allow(clippy::cast_precision_loss)]
+            let k = if i < npos {
+                i as f64
+            } else {
+                -((n - i) as f64)
+            };
+            TAU / length * k
+        })
+        .collect()
+}
+
+/// Wavenumbers along ``axis`` of the local indices of ``pencil``
+///
+/// # Panics
+/// ``axis`` is outside of the array dimensions
+#[must_use]
+pub fn local_wavenumbers<C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    axis: usize,
+    length: f64,
+) -> Vec<f64> {
+    assert!(axis < M, "Axis {axis} outside array dimensions {M}.");
+    let dist = &pencil.dists[axis];
+    wavenumbers(pencil.shape_global()[axis], length)[dist.st..=dist.en].to_vec()
+}
+
+/// Fourier transform of ``data`` along the contiguous axis of
+/// ``pencil``, in place
+///
+/// # Panics
+/// - ``axis`` is not the contiguous axis of ``pencil``
+/// - Shape of ``data`` does not match ``pencil``
+pub fn fft_along<S, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    data: &mut ArrayBase<S, D>,
+    axis: usize,
+    direction: Direction,
+) where
+    S: DataMut<Elem = Complex64>,
+    D: Dimension,
+{
+    assert!(
+        axis == pencil.axis_contig,
+        "Fourier transform along axis {axis} of pencil contiguous in {}.",
+        pencil.axis_contig
+    );
+    assert!(data.shape() == pencil.shape(), "Shape mismatch of data.");
+    let n = data.len_of(Axis(axis));
+    let fft = plan(n, direction);
+    #[allow(clippy::cast_precision_loss)]
+    let scale = match direction {
+        Direction::Forward => 1.,
+        Direction::Backward => 1. / n as f64,
+    };
+    let mut buf = vec![Complex64::default(); n];
+    let mut scratch = vec![Complex64::default(); fft.get_inplace_scratch_len()];
+    for mut lane in data.lanes_mut(Axis(axis)) {
+        for (b, l) in buf.iter_mut().zip(lane.iter()) {
+            *b = *l;
+        }
+        fft.process_with_scratch(&mut buf, &mut scratch);
+        for (l, b) in lane.iter_mut().zip(&buf) {
+            *l = b * scale;
+        }
+    }
+}
+
+/// Forward transform of ``snd`` of the x-pencil into ``rcv`` of the
+/// z-pencil
+///
+/// # Panics
+/// Shape mismatch of snd or rcv with x or z pencil
+pub fn forward<S1, S2, C>(
+    decomp: &Decomp3<C>,
+    snd: &ArrayBase<S1, Ix3>,
+    rcv: &mut ArrayBase<S2, Ix3>,
+) where
+    S1: Data<Elem = Complex64>,
+    S2: DataMut<Elem = Complex64>,
+    C: Comm,
+{
+    assert_eq_shape!(snd, decomp.x_pencil, "forward");
+    assert_eq_shape!(rcv, decomp.z_pencil, "forward");
+    let mut x_hat = snd.to_owned();
+    fft_along(&decomp.x_pencil, &mut x_hat, 0, Direction::Forward);
+    let mut y_hat = Array3::zeros(decomp.y_pencil.shape());
+    decomp.transpose_x_to_y(&x_hat, &mut y_hat);
+    fft_along(&decomp.y_pencil, &mut y_hat, 1, Direction::Forward);
+    decomp.transpose_y_to_z(&y_hat, rcv);
+    fft_along(&decomp.z_pencil, rcv, 2, Direction::Forward);
+}
+
+/// Backward transform of ``snd`` of the z-pencil into ``rcv`` of the
+/// x-pencil, the inverse of [`forward`]
+///
+/// # Panics
+/// Shape mismatch of snd or rcv with z or x pencil
+pub fn backward<S1, S2, C>(
+    decomp: &Decomp3<C>,
+    snd: &ArrayBase<S1, Ix3>,
+    rcv: &mut ArrayBase<S2, Ix3>,
+) where
+    S1: Data<Elem = Complex64>,
+    S2: DataMut<Elem = Complex64>,
+    C: Comm,
+{
+    assert_eq_shape!(snd, decomp.z_pencil, "backward");
+    assert_eq_shape!(rcv, decomp.x_pencil, "backward");
+    let mut z_hat = snd.to_owned();
+    fft_along(&decomp.z_pencil, &mut z_hat, 2, Direction::Backward);
+    let mut y_hat = Array3::zeros(decomp.y_pencil.shape());
+    decomp.transpose_z_to_y(&z_hat, &mut y_hat);
+    fft_along(&decomp.y_pencil, &mut y_hat, 1, Direction::Backward);
+    decomp.transpose_y_to_x(&y_hat, rcv);
+    fft_along(&decomp.x_pencil, rcv, 0, Direction::Backward);
+}
+
+/// Multiply spectral ``data`` of ``pencil`` by ``(i k)^order`` along
+/// ``axis``, in place
+///
+/// The Nyquist mode is removed for odd ``order``, so that derivatives
+/// of real fields remain real.
+///
+/// # Panics
+/// - ``axis`` is outside of the array dimensions
+/// - Shape of ``data`` does not match ``pencil``
+pub fn derivative<S, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    data: &mut ArrayBase<S, D>,
+    axis: usize,
+    length: f64,
+    order: i32,
+) where
+    S: DataMut<Elem = Complex64>,
+    D: Dimension,
+{
+    assert!(axis < M, "Axis {axis} outside array dimensions {M}.");
+    assert!(data.shape() == pencil.shape(), "Shape mismatch of data.");
+    let n = pencil.shape_global()[axis];
+    let st = pencil.dists[axis].st;
+    let factors: Array1<Complex64> = local_wavenumbers(pencil, axis, length)
+        .into_iter()
+        .enumerate()
+        .map(|(i, k)| {
+            if order % 2 != 0 && n % 2 == 0 && st + i == n / 2 {
+                Complex64::default()
+            } else {
+                Complex64::new(0., k).powi(order)
+            }
+        })
+        .collect();
+    for mut lane in data.lanes_mut(Axis(axis)) {
+        lane *= &factors;
+    }
+}
+
+/// Solve the Poisson equation ``laplace(sol) = rhs`` on a periodic
+/// domain of ``lengths``, both in the x-pencil
+///
+/// The mean of ``rhs`` is ignored, ``sol`` has zero mean.
+///
+/// # Panics
+/// Shape mismatch of rhs or sol with x pencil
+pub fn poisson<S1, S2, C>(
+    decomp: &Decomp3<C>,
+    lengths: [f64; 3],
+    rhs: &ArrayBase<S1, Ix3>,
+    sol: &mut ArrayBase<S2, Ix3>,
+) where
+    S1: Data<Elem = f64>,
+    S2: DataMut<Elem = f64>,
+    C: Comm,
+{
+    assert_eq_shape!(rhs, decomp.x_pencil, "poisson");
+    assert_eq_shape!(sol, decomp.x_pencil, "poisson");
+    let pencil = &decomp.z_pencil;
+    let mut z_hat = Array3::zeros(pencil.shape());
+    forward(decomp, &rhs.mapv(Complex64::from), &mut z_hat);
+    let k = [0, 1, 2].map(|a| local_wavenumbers(pencil, a, lengths[a]));
+    for ((i, j, l), v) in z_hat.indexed_iter_mut() {
+        let k2 = k[0][i] * k[0][i] + k[1][j] * k[1][j] + k[2][l] * k[2][l];
+        *v = if k2 == 0. {
+            Complex64::default()
+        } else {
+            -*v / k2
+        };
+    }
+    let mut x_data = Array3::zeros(decomp.x_pencil.shape());
+    backward(decomp, &z_hat, &mut x_data);
+    sol.zip_mut_with(&x_data, |s, x| *s = x.re);
+}
+
+/// Plan of a transform of ``n`` points in ``direction``
+///
+/// Plans are created once per length and direction, and shared by
+/// all threads.
+fn plan(n: usize, direction: Direction) -> Arc<dyn Fft<f64>> {
+    static PLANNER: OnceLock<Mutex<FftPlanner<f64>>> = OnceLock::new();
+    let mut planner = PLANNER
+        .get_or_init(|| Mutex::new(FftPlanner::new()))
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    match direction {
+        Direction::Forward => planner.plan_fft_forward(n),
+        Direction::Backward => planner.plan_fft_inverse(n),
+    }
+}
@@ -0,0 +1,402 @@
+//! # Layout of pencil decompositions
+//!
+//! Communicator-free description of a decomposition, which can be
+//! stored alongside the output of a run. With the ``serde`` feature
+//! layouts are serialisable, and at restart the running decomposition
+//! can be checked against the stored one.
+//!
+//! # Example
+//! ```
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let decomp = Decomp3::from_comm(&comm, [8, 6, 4], [2, 2], [false, false]);
+//!     // Any processor may store the layout
+//!     let stored = decomp.layout();
+//!     // ... and at restart
+//!     let decomp = Decomp3::from_layout(&comm, &stored);
+//!     decomp.check_layout(&stored);
+//! });
+//! ```
+use crate::distribution::Distribution;
+use std::ops::Range;
+
+/// Communicator-free part of a [`crate::Pencil`]
+///
+/// Distributions and cartesian coordinates refer to one processor.
+///
+/// # Example
+/// Layout of the y-pencil on the processor at coordinates [1, 0]
+/// of a 2 x 2 processor grid, without any communication
+/// ```
+/// use pencil_decomp::layout::PencilLayout;
+///
+/// let layout = PencilLayout::new([6, 7, 9], 1, &[2, 2], &[1, 0], &[false, false]);
+/// assert_eq!(layout.shape(), [3, 7, 4]);
+/// assert_eq!(layout.global_ranges(3), [3..6, 0..7, 4..9]);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PencilLayout<const M: usize> {
+    /// Grid point distribution along each axis
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub dists: [Distribution; M],
+    /// One axis is contiguous
+    pub axis_contig: usize,
+    /// Number of processors along each dimension of the cartesian topology
+    pub cart_dims: Vec<i32>,
+    /// Coordinates of the processor in the cartesian topology
+    pub cart_coords: Vec<i32>,
+    /// Periodicity of the cartesian topology
+    pub cart_periodic: Vec<bool>,
+}
+
+impl<const M: usize> PencilLayout<M> {
+    /// Layout of pencil on the processor at ``cart_coords``
+    ///
+    /// # Arguments
+    /// * `n_global`     : Total number of grid points [nx global, ny global, ...]
+    /// * `axis_contig`  : Contiguous axis
+    /// * `cart_dims`    : Number of processors along each dimension of the cartesian topology
+    /// * `cart_coords`  : Coordinates of the processor in the cartesian topology
+    /// * `cart_periodic`: Periodicity of the cartesian topology
+    ///
+    /// # Panics
+    /// - Contiguous axis must be < *M*
+    /// - Cartesian topology must have *M* - 1 dimensions
+    /// - Coordinates outside of the cartesian topology
+    /// - Less grid points than processors along an axis
+    #[must_use]
+    pub fn new(
+        n_global: [usize; M],
+        axis_contig: usize,
+        cart_dims: &[i32],
+        cart_coords: &[i32],
+        cart_periodic: &[bool],
+    ) -> Self {
+        assert!(axis_contig < M, "Contiguous axis must be < M");
+        assert!(
+            cart_dims.len() == M - 1 && cart_coords.len() == M - 1 && cart_periodic.len() == M - 1,
+            "Dimensionality mismatch, expect cartesian topology of {} dims",
+            M - 1
+        );
+        assert!(
+            cart_coords
+                .iter()
+                .zip(cart_dims.iter())
+                .all(|(c, d)| 0 <= *c && c < d),
+            "Coordinates {cart_coords:?} outside of processor grid {cart_dims:?}"
+        );
+        // Distribute grid points
+        let mut dim = 0;
+        let dists = std::array::from_fn(|i| {
+            if i == axis_contig {
+                Distribution::contiguous(n_global[i])
+            } else {
+                dim += 1;
+                Distribution::split(
+                    n_global[i],
+                    cart_dims[dim - 1].try_into().unwrap(),
+                    cart_coords[dim - 1].try_into().unwrap(),
+                )
+            }
+        });
+        Self {
+            dists,
+            axis_contig,
+            cart_dims: cart_dims.to_vec(),
+            cart_coords: cart_coords.to_vec(),
+            cart_periodic: cart_periodic.to_vec(),
+        }
+    }
+
+    /// Gets the coordinate of a process in a communicator that has a cartesian topology.
+    #[must_use]
+    pub fn cart_coords(&self) -> Vec<i32> {
+        self.cart_coords.clone()
+    }
+
+    /// Gets integer array of size ndims specifying the number of
+    /// processes in each dimension
+    #[must_use]
+    pub fn cart_dims(&self) -> Vec<i32> {
+        self.cart_dims.clone()
+    }
+
+    /// Gets logical array of size ndims specifying whether the grid is periodic
+    #[must_use]
+    pub fn cart_periodic(&self) -> Vec<bool> {
+        self.cart_periodic.clone()
+    }
+
+    /// Maps physical dimension to cartesian topology dimension
+    ///
+    /// For example, if contiguos axis is 1, then
+    /// ``dim`` = 0 -> ``cart_dim`` = 0,
+    /// ``dim`` = 2 -> ``cart_dim`` = 1.
+    ///
+    /// # Panics
+    /// - If *dim* equals contiguos axis
+    /// - If *dim* is larger than *M*
+    pub(crate) fn map_dim_to_cart_dim(&self, dim: usize) -> usize {
+        assert!(dim < M);
+        match dim.cmp(&self.axis_contig) {
+            std::cmp::Ordering::Less => dim,
+            std::cmp::Ordering::Greater => dim - 1,
+            std::cmp::Ordering::Equal => panic!("dim must differ from axis_contig"),
+        }
+    }
+
+    /// Return number of splits / processors along a certain dimension(axis)
+    #[must_use]
+    pub fn nprocs_along_axis(&self, axis: usize) -> i32 {
+        let cart_dim = self.map_dim_to_cart_dim(axis);
+        self.cart_dims[cart_dim]
+    }
+
+    /// Return the total length of data hold by current processor
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.shape().iter().product()
+    }
+
+    /// Return the total length of data hold by current processor
+    #[must_use]
+    pub fn len_global(&self) -> usize {
+        self.shape_global().iter().product()
+    }
+
+    /// Returns true if self has a length of zero.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.shape().iter().product::<usize>() == 0
+    }
+
+    /// Shape of pencil distributed data
+    ///
+    /// # Panics
+    /// Vector to array conversion fails
+    #[must_use]
+    pub fn shape(&self) -> [usize; M] {
+        self.dists
+            .iter()
+            .map(|x| x.sz)
+            .collect::<Vec<usize>>()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Shape of global data
+    ///
+    /// # Panics
+    /// Vector to array conversion fails
+    #[must_use]
+    pub fn shape_global(&self) -> [usize; M] {
+        self.dists
+            .iter()
+            .map(|x| x.sz_procs.iter().sum())
+            .collect::<Vec<usize>>()
+            .try_into()
+            .unwrap()
+    }
+
+    /// Global index ranges of the data hold by processor ``rank``
+    ///
+    /// # Panics
+    /// ``rank`` is outside of the cartesian topology
+    #[must_use]
+    pub fn global_ranges(&self, rank: i32) -> [Range<usize>; M] {
+        let nprocs = self.cart_dims.iter().product::<i32>();
+        assert!(
+            0 <= rank && rank < nprocs,
+            "Rank {rank} outside of {nprocs} procs."
+        );
+        let coords = rank_to_coords(rank, &self.cart_dims);
+        std::array::from_fn(|axis| {
+            let dist = &self.dists[axis];
+            if axis == self.axis_contig {
+                dist.st..dist.en + 1
+            } else {
+                let coord: usize = coords[self.map_dim_to_cart_dim(axis)].try_into().unwrap();
+                dist.st_procs[coord]..dist.en_procs[coord] + 1
+            }
+        })
+    }
+
+    /// Ranks of all processors which hold part of the global
+    /// index ``ranges``
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::layout::PencilLayout;
+    ///
+    /// let layout = PencilLayout::new([6, 7, 9], 0, &[2, 2], &[0, 0], &[false, false]);
+    /// // Plane z = 2 is held by the processors at z-coordinate 0
+    /// assert_eq!(layout.owners(&[0..6, 0..7, 2..3]), [0, 2]);
+    /// ```
+    ///
+    /// # Panics
+    /// Conversion of the number of processors
+    #[must_use]
+    pub fn owners(&self, ranges: &[Range<usize>; M]) -> Vec<i32> {
+        let nprocs = self.cart_dims.iter().product::<i32>();
+        (0..nprocs)
+            .filter(|&rank| intersection(&self.global_ranges(rank), ranges).is_some())
+            .collect()
+    }
+
+    /// First difference to ``other`` in the parts which are the
+    /// same on all processors, i.e. ignoring the coordinates and
+    /// the local distributions of the processors
+    #[must_use]
+    pub fn mismatch(&self, other: &Self) -> Option<String> {
+        if self.axis_contig != other.axis_contig {
+            return Some(format!(
+                "contiguous axis {} != {}",
+                self.axis_contig, other.axis_contig
+            ));
+        }
+        if self.cart_dims != other.cart_dims {
+            return Some(format!(
+                "processor grid {:?} != {:?}",
+                self.cart_dims, other.cart_dims
+            ));
+        }
+        if self.cart_periodic != other.cart_periodic {
+            return Some(format!(
+                "periodicity {:?} != {:?}",
+                self.cart_periodic, other.cart_periodic
+            ));
+        }
+        for (axis, (a, b)) in self.dists.iter().zip(other.dists.iter()).enumerate() {
+            if a.st_procs != b.st_procs || a.en_procs != b.en_procs {
+                return Some(format!(
+                    "distribution along axis {axis}: starts {:?} != {:?}, ends {:?} != {:?}",
+                    a.st_procs, b.st_procs, a.en_procs, b.en_procs
+                ));
+            }
+        }
+        None
+    }
+}
+
+/// Intersection of global index ranges ``a`` and ``b``,
+/// ``None`` if it is empty
+#[must_use]
+pub fn intersection<const M: usize>(
+    a: &[Range<usize>; M],
+    b: &[Range<usize>; M],
+) -> Option<[Range<usize>; M]> {
+    let ranges: [Range<usize>; M] =
+        std::array::from_fn(|i| a[i].start.max(b[i].start)..a[i].end.min(b[i].end));
+    ranges.iter().all(|r| r.start < r.end).then_some(ranges)
+}
+
+/// Coordinates of processor ``rank`` in a cartesian topology with
+/// ``cart_dims`` processors along each dimension, ranks are ordered
+/// row-major
+pub(crate) fn rank_to_coords(rank: i32, cart_dims: &[i32]) -> Vec<i32> {
+    let mut coords = vec![0; cart_dims.len()];
+    let mut rest = rank;
+    for (c, d) in coords.iter_mut().zip(cart_dims.iter()).rev() {
+        *c = rest % d;
+        rest /= d;
+    }
+    coords
+}
+
+/// Communicator-free part of [`crate::Decomp2`] and [`crate::Decomp3`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DecompLayout<const M: usize> {
+    /// Total number of grid points
+    #[cfg_attr(feature = "serde", serde(with = "serde_array"))]
+    pub n_global: [usize; M],
+    /// Layout of the x-, y- (and z-) pencil
+    pub pencils: Vec<PencilLayout<M>>,
+}
+
+impl<const M: usize> DecompLayout<M> {
+    /// First difference to ``other`` which does not depend on the
+    /// processor, see [`PencilLayout::mismatch`]
+    #[must_use]
+    pub fn mismatch(&self, other: &Self) -> Option<String> {
+        if self.n_global != other.n_global {
+            return Some(format!(
+                "grid size {:?} != {:?}",
+                self.n_global, other.n_global
+            ));
+        }
+        if self.pencils.len() != other.pencils.len() {
+            return Some(format!(
+                "number of pencils {} != {}",
+                self.pencils.len(),
+                other.pencils.len()
+            ));
+        }
+        self.pencils
+            .iter()
+            .zip(other.pencils.iter())
+            .find_map(|(a, b)| a.mismatch(b))
+    }
+
+    /// Assert that the decomposition matches ``stored``, which may
+    /// come from any processor
+    ///
+    /// # Panics
+    /// Decompositions differ
+    pub fn assert_matches(&self, stored: &Self) {
+        if let Some(mismatch) = self.mismatch(stored) {
+            panic!("Decomposition does not match stored layout: {mismatch}");
+        }
+    }
+
+    /// Processor grid of the decomposition
+    ///
+    /// # Panics
+    /// Layout has no pencils
+    #[must_use]
+    pub fn cart_dims(&self) -> &[i32] {
+        &self.pencils[0].cart_dims
+    }
+
+    /// Periodicity of the processor grid
+    ///
+    /// # Panics
+    /// Layout has no pencils
+    #[must_use]
+    pub fn cart_periodic(&self) -> &[bool] {
+        &self.pencils[0].cart_periodic
+    }
+}
+
+/// Serde of const generic arrays as sequences
+#[cfg(feature = "serde")]
+pub(crate) mod serde_array {
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(crate) fn serialize<S, T, const M: usize>(
+        array: &[T; M],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+        T: Serialize,
+    {
+        array.as_slice().serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D, T, const M: usize>(
+        deserializer: D,
+    ) -> Result<[T; M], D::Error>
+    where
+        D: Deserializer<'de>,
+        T: Deserialize<'de>,
+    {
+        let vec = Vec::<T>::deserialize(deserializer)?;
+        let len = vec.len();
+        vec.try_into()
+            .map_err(|_| D::Error::invalid_length(len, &format!("an array of length {M}").as_str()))
+    }
+}
@@ -12,11 +12,26 @@
 #[macro_use]
 mod internal_macros;
 
+pub mod comm;
 pub mod distribution;
+#[cfg(feature = "fft")]
+pub mod fourier;
+pub mod layout;
+pub mod pack;
 pub mod pencil;
+pub mod plan;
+pub mod render;
+pub mod resample;
+pub mod scan;
 pub mod simple_comms;
+pub mod spectral;
+pub mod stats;
+pub mod tridiag;
 pub use pencil::Pencil;
 pub mod decomp3;
 pub use decomp3::Decomp3;
 pub mod decomp2;
 pub use decomp2::Decomp2;
+pub mod thread_comm;
+pub use thread_comm::ThreadComm;
+pub mod autotune;
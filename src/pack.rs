@@ -0,0 +1,350 @@
+//! # Pack and unpack transpose buffers
+//!
+//! The send buffer of a transpose holds one block per destination
+//! processor. Each block is written in the memory order of the send
+//! pencil (see [`Pencil::order`]), and the receiving processor reads
+//! it back in the same order. Arrays of any memory layout can be
+//! transposed, but packing is cheapest when the arrays are stored
+//! in the order of their pencils.
+//!
+//! Blocks are copied in contiguous runs where possible. If the
+//! buffer order and the memory layout of a block disagree, the
+//! block is copied in tiles, which keeps reads and writes in cache.
+//!
+//! With feature ``rayon``, the blocks of all destination processors
+//! are packed and unpacked in parallel.
+use crate::pencil::Pencil;
+use ndarray::{
+    s, ArrayBase, ArrayView, ArrayView2, ArrayView3, ArrayViewMut, ArrayViewMut2, ArrayViewMut3,
+    Axis, Data, DataMut, Dimension, Ix3, Order, Slice,
+};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Edge length of tiles for copies with mismatching layouts
+const TILE: usize = 32;
+
+/// Prepare send buffer for transpose from ``send`` to ``recv`` pencil
+///
+/// # Panics
+/// Buffer size does not match size of ``data``
+pub(crate) fn split<S, T, D, C, const M: usize, const N: usize>(
+    data: &ArrayBase<S, D>,
+    buf: &mut [T],
+    send: &Pencil<M, N, C>,
+    recv: &Pencil<M, N, C>,
+) where
+    S: Data<Elem = T>,
+    T: Copy + Send + Sync,
+    D: Dimension,
+{
+    // Blocks are cut along the axis which is split in the recv pencil
+    let axis = send.axis_contig;
+    let dist = &recv.dists[axis];
+    let mut rest = buf;
+    let mut blocks = Vec::with_capacity(dist.st_procs.len());
+    for (&st, &en) in dist.st_procs.iter().zip(dist.en_procs.iter()) {
+        let block = data.slice_axis(Axis(axis), Slice::from(st..=en));
+        assert!(block.len() <= rest.len(), "Buffer size mismatch");
+        let (chunk, tail) = std::mem::take(&mut rest).split_at_mut(block.len());
+        rest = tail;
+        blocks.push((block, chunk));
+    }
+    assert!(rest.is_empty(), "Buffer size mismatch");
+    let order = send.order;
+    for_each_block(blocks, |(block, chunk)| {
+        pack_block(block, chunk, order);
+    });
+}
+
+/// Redistribute recv buffer of transpose from ``send`` to ``recv`` pencil
+///
+/// # Panics
+/// Buffer size does not match size of ``data``
+pub(crate) fn merge<S, T, D, C, const M: usize, const N: usize>(
+    buf: &[T],
+    data: &mut ArrayBase<S, D>,
+    send: &Pencil<M, N, C>,
+    recv: &Pencil<M, N, C>,
+) where
+    S: DataMut<Elem = T>,
+    T: Copy + Send + Sync,
+    D: Dimension,
+{
+    // Blocks are cut along the axis which is split in the send pencil
+    let axis = recv.axis_contig;
+    let dist = &send.dists[axis];
+    let mut rest = data.view_mut();
+    let mut pos = 0;
+    let mut blocks = Vec::with_capacity(dist.st_procs.len());
+    for (&st, &en) in dist.st_procs.iter().zip(dist.en_procs.iter()) {
+        let (block, tail) = rest.split_at(Axis(axis), en - st + 1);
+        rest = tail;
+        let n = block.len();
+        assert!(pos + n <= buf.len(), "Buffer size mismatch");
+        blocks.push((&buf[pos..pos + n], block));
+        pos += n;
+    }
+    assert_eq!(pos, buf.len(), "Buffer size mismatch");
+    let order = send.order;
+    for_each_block(blocks, |(chunk, block)| {
+        unpack_block(chunk, block, order);
+    });
+}
+
+/// Apply ``f`` to each block, in parallel with feature ``rayon``
+fn for_each_block<B, F>(blocks: Vec<B>, f: F)
+where
+    B: Send,
+    F: Fn(B) + Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    blocks.into_par_iter().for_each(f);
+    #[cfg(not(feature = "rayon"))]
+    blocks.into_iter().for_each(f);
+}
+
+/// Copy ``block`` into the front of ``buf`` in the given order,
+/// returns the number of copied elements
+///
+/// # Panics
+/// - ``buf`` is smaller than ``block``
+/// - ``block`` has more than three dimensions
+pub fn pack_block<T: Copy, D: Dimension>(
+    block: ArrayView<T, D>,
+    buf: &mut [T],
+    order: Order,
+) -> usize {
+    let block = if order == Order::ColumnMajor {
+        block.reversed_axes()
+    } else {
+        block
+    };
+    let n = block.len();
+    pack3(into_3d(block), &mut buf[..n]);
+    n
+}
+
+/// Copy the front of ``buf`` into ``block`` in the given order,
+/// returns the number of copied elements
+///
+/// # Panics
+/// - ``buf`` is smaller than ``block``
+/// - ``block`` has more than three dimensions
+pub fn unpack_block<T: Copy, D: Dimension>(
+    buf: &[T],
+    block: ArrayViewMut<T, D>,
+    order: Order,
+) -> usize {
+    let block = if order == Order::ColumnMajor {
+        block.reversed_axes()
+    } else {
+        block
+    };
+    let n = block.len();
+    unpack3(&buf[..n], into_3d_mut(block));
+    n
+}
+
+/// Prepend axes of length one to view with less than three dimensions
+fn into_3d<T, D: Dimension>(view: ArrayView<T, D>) -> ArrayView3<T> {
+    let mut view = view.into_dyn();
+    while view.ndim() < 3 {
+        view = view.insert_axis(Axis(0));
+    }
+    view.into_dimensionality::<Ix3>()
+        .expect("Pack and unpack support up to three dimensions.")
+}
+
+/// Prepend axes of length one to view with less than three dimensions
+fn into_3d_mut<T, D: Dimension>(view: ArrayViewMut<T, D>) -> ArrayViewMut3<T> {
+    let mut view = view.into_dyn();
+    while view.ndim() < 3 {
+        view = view.insert_axis(Axis(0));
+    }
+    view.into_dimensionality::<Ix3>()
+        .expect("Pack and unpack support up to three dimensions.")
+}
+
+/// Axis (0 or 1) with the smallest stride, if it is smaller than
+/// the stride of the last axis. Axes of length one are ignored.
+fn faster_outer_axis(shape: &[usize], strides: &[isize]) -> Option<usize> {
+    let stride = |axis: usize| {
+        if shape[axis] > 1 {
+            strides[axis].unsigned_abs()
+        } else {
+            usize::MAX
+        }
+    };
+    let fast = usize::from(stride(1) <= stride(0));
+    (stride(fast) < stride(2)).then_some(fast)
+}
+
+/// Copy ``src`` into ``buf`` in row-major order
+fn pack3<T: Copy>(src: ArrayView3<T>, buf: &mut [T]) {
+    if let Some(s) = src.as_slice() {
+        buf.copy_from_slice(s);
+        return;
+    }
+    if buf.is_empty() {
+        return;
+    }
+    let (n0, n1, n2) = src.dim();
+    match faster_outer_axis(src.shape(), src.strides()) {
+        // Last axis is fastest: copy rows
+        None => {
+            for (row, chunk) in src.rows().into_iter().zip(buf.chunks_exact_mut(n2)) {
+                match row.as_slice() {
+                    Some(s) => chunk.copy_from_slice(s),
+                    None => chunk.iter_mut().zip(row.iter()).for_each(|(b, d)| *b = *d),
+                }
+            }
+        }
+        // Axis 1 is fastest: tiles in (1, 2)-plane
+        Some(1) => {
+            for (plane, chunk) in src.outer_iter().zip(buf.chunks_exact_mut(n1 * n2)) {
+                pack_tiled(plane, chunk, n2);
+            }
+        }
+        // Axis 0 is fastest: tiles in (0, 2)-plane
+        Some(_) => {
+            for j in 0..n1 {
+                let plane = src.index_axis(Axis(1), j);
+                let offset = j * n2;
+                pack_tiled(
+                    plane,
+                    &mut buf[offset..offset + (n0 - 1) * n1 * n2 + n2],
+                    n1 * n2,
+                );
+            }
+        }
+    }
+}
+
+/// Copy ``buf`` in row-major order into ``dst``
+fn unpack3<T: Copy>(buf: &[T], mut dst: ArrayViewMut3<T>) {
+    if let Some(s) = dst.as_slice_mut() {
+        s.copy_from_slice(buf);
+        return;
+    }
+    if buf.is_empty() {
+        return;
+    }
+    let (n0, n1, n2) = dst.dim();
+    match faster_outer_axis(dst.shape(), dst.strides()) {
+        // Last axis is fastest: copy rows
+        None => {
+            for (mut row, chunk) in dst.rows_mut().into_iter().zip(buf.chunks_exact(n2)) {
+                match row.as_slice_mut() {
+                    Some(s) => s.copy_from_slice(chunk),
+                    None => row.iter_mut().zip(chunk.iter()).for_each(|(d, b)| *d = *b),
+                }
+            }
+        }
+        // Axis 1 is fastest: tiles in (1, 2)-plane
+        Some(1) => {
+            for (plane, chunk) in dst.outer_iter_mut().zip(buf.chunks_exact(n1 * n2)) {
+                unpack_tiled(chunk, plane, n2);
+            }
+        }
+        // Axis 0 is fastest: tiles in (0, 2)-plane
+        Some(_) => {
+            for j in 0..n1 {
+                let plane = dst.index_axis_mut(Axis(1), j);
+                let offset = j * n2;
+                unpack_tiled(
+                    &buf[offset..offset + (n0 - 1) * n1 * n2 + n2],
+                    plane,
+                    n1 * n2,
+                );
+            }
+        }
+    }
+}
+
+/// Copy ``src`` of shape (na, nb), whose first axis is fastest
+/// in memory, into ``buf`` where element (a, b) is at
+/// ``a * row_stride + b``
+fn pack_tiled<T: Copy>(src: ArrayView2<T>, buf: &mut [T], row_stride: usize) {
+    let (na, nb) = src.dim();
+    for a0 in (0..na).step_by(TILE) {
+        let a1 = (a0 + TILE).min(na);
+        for b0 in (0..nb).step_by(TILE) {
+            let b1 = (b0 + TILE).min(nb);
+            let tile = src.slice(s![a0..a1, b0..b1]);
+            for (a, row) in (a0..a1).zip(tile.rows()) {
+                let out = &mut buf[a * row_stride + b0..a * row_stride + b1];
+                out.iter_mut().zip(row.iter()).for_each(|(y, x)| *y = *x);
+            }
+        }
+    }
+}
+
+/// Copy ``buf``, where element (a, b) is at ``a * row_stride + b``,
+/// into ``dst`` of shape (na, nb), whose first axis is fastest in memory
+fn unpack_tiled<T: Copy>(buf: &[T], mut dst: ArrayViewMut2<T>, row_stride: usize) {
+    let (na, nb) = dst.dim();
+    for a0 in (0..na).step_by(TILE) {
+        let a1 = (a0 + TILE).min(na);
+        for b0 in (0..nb).step_by(TILE) {
+            let b1 = (b0 + TILE).min(nb);
+            let mut tile = dst.slice_mut(s![a0..a1, b0..b1]);
+            for (b, mut col) in (b0..b1).zip(tile.columns_mut()) {
+                let inp = buf[a0 * row_stride + b..].iter().step_by(row_stride);
+                col.iter_mut().zip(inp).for_each(|(y, x)| *y = *x);
+            }
+        }
+    }
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::{merge, pack_block, split, unpack_block};
+    use crate::{Decomp3, ThreadComm};
+    use ndarray::{Array3, Axis, ShapeBuilder, Slice};
+
+    /// Parallel split and merge match the serial kernels on uneven blocks
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_split_merge_match_serial() {
+        ThreadComm::run(3, |comm| {
+            for order in [ndarray::Order::RowMajor, ndarray::Order::ColumnMajor] {
+                let decomp =
+                    Decomp3::from_comm(&comm, [11, 7, 5], [3, 1], [false, false]).with_order(order);
+                let (send, recv) = (&decomp.x_pencil, &decomp.y_pencil);
+                let data = Array3::from_shape_fn(send.shape().f(), |(i, j, k)| {
+                    (i * 100 + j * 10 + k) as f64
+                });
+                let dist = &recv.dists[send.axis_contig];
+                let blocks: Vec<_> = dist
+                    .st_procs
+                    .iter()
+                    .zip(dist.en_procs.iter())
+                    .map(|(&st, &en)| Slice::from(st..=en))
+                    .collect();
+
+                let mut buf = vec![0.; data.len()];
+                split(&data, &mut buf, send, recv);
+                let mut expected = vec![0.; data.len()];
+                let mut pos = 0;
+                for &block in &blocks {
+                    let block = data.slice_axis(Axis(send.axis_contig), block);
+                    pos += pack_block(block, &mut expected[pos..], send.order);
+                }
+                assert_eq!(buf, expected, "{order:?}");
+
+                // Merge the buffer back into the send pencil
+                let mut merged = Array3::zeros(send.shape());
+                merge(&buf, &mut merged, recv, send);
+                let mut expected = Array3::zeros(send.shape());
+                let mut pos = 0;
+                for &block in &blocks {
+                    let block = expected.slice_axis_mut(Axis(send.axis_contig), block);
+                    pos += unpack_block(&buf[pos..], block, recv.order);
+                }
+                assert_eq!(merged, expected, "{order:?}");
+                assert_eq!(merged, data, "{order:?}");
+            }
+        });
+    }
+}
@@ -1,34 +1,51 @@
 //! # Pencil distributed data
-use crate::distribution::Distribution;
-use mpi::topology::Communicator;
-use mpi::{
-    collective::CommunicatorCollectives, collective::Root, datatype::Partition,
-    datatype::PartitionMut, environment::Universe, topology::CartesianCommunicator,
-    topology::CartesianLayout, traits::Equivalence, Count,
-};
+use crate::comm::Subarray;
+use crate::comm::{assert_root, ranges, Comm, Element, Exchange, MpiComm, EXCHANGE_ENV};
+use crate::distribution::{strided_range, Distribution};
+use crate::layout::{intersection, rank_to_coords, PencilLayout};
+use crate::pack::{merge, split};
+use crate::stats::{OpStats, Stats, Timer};
+use mpi::{environment::Universe, topology::Rank, Count};
+use ndarray::{ArrayBase, Data, DataMut, Dimension, Order, Slice};
 use num_traits::Zero;
+use std::ops::Deref;
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Pencil Distribution
 ///
+/// Wraps the communicator-free [`PencilLayout`] of current processor,
+/// whose fields and methods are available through ``Deref``, together
+/// with its communicators.
+///
 /// *M* number of grid dimensions.
 /// *N* specifies number of dimension of the cartesian topology,
 /// Currently restricted to *N* = *M* - 1
-pub struct Pencil<'a, const M: usize, const N: usize> {
-    /// Mpi universe
-    pub universe: &'a Universe,
+///
+/// *C* is the communication backend, see [`crate::comm`].
+pub struct Pencil<const M: usize, const N: usize, C = MpiComm> {
     /// Communicator
-    pub comm: CartesianCommunicator,
-    /// Grid point distribution along each axis
-    pub dists: [Distribution; M],
-    /// One axis is contiguous
-    pub axis_contig: usize,
+    pub comm: C,
+    /// Sub-communicators along each dimension of the cartesian topology
+    subcomms: Vec<C>,
+    /// Layout of pencil on current processor
+    layout: PencilLayout<M>,
+    /// Preferred memory order of pencil distributed arrays, which
+    /// defines the order in which transpose buffers are packed.
+    /// Arrays with any memory layout are supported.
+    pub order: Order,
+    /// Algorithm of all-to-all exchanges in transposes from this pencil,
+    /// defaults to [`Exchange::from_env`]
+    pub exchange: Exchange,
+    /// Statistics of communication routines, if enabled
+    stats: Option<Arc<Stats>>,
 }
 
-impl<'a, const M: usize, const N: usize> Pencil<'a, M, N> {
+impl<const M: usize, const N: usize> Pencil<M, N> {
     /// Construct pencil distribution
     ///
     /// # Arguments
-    /// * `universe`     : Mpi Universe
+    /// * `universe`     : Mpi Universe, shared by the pencil
     /// * `n_global`     : Total number of grid points [nx global, ny global]
     /// * `axis_contig`  : Contiguous axis
     /// * `cart_ndims`   : Number of dimensions of cartesian grid
@@ -43,8 +60,9 @@ impl<'a, const M: usize, const N: usize> Pencil<'a, M, N> {
     /// ```ignore
     /// use mpi::topology::Communicator;
     /// use pencil_decomp::Pencil;
+    /// use std::sync::Arc;
     /// // Init Mpi
-    /// let universe = mpi::initialize().unwrap();
+    /// let universe = Arc::new(mpi::initialize().unwrap());
     /// let world = universe.world();
     /// assert!(world.size() == 6, "Run with 6 processors");
     ///
@@ -60,7 +78,57 @@ impl<'a, const M: usize, const N: usize> Pencil<'a, M, N> {
     /// ```
     #[must_use]
     pub fn new(
-        universe: &'a Universe,
+        universe: &Arc<Universe>,
+        n_global: [usize; M],
+        axis_contig: usize,
+        cart_ndims: [i32; N],
+        cart_periodic: [bool; N],
+    ) -> Self {
+        let comm = MpiComm::world(universe);
+        Self::with_comm(comm, n_global, axis_contig, cart_ndims, cart_periodic)
+    }
+}
+
+impl<const M: usize, const N: usize, C: Comm> Pencil<M, N, C> {
+    /// Construct pencil distribution on any communication backend
+    ///
+    /// # Arguments
+    /// * `comm`         : Communicator, see [`crate::comm`]
+    /// * `n_global`     : Total number of grid points [nx global, ny global]
+    /// * `axis_contig`  : Contiguous axis
+    /// * `cart_ndims`   : Number of dimensions of cartesian grid
+    /// * `cart_periodic`: Logical array of size ``cart_ndims`` specifying whether the grid is periodic
+    ///
+    /// # Panics
+    /// - Mismatch of *ndims* and number of processors
+    /// - ``PENCIL_DECOMP_EXCHANGE`` holds an algorithm which ``comm``
+    ///   does not support, see [`Comm::supported_exchanges`]
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::{Pencil, ThreadComm};
+    ///
+    /// let lens = ThreadComm::run(2, |comm| {
+    ///     let pencil = Pencil::from_comm(&comm, [6, 4], 0, [2], [false]);
+    ///     pencil.len()
+    /// });
+    /// assert_eq!(lens, [6 * 2, 6 * 2]);
+    /// ```
+    #[must_use]
+    pub fn from_comm(
+        comm: &C,
+        n_global: [usize; M],
+        axis_contig: usize,
+        cart_ndims: [i32; N],
+        cart_periodic: [bool; N],
+    ) -> Self {
+        let comm = comm.split(0, comm.rank());
+        Self::with_comm(comm, n_global, axis_contig, cart_ndims, cart_periodic)
+    }
+
+    /// Construct pencil distribution, takes ownership of ``comm``
+    fn with_comm(
+        comm: C,
         n_global: [usize; M],
         axis_contig: usize,
         cart_ndims: [i32; N],
@@ -73,102 +141,92 @@ impl<'a, const M: usize, const N: usize> Pencil<'a, M, N> {
             N == M - 1,
             "Dimensionality mismatch, expect N == M - 1, check cart_ndims"
         );
-        // Mpi comm world
-        let world_comm = universe.world();
         // Check number of processors
         let n = cart_ndims.iter().product::<i32>();
-        let m = world_comm.size();
+        let m = comm.size();
         let cn = cart_ndims;
-        assert!(n == m, "Expect {} procs for grid {:?}, got {}", n, cn, m);
-        // Create cartesian communicator
-        let comm = world_comm
-            .create_cartesian_communicator(&cart_ndims, &cart_periodic, false)
-            .unwrap();
-        // Distribute grid points
-        let mut dists: Vec<Distribution> = Vec::new();
-        // nrank
-        let CartesianLayout { coords, .. } = comm.get_layout();
-        let mut dim = 0;
-        for (i, &n_dim) in n_global.iter().enumerate() {
-            if i == axis_contig {
-                dists.push(Distribution::contiguous(n_dim));
-            } else {
-                dists.push(Distribution::split(
-                    n_dim,
-                    cart_ndims[dim].try_into().unwrap(),
-                    coords[dim].try_into().unwrap(),
-                ));
-                dim += 1;
-            }
+        assert!(n == m, "Expect {n} procs for grid {cn:?}, got {m}");
+        // Coordinates in cartesian topology, ranks are ordered row-major
+        let cart_coords = rank_to_coords(comm.rank(), &cart_ndims);
+        // Sub-communicators along each dimension of the cartesian topology.
+        // The color is the rank of the first processor in the sub-group.
+        let mut stride = 1;
+        let mut subcomms: Vec<C> = Vec::new();
+        for (&c, &d) in cart_coords.iter().zip(cart_ndims.iter()).rev() {
+            subcomms.push(comm.split(comm.rank() - c * stride, c));
+            stride *= d;
         }
-        // Convert to array
-        let dists: [Distribution; M] = dists.try_into().unwrap();
+        subcomms.reverse();
+        let layout = PencilLayout::new(
+            n_global,
+            axis_contig,
+            &cart_ndims,
+            &cart_coords,
+            &cart_periodic,
+        );
+        let exchange = Exchange::from_env();
+        assert!(
+            comm.supported_exchanges().contains(&exchange),
+            "{EXCHANGE_ENV}: {exchange} exchange is not supported by this communicator, \
+             expect one of {:?}.",
+            comm.supported_exchanges()
+        );
         Self {
-            universe,
             comm,
-            // n_global,
-            dists,
-            axis_contig,
+            subcomms,
+            layout,
+            order: Order::RowMajor,
+            exchange,
+            stats: None,
         }
     }
+}
 
-    /// Gets the coordinate of a process in a communicator that has a cartesian topology.
+impl<const M: usize, const N: usize, C> Pencil<M, N, C> {
+    /// Set preferred memory order of pencil distributed arrays
     ///
-    /// # Panics
-    /// Must be of size *N*
+    /// Transposes are fastest, if arrays are stored in this order.
+    /// For example, a x-pencil in ``Order::ColumnMajor`` and a z-pencil in
+    /// ``Order::RowMajor`` have their contiguous axis also fastest
+    /// varying in memory.
     #[must_use]
-    pub fn cart_coords(&self) -> Vec<i32> {
-        let CartesianLayout { coords, .. } = self.comm.get_layout();
-        assert!(coords.len() == N);
-        coords
+    pub fn with_order(mut self, order: Order) -> Self {
+        self.order = order;
+        self
     }
 
-    /// Gets integer array of size ndims specifying the number of
-    /// processes in each dimension
-    ///
-    /// # Panics
-    /// Int conversion
+    /// Set algorithm of all-to-all exchanges in transposes from this pencil
     #[must_use]
-    pub fn cart_dims(&self) -> Vec<i32> {
-        let CartesianLayout { dims, .. } = self.comm.get_layout();
-        assert!(dims.len() == N);
-        dims
+    pub fn with_exchange(mut self, exchange: Exchange) -> Self {
+        self.exchange = exchange;
+        self
     }
 
-    /// Gets logical array of size ndims specifying whether the grid is periodic
-    ///
-    /// # Panics
-    /// Int conversion
+    /// Record statistics of communication routines of this pencil
+    /// in ``stats``, see [`crate::stats`]
     #[must_use]
-    pub fn cart_periodic(&self) -> Vec<bool> {
-        let CartesianLayout { periods, .. } = self.comm.get_layout();
-        assert!(periods.len() == N);
-        periods
+    pub fn with_stats(mut self, stats: Arc<Stats>) -> Self {
+        self.stats = Some(stats);
+        self
     }
 
-    /// Maps physical dimension to cartesian topology dimension
-    ///
-    /// For example, if contiguos axis is 1, then
-    /// ``dim`` = 0 -> ``cart_dim`` = 0,
-    /// ``dim`` = 2 -> ``cart_dim`` = 1.
-    ///
-    /// # Panics
-    /// - If *dim* equals contiguos axis
-    /// - If *dim* is larger than *M*
-    fn map_dim_to_cart_dim(&self, dim: usize) -> usize {
-        assert!(dim < M);
-        match dim.cmp(&self.axis_contig) {
-            std::cmp::Ordering::Less => dim,
-            std::cmp::Ordering::Greater => dim - 1,
-            std::cmp::Ordering::Equal => panic!("dim must differ from axis_contig"),
+    /// Statistics of communication routines, if enabled
+    #[must_use]
+    pub fn stats(&self) -> Option<&Arc<Stats>> {
+        self.stats.as_ref()
+    }
+
+    /// Record statistics of one call to ``op``, if enabled
+    pub(crate) fn record(&self, op: impl FnOnce() -> String, stats: OpStats) {
+        if let Some(s) = &self.stats {
+            s.record(&op(), stats);
         }
     }
 
-    /// Return number of splits / processors along a certain dimension(axis)
+    /// Communicator-free layout of this pencil on current processor
     #[must_use]
-    pub fn nprocs_along_axis(&self, axis: usize) -> i32 {
-        let cart_dim = self.map_dim_to_cart_dim(axis);
-        self.cart_dims()[cart_dim]
+    pub fn layout(&self) -> &PencilLayout<M> {
+        &self.layout
     }
 
     /// Return communicator defining sub-groups for ALLTOALL(V)
@@ -177,176 +235,714 @@ impl<'a, const M: usize, const N: usize> Pencil<'a, M, N> {
     /// *dim* must be different from contiguous axis, cartesian
     /// communicator only communicates between split dimensions
     #[must_use]
-    pub fn subcomm_along_axis(&self, axis: usize) -> CartesianCommunicator {
+    pub fn subcomm_along_axis(&self, axis: usize) -> &C {
         let cart_dim = self.map_dim_to_cart_dim(axis);
-        let mut retain = [false; N];
-        retain[cart_dim] = true;
-        self.comm.subgroup(&retain)
-    }
-
-    /// Return the total length of data hold by current processor
-    #[must_use]
-    pub fn len(&self) -> usize {
-        self.shape().iter().product()
-    }
-
-    /// Return the total length of data hold by current processor
-    #[must_use]
-    pub fn len_global(&self) -> usize {
-        self.shape_global().iter().product()
-    }
-
-    /// Returns true if self has a length of zero.
-    #[must_use]
-    pub fn is_empty(&self) -> bool {
-        self.shape().iter().product::<usize>() == 0
+        &self.subcomms[cart_dim]
     }
+}
 
-    /// Shape of pencil distributed data
-    ///
-    /// # Panics
-    /// Vector to array conversion fails
-    #[must_use]
-    pub fn shape(&self) -> [usize; M] {
-        self.dists
-            .iter()
-            .map(|x| x.sz)
-            .collect::<Vec<usize>>()
-            .try_into()
-            .unwrap()
-    }
+impl<const M: usize, const N: usize, C> Deref for Pencil<M, N, C> {
+    type Target = PencilLayout<M>;
 
-    /// Shape of global data
-    ///
-    /// # Panics
-    /// Vector to array conversion fails
-    #[must_use]
-    pub fn shape_global(&self) -> [usize; M] {
-        self.dists
-            .iter()
-            .map(|x| x.sz_procs.iter().sum())
-            .collect::<Vec<usize>>()
-            .try_into()
-            .unwrap()
+    fn deref(&self) -> &PencilLayout<M> {
+        &self.layout
     }
 }
 
 /// Transpose between pencils
 ///
 /// See for example [`pencil_decomp::decomp3::transpose_x_to_y`]
-pub(crate) fn transpose<S, R, T, Split, Merge, const M: usize, const N: usize>(
-    send_pencil: &Pencil<M, N>,
-    recv_pencil: &Pencil<M, N>,
-    snd: &S,
-    rcv: &mut R,
-    split: Split,
-    merge: Merge,
+pub(crate) fn transpose<S1, S2, T, D, C, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    recv_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    rcv: &mut ArrayBase<S2, D>,
 ) where
-    T: Zero + Copy + Equivalence,
-    Split: Fn(&S, &mut [T], &Pencil<M, N>, &Pencil<M, N>),
-    Merge: Fn(&[T], &mut R, &Pencil<M, N>, &Pencil<M, N>),
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
 {
     assert!(send_pencil.axis_contig != recv_pencil.axis_contig);
 
     // send & receive buffer
+    let mut timer = Timer::start();
     let mut send_buf = vec![T::zero(); send_pencil.len()];
     let mut recv_buf = vec![T::zero(); recv_pencil.len()];
     split(snd, &mut send_buf, send_pencil, recv_pencil);
+    let pack = timer.lap();
 
     let (send_counts, send_displs) = send_counts_all_to_all(send_pencil, recv_pencil);
     let (recv_counts, recv_displs) = recv_counts_all_to_all(send_pencil, recv_pencil);
     let comm = send_pencil.subcomm_along_axis(recv_pencil.axis_contig);
-    {
-        let send_buffer = Partition::new(&send_buf[..], &send_counts[..], &send_displs[..]);
-        let mut recv_buffer =
-            PartitionMut::new(&mut recv_buf[..], &recv_counts[..], &recv_displs[..]);
-        comm.all_to_all_varcount_into(&send_buffer, &mut recv_buffer);
-    }
+    comm.all_to_all_varcount_with(
+        send_pencil.exchange,
+        &send_buf,
+        &send_counts,
+        &send_displs,
+        &mut recv_buf,
+        &recv_counts,
+        &recv_displs,
+    );
+    let exchange = timer.lap();
 
     // copy receive buffer into array
     merge(&recv_buf, rcv, send_pencil, recv_pencil);
+    let unpack = timer.lap();
+
+    let op = || transpose_name(send_pencil, recv_pencil);
+    send_pencil.record(
+        op,
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_buf.len()),
+            bytes_recv: bytes::<T>(recv_buf.len()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Name of axis in statistics
+pub(crate) fn axis_name(axis: usize) -> String {
+    ["x", "y", "z"]
+        .get(axis)
+        .map_or_else(|| axis.to_string(), ToString::to_string)
+}
+
+/// Name of transpose in statistics
+fn transpose_name<C, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    recv_pencil: &Pencil<M, N, C>,
+) -> String {
+    let (a, b) = (send_pencil.axis_contig, recv_pencil.axis_contig);
+    format!("transpose {}->{}", axis_name(a), axis_name(b))
+}
+
+/// Size of ``len`` elements of type ``T`` in bytes
+pub(crate) fn bytes<T>(len: usize) -> u64 {
+    (len * std::mem::size_of::<T>()).try_into().unwrap()
+}
+
+/// Transpose between any two pencils of the same global grid
+/// with ``mpi_alltoallw``
+///
+/// Each block is described by a subarray datatype, so data moves
+/// from ``snd`` directly into ``rcv``. Unlike [`transpose`], the
+/// pencils may split the same axis over different numbers of
+/// processors, for example x- and z-pencils of [`crate::Decomp3`].
+/// Arrays which are not stored in the order of ``send_pencil``
+/// are copied into a temporary array of that order.
+///
+/// The communicators of both pencils must hold the same processors
+/// in the same order.
+///
+/// # Panics
+/// - Global shapes or processor counts of pencils differ
+/// - Shape of ``snd`` or ``rcv`` does not match its pencil
+pub fn transpose_w<S1, S2, T, D, C, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    recv_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Element,
+    D: Dimension,
+    C: Comm,
+{
+    let op = || format!("{} (w)", transpose_name(send_pencil, recv_pencil));
+    exchange_w(send_pencil, recv_pencil, snd, rcv, op);
 }
 
-/// Gather pencil along axis into root
+/// Redistribute data between pencils of two different
+/// decompositions of the same global grid
+///
+/// For example, move a field from a solver on a 2 x 3 processor
+/// grid to an analysis on a 3 x 2 grid. Each processor exchanges
+/// exactly the intersections of its blocks with the blocks of the
+/// other layout, see [`crate::layout::intersection`]. The pencils
+/// may have the same or different contiguous axes.
+///
+/// The communicators of both pencils must hold the same processors
+/// in the same order.
+///
+/// # Example
+/// ```
+/// use ndarray::Array3;
+/// use pencil_decomp::pencil::redistribute;
+/// use pencil_decomp::{Decomp3, ThreadComm};
+///
+/// ThreadComm::run(6, |comm| {
+///     let solver = Decomp3::from_comm(&comm, [6, 7, 9], [2, 3], [false, false]);
+///     let analysis = Decomp3::from_comm(&comm, [6, 7, 9], [3, 2], [false, false]);
+///     let src = Array3::<f64>::zeros(solver.z_pencil.shape());
+///     let mut dst = Array3::zeros(analysis.x_pencil.shape());
+///     redistribute(&solver.z_pencil, &src, &analysis.x_pencil, &mut dst);
+/// });
+/// ```
 ///
-/// See for example [`pencil_decomp::decomp2::gather_x`]
-pub(crate) fn gather_into_root_along_axis<S, R, T, Split, Merge, const M: usize, const N: usize>(
-    pencil: &Pencil<M, N>,
+/// # Panics
+/// - Global shapes or processor counts of pencils differ
+/// - Shape of ``src`` or ``dst`` does not match its pencil
+pub fn redistribute<S1, S2, T, D, C, const M: usize, const N: usize>(
+    src_pencil: &Pencil<M, N, C>,
+    src: &ArrayBase<S1, D>,
+    dst_pencil: &Pencil<M, N, C>,
+    dst: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Element,
+    D: Dimension,
+    C: Comm,
+{
+    let op = || {
+        let names = [src_pencil.axis_contig, dst_pencil.axis_contig].map(axis_name);
+        format!("redistribute {}->{}", names[0], names[1])
+    };
+    exchange_w(src_pencil, dst_pencil, src, dst, op);
+}
+
+/// Exchange intersecting blocks of ``send_pencil`` and ``recv_pencil``
+/// with ``mpi_alltoallw``, see [`transpose_w`]
+fn exchange_w<S1, S2, T, D, C, F, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    recv_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    rcv: &mut ArrayBase<S2, D>,
+    op: F,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Element,
+    D: Dimension,
+    C: Comm,
+    F: FnOnce() -> String,
+{
+    assert!(
+        send_pencil.shape_global() == recv_pencil.shape_global(),
+        "Global shape mismatch of pencils."
+    );
+    assert!(snd.shape() == send_pencil.shape(), "Shape mismatch of snd.");
+    assert!(rcv.shape() == recv_pencil.shape(), "Shape mismatch of rcv.");
+    let comm = &send_pencil.comm;
+    assert!(
+        comm.size() == recv_pencil.comm.size(),
+        "Size mismatch of comms."
+    );
+    let order = send_pencil.order;
+    let rank = comm.rank();
+
+    // Intersection of own and other blocks, relative to own block
+    let own_send = send_pencil.global_ranges(rank);
+    let own_recv = recv_pencil.global_ranges(rank);
+    let send_blocks: Vec<Option<Subarray>> = (0..comm.size())
+        .map(|r| intersect(&own_send, &recv_pencil.global_ranges(r), order))
+        .collect();
+    let recv_blocks: Vec<Option<Subarray>> = (0..comm.size())
+        .map(|r| intersect(&own_recv, &send_pencil.global_ranges(r), order))
+        .collect();
+
+    // Arrays must be contiguous in the order of the send pencil
+    let mut timer = Timer::start();
+    let snd_tmp;
+    let snd = if let Some(s) = as_slice_in_order(snd, order) {
+        s
+    } else {
+        snd_tmp = to_vec_in_order(snd, order);
+        &snd_tmp
+    };
+    let pack = timer.lap();
+    let (exchange, unpack);
+    if let Some(r) = as_slice_mut_in_order(rcv, order) {
+        comm.all_to_all_w(snd, &send_blocks, r, &recv_blocks);
+        (exchange, unpack) = (timer.lap(), 0.);
+    } else {
+        let mut rcv_tmp = to_vec_in_order(rcv, order);
+        comm.all_to_all_w(snd, &send_blocks, &mut rcv_tmp, &recv_blocks);
+        exchange = timer.lap();
+        assign_in_order(rcv, rcv_tmp, order);
+        unpack = timer.lap();
+    }
+
+    let block_len = |b: &Subarray| b.subsizes.iter().product::<usize>();
+    send_pencil.record(
+        op,
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_blocks.iter().flatten().map(block_len).sum()),
+            bytes_recv: bytes::<T>(recv_blocks.iter().flatten().map(block_len).sum()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Intersection of ``own`` and ``other`` global ranges, as block
+/// of the array which holds ``own``
+pub(crate) fn intersect<const M: usize>(
+    own: &[Range<usize>; M],
+    other: &[Range<usize>; M],
+    order: Order,
+) -> Option<Subarray> {
+    intersection(own, other).map(|block| Subarray {
+        sizes: own.iter().map(ExactSizeIterator::len).collect(),
+        subsizes: block.iter().map(ExactSizeIterator::len).collect(),
+        starts: block
+            .iter()
+            .zip(own.iter())
+            .map(|(b, a)| b.start - a.start)
+            .collect(),
+        order,
+    })
+}
+
+/// Memory of ``data``, if it is contiguous in ``order``
+pub(crate) fn as_slice_in_order<S: Data, D: Dimension>(
+    data: &ArrayBase<S, D>,
+    order: Order,
+) -> Option<&[S::Elem]> {
+    if order == Order::ColumnMajor {
+        data.view().reversed_axes().to_slice()
+    } else {
+        data.as_slice()
+    }
+}
+
+/// Mutable memory of ``data``, if it is contiguous in ``order``
+fn as_slice_mut_in_order<S: DataMut, D: Dimension>(
+    data: &mut ArrayBase<S, D>,
+    order: Order,
+) -> Option<&mut [S::Elem]> {
+    if order == Order::ColumnMajor {
+        data.view_mut().reversed_axes().into_slice()
+    } else {
+        data.as_slice_mut()
+    }
+}
+
+/// Copy elements of ``data`` in ``order``
+pub(crate) fn to_vec_in_order<S, T, D>(data: &ArrayBase<S, D>, order: Order) -> Vec<T>
+where
+    S: Data<Elem = T>,
+    T: Copy,
+    D: Dimension,
+{
+    if order == Order::ColumnMajor {
+        data.t().iter().copied().collect()
+    } else {
+        data.iter().copied().collect()
+    }
+}
+
+/// Copy ``values`` into elements of ``data`` in ``order``
+fn assign_in_order<S, T, D>(data: &mut ArrayBase<S, D>, values: Vec<T>, order: Order)
+where
+    S: DataMut<Elem = T>,
+    D: Dimension,
+{
+    let mut view = data.view_mut();
+    if order == Order::ColumnMajor {
+        view = view.reversed_axes();
+    }
+    view.iter_mut().zip(values).for_each(|(x, v)| *x = v);
+}
+
+/// Gather pencil along axis into processor ``root`` of the
+/// sub-communicator along axis
+///
+/// See for example [`crate::Decomp2::gather_x_to`]
+pub(crate) fn gather_into_root_along_axis<
+    S,
+    R,
+    T,
+    C,
+    Split,
+    Merge,
+    const M: usize,
+    const N: usize,
+>(
+    pencil: &Pencil<M, N, C>,
+    root_rank: Rank,
     snd: &S,
     rcv: &mut R,
     axis: usize,
     split: Split,
     merge: Merge,
 ) where
-    T: Zero + Copy + Equivalence,
+    T: Zero + Element,
+    C: Comm,
     Split: Fn(&S, &mut [T]),
     Merge: Fn(&[T], &mut R),
 {
     // Asserts are handled in `recv_counts_gather_axis`
 
-    let root_rank = 0;
     let comm = pencil.subcomm_along_axis(axis);
-    let root_process = comm.process_at_rank(root_rank);
+    assert_root(comm, root_rank);
 
+    let mut timer = Timer::start();
     let mut send_buf = vec![T::zero(); pencil.len()];
     split(snd, &mut send_buf);
+    let pack = timer.lap();
+    let (exchange, unpack, recv_len);
     if comm.rank() == root_rank {
         let mut recv_buf = vec![T::zero(); pencil.len_global()];
 
         let (counts, displs) = recv_counts_gather_axis(pencil, axis);
-        {
-            let mut partition = PartitionMut::new(&mut recv_buf[..], &counts[..], &displs[..]);
-            root_process.gather_varcount_into_root(&send_buf[..], &mut partition);
-        }
+        comm.gather_varcount(root_rank, &send_buf, &mut recv_buf, &counts, &displs);
+        exchange = timer.lap();
         // copy receive buffer into array
         merge(&recv_buf, rcv);
+        (unpack, recv_len) = (timer.lap(), recv_buf.len());
     } else {
-        root_process.gather_varcount_into(&send_buf[..]);
+        comm.gather_varcount(root_rank, &send_buf, &mut [], &[], &[]);
+        (exchange, unpack, recv_len) = (timer.lap(), 0., 0);
     }
+
+    pencil.record(
+        || format!("gather {}", axis_name(pencil.axis_contig)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_buf.len()),
+            bytes_recv: bytes::<T>(recv_len),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
 }
 
-/// Gather pencil along axis into root
+/// Scatter pencil along axis from processor ``root`` of the
+/// sub-communicator along axis
 ///
-/// See for example [`pencil_decomp::decomp2::gather_x`]
-pub(crate) fn scatter_along_axis<S, R, T, Split, Merge, const M: usize, const N: usize>(
-    pencil: &Pencil<M, N>,
+/// See for example [`crate::Decomp2::scatter_x_from`]
+pub(crate) fn scatter_along_axis<S, R, T, C, Split, Merge, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    root_rank: Rank,
     snd: &S,
     rcv: &mut R,
     axis: usize,
     split: Split,
     merge: Merge,
 ) where
-    T: Zero + Copy + Equivalence,
+    T: Zero + Element,
+    C: Comm,
     Split: Fn(&S, &mut [T]),
     Merge: Fn(&[T], &mut R),
 {
     // Asserts are handled in sub-functions
 
-    let root_rank = 0;
     let comm = pencil.subcomm_along_axis(axis);
-    let root_process = comm.process_at_rank(root_rank);
+    assert_root(comm, root_rank);
 
     // recv buffer
+    let mut timer = Timer::start();
     let mut recv_buf = vec![T::zero(); pencil.len()];
 
+    let (pack, send_len);
     if comm.rank() == root_rank {
         // send buffer
         let mut send_buf = vec![T::zero(); pencil.len_global()];
         split(snd, &mut send_buf);
+        (pack, send_len) = (timer.lap(), send_buf.len());
 
         let (counts, displs) = recv_counts_gather_axis(pencil, axis);
-        {
-            let partition = Partition::new(&send_buf[..], &counts[..], &displs[..]);
-            root_process.scatter_varcount_into_root(&partition, &mut recv_buf[..]);
-        }
+        comm.scatter_varcount(root_rank, &send_buf, &counts, &displs, &mut recv_buf);
     } else {
-        root_process.scatter_varcount_into(&mut recv_buf[..]);
+        (pack, send_len) = (timer.lap(), 0);
+        comm.scatter_varcount(root_rank, &[], &[], &[], &mut recv_buf);
     }
+    let exchange = timer.lap();
     // copy receive buffer into array
     merge(&recv_buf, rcv);
+    let unpack = timer.lap();
+
+    pencil.record(
+        || format!("scatter {}", axis_name(pencil.axis_contig)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_len),
+            bytes_recv: bytes::<T>(recv_buf.len()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// All-gather pencil on all processors
+///
+/// Distributed axes are merged one after another with
+/// ``mpi_allgatherv`` within the sub-communicator along each axis.
+///
+/// See for example [`crate::Decomp3::allgather_x`]
+pub(crate) fn all_gather<S1, S2, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let n_global = pencil.shape_global();
+    assert_eq!(snd.shape(), pencil.shape(), "Shape mismatch of snd.");
+    assert_eq!(rcv.shape(), n_global, "Shape mismatch of rcv.");
+
+    let mut timer = Timer::start();
+    let (mut pack, mut exchange, mut unpack) = (0., 0., 0.);
+    let (mut send_len, mut recv_len) = (0, 0);
+    let mut layout = pencil.layout().clone();
+    let mut data = snd.view().into_dyn().to_owned();
+    for axis in (0..M).filter(|&a| a != pencil.axis_contig) {
+        // Blocks are stored with the gathered axis outermost
+        let mut perm: Vec<usize> = (0..M).collect();
+        perm.swap(0, axis);
+        let send_buf: Vec<T> = data
+            .view()
+            .permuted_axes(&perm[..])
+            .iter()
+            .copied()
+            .collect();
+        let (counts, displs) = recv_counts_gather_axis(&layout, axis);
+        let mut recv_buf = vec![T::zero(); gather_counts(&layout, axis).iter().sum()];
+        pack += timer.lap();
+
+        let comm = pencil.subcomm_along_axis(axis);
+        comm.all_gather_varcount(&send_buf, &mut recv_buf, &counts, &displs);
+        exchange += timer.lap();
+
+        let dist = &layout.dists[axis];
+        let mut shape = data.shape().to_vec();
+        shape[axis] = n_global[axis];
+        let mut merged = ndarray::ArrayD::zeros(shape);
+        for (np, block) in ranges(&counts, &displs).into_iter().enumerate() {
+            merged
+                .slice_axis_mut(
+                    ndarray::Axis(axis),
+                    (dist.st_procs[np]..=dist.en_procs[np]).into(),
+                )
+                .permuted_axes(&perm[..])
+                .iter_mut()
+                .zip(recv_buf[block].iter())
+                .for_each(|(x, v)| *x = *v);
+        }
+        (send_len, recv_len) = (send_len + send_buf.len(), recv_len + recv_buf.len());
+        layout.dists[axis] = Distribution::contiguous(n_global[axis]);
+        data = merged;
+        unpack += timer.lap();
+    }
+    rcv.view_mut().into_dyn().assign(&data);
+    unpack += timer.lap();
+
+    pencil.record(
+        || format!("allgather {}", axis_name(pencil.axis_contig)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_len),
+            bytes_recv: bytes::<T>(recv_len),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Gather the global index ``ranges`` of pencil distributed data
+/// into ``rcv`` on processor ``root``
+///
+/// Only processors which hold part of ``ranges`` send data, see
+/// [`PencilLayout::owners`]. ``rcv`` has the shape of ``ranges``
+/// and is only used on ``root``, other processors may pass an empty array.
+///
+/// # Panics
+/// - Shape of ``snd`` does not match ``pencil``
+/// - Shape of ``rcv`` does not match ``ranges`` on ``root``
+/// - ``ranges`` are empty or exceed the global grid
+/// - ``root`` is outside of the communicator
+pub fn gather_subvolume<S1, S2, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    root: Rank,
+    snd: &ArrayBase<S1, D>,
+    ranges: &[Range<usize>; M],
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let n_global = pencil.shape_global();
+    assert!(
+        ranges
+            .iter()
+            .zip(n_global.iter())
+            .all(|(r, &n)| r.start < r.end && r.end <= n),
+        "Ranges {ranges:?} empty or outside of global grid {n_global:?}."
+    );
+    let shape: Vec<usize> = ranges.iter().map(ExactSizeIterator::len).collect();
+    let block = |held: &[Range<usize>; M]| {
+        intersection(held, ranges).map(|block| {
+            let src = (0..M)
+                .map(|i| Slice::from(block[i].start - held[i].start..block[i].end - held[i].start))
+                .collect();
+            let dst = (0..M)
+                .map(|i| block[i].start - ranges[i].start..block[i].end - ranges[i].start)
+                .collect();
+            (src, dst)
+        })
+    };
+    gather_blocks(pencil, root, snd, &shape, rcv, block, "gather subvolume");
+}
+
+/// Gather every ``strides[i]``-th grid point along axis *i* of
+/// pencil distributed data into ``rcv`` on processor ``root``
+///
+/// Each processor sends only the strided grid points it holds,
+/// see [`Distribution::strided`]. ``rcv`` holds the global grid
+/// points at multiples of ``strides``, it has the shape
+/// ``n_global[i].div_ceil(strides[i])`` and is only used on ``root``,
+/// other processors may pass an empty array.
+///
+/// # Panics
+/// - Shape of ``snd`` does not match ``pencil``
+/// - Shape of ``rcv`` does not match the downsampled grid on ``root``
+/// - A stride is zero
+/// - ``root`` is outside of the communicator
+pub fn gather_strided<S1, S2, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    root: Rank,
+    snd: &ArrayBase<S1, D>,
+    strides: [usize; M],
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+{
+    assert!(strides.iter().all(|&k| k > 0), "Strides must be positive.");
+    let shape: Vec<usize> = pencil
+        .shape_global()
+        .iter()
+        .zip(strides.iter())
+        .map(|(n, k)| n.div_ceil(*k))
+        .collect();
+    let block = |held: &[Range<usize>; M]| {
+        let coarse: Vec<Range<usize>> = (0..M)
+            .map(|i| strided_range(&held[i], strides[i]))
+            .collect();
+        coarse.iter().all(|r| !r.is_empty()).then(|| {
+            let src = (0..M)
+                .map(|i| {
+                    let st = coarse[i].start * strides[i] - held[i].start;
+                    let en = st + (coarse[i].len() - 1) * strides[i] + 1;
+                    Slice::new(
+                        st.try_into().unwrap(),
+                        Some(en.try_into().unwrap()),
+                        strides[i].try_into().unwrap(),
+                    )
+                })
+                .collect();
+            (src, coarse)
+        })
+    };
+    gather_blocks(pencil, root, snd, &shape, rcv, block, "gather strided");
+}
+
+/// Gather blocks of pencil distributed data into ``rcv`` of ``shape``
+/// on processor ``root``
+///
+/// ``block`` maps the global ranges held by a processor to the
+/// slices of its data which are sent and the ranges of ``rcv``
+/// they are received in, ``None`` if it sends nothing.
+fn gather_blocks<S1, S2, T, D, C, F, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    root: Rank,
+    snd: &ArrayBase<S1, D>,
+    shape: &[usize],
+    rcv: &mut ArrayBase<S2, D>,
+    block: F,
+    op: &str,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+    F: Fn(&[Range<usize>; M]) -> Option<(Vec<Slice>, Vec<Range<usize>>)>,
+{
+    assert!(snd.shape() == pencil.shape(), "Shape mismatch of snd.");
+    let comm = &pencil.comm;
+    assert_root(comm, root);
+    let rank = comm.rank();
+    assert!(
+        rank != root || rcv.shape() == shape,
+        "Shape mismatch of rcv, expect {shape:?}."
+    );
+
+    let mut timer = Timer::start();
+    let send_buf: Vec<T> = block(&pencil.global_ranges(rank)).map_or_else(Vec::new, |(src, _)| {
+        snd.slice_each_axis(|ax| src[ax.axis.index()])
+            .iter()
+            .copied()
+            .collect()
+    });
+    let pack = timer.lap();
+
+    let (exchange, unpack, recv_len);
+    if rank == root {
+        let dsts: Vec<Option<Vec<Range<usize>>>> = (0..comm.size())
+            .map(|r| block(&pencil.global_ranges(r)).map(|(_, dst)| dst))
+            .collect();
+        let counts: Vec<usize> = dsts
+            .iter()
+            .map(|d| {
+                d.as_ref()
+                    .map_or(0, |d| d.iter().map(ExactSizeIterator::len).product())
+            })
+            .collect();
+        let (counts, displs) = counts_and_displs(&counts);
+        let mut recv_buf = vec![T::zero(); shape.iter().product()];
+        comm.gather_varcount(root, &send_buf, &mut recv_buf, &counts, &displs);
+        exchange = timer.lap();
+        for (dst, buf) in dsts.iter().zip(ranges(&counts, &displs)) {
+            if let Some(dst) = dst {
+                rcv.slice_each_axis_mut(|ax| Slice::from(dst[ax.axis.index()].clone()))
+                    .iter_mut()
+                    .zip(recv_buf[buf].iter())
+                    .for_each(|(x, v)| *x = *v);
+            }
+        }
+        (unpack, recv_len) = (timer.lap(), recv_buf.len());
+    } else {
+        comm.gather_varcount(root, &send_buf, &mut [], &[], &[]);
+        (exchange, unpack, recv_len) = (timer.lap(), 0., 0);
+    }
+
+    pencil.record(
+        || format!("{op} {}", axis_name(pencil.axis_contig)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_buf.len()),
+            bytes_recv: bytes::<T>(recv_len),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
 }
 
-/// Returns send counts and displs from two ``Pencil`` for
+/// Returns send counts and displs from two pencil layouts for
 /// mpis ``mpi_all_to_allv`` routine
 ///
 /// To get recv counts and displs, reverse order of send & recv
@@ -354,47 +950,60 @@ pub(crate) fn scatter_along_axis<S, R, T, Split, Merge, const M: usize, const N:
 ///
 /// # Panics
 /// - send and recv pencil must not have same contiguous axis
-/// - transpose cant be done with ``all_to_all_v``, use ``all_to_all_w`` instead (not implemented)
+/// - transpose cant be done with ``all_to_all_v``, use [`transpose_w`] instead
 /// - i32 to usize conversion fails
 #[must_use]
-pub fn send_counts_all_to_all<const M: usize, const N: usize>(
-    send: &Pencil<M, N>,
-    recv: &Pencil<M, N>,
+pub fn send_counts_all_to_all<const M: usize>(
+    send: &PencilLayout<M>,
+    recv: &PencilLayout<M>,
 ) -> (Vec<Count>, Vec<Count>) {
+    counts_and_displs(&send_counts(send, recv))
+}
+
+/// Number of elements sent to each processor of the sub-communicator
+/// along the contiguous axis of ``recv``, see [`send_counts_all_to_all`]
+///
+/// # Panics
+/// - send and recv pencil must not have same contiguous axis
+/// - transpose cant be done with ``all_to_all_v``
+#[must_use]
+pub fn send_counts<const M: usize>(send: &PencilLayout<M>, recv: &PencilLayout<M>) -> Vec<usize> {
     assert!(
         send.axis_contig != recv.axis_contig,
         "Expect pencils with different contiguous axes."
     );
-    let nprocs = send.nprocs_along_axis(recv.axis_contig);
-    // counts
-    let counts = (0..nprocs.try_into().unwrap())
+    let (send_axis, recv_axis) = (send.axis_contig, recv.axis_contig);
+    let (send, recv) = (&send.dists, &recv.dists);
+    let nprocs = send[recv_axis].sz_procs.len();
+    (0..nprocs)
         .map(|np| {
             let mut count = 1;
             for i in 0..M {
-                if i != send.axis_contig && i != recv.axis_contig {
+                if i != send_axis && i != recv_axis {
                     // Both pencils are split.
                     // Check if they are split in the same way,
                     // otherwise ``all_to_all_v`` wont work
                     assert!(
-                        send.dists[i].sz == recv.dists[i].sz,
-                        "unable to get send counts. Maybe you need to use manual all_to_all_w."
+                        send[i].sz == recv[i].sz,
+                        "unable to get send counts. Maybe you need to use transpose_w."
                     );
-                    //count *= send.dists[i].sz_procs[np];
-                    count *= send.dists[i].sz;
-                } else if i == recv.axis_contig {
+                    count *= send[i].sz;
+                } else if i == recv_axis {
                     // Send is split, recv is contiguous
-                    // count *= send.dists[i].sz_procs[np];
-                    count *= send.dists[i].sz;
+                    count *= send[i].sz;
                 } else {
                     // Recv is split, send is contiguous
-                    count *= recv.dists[i].sz_procs[np];
+                    count *= recv[i].sz_procs[np];
                 }
             }
-            count.try_into().unwrap()
+            count
         })
-        .collect::<Vec<Count>>();
+        .collect()
+}
 
-    // displacements
+/// Convert counts to mpi counts and displacements
+pub(crate) fn counts_and_displs(counts: &[usize]) -> (Vec<Count>, Vec<Count>) {
+    let counts: Vec<Count> = counts.iter().map(|&c| c.try_into().unwrap()).collect();
     let displs: Vec<Count> = counts
         .iter()
         .scan(0, |acc, &x| {
@@ -411,9 +1020,9 @@ pub fn send_counts_all_to_all<const M: usize, const N: usize>(
 /// Just calls [`send_counts_all_to_all`] with reversed
 /// send/recv order
 #[must_use]
-pub fn recv_counts_all_to_all<const M: usize, const N: usize>(
-    send: &Pencil<M, N>,
-    recv: &Pencil<M, N>,
+pub fn recv_counts_all_to_all<const M: usize>(
+    send: &PencilLayout<M>,
+    recv: &PencilLayout<M>,
 ) -> (Vec<Count>, Vec<Count>) {
     send_counts_all_to_all(recv, send)
 }
@@ -429,9 +1038,10 @@ pub fn recv_counts_all_to_all<const M: usize, const N: usize>(
 /// ```ìgnore
 /// use mpi::topology::Communicator;
 /// use pencil_decomp::pencil::{Pencil, recv_counts_gather_axis};
+/// use std::sync::Arc;
 ///
 /// // Init Mpi
-/// let universe = mpi::initialize().unwrap();
+/// let universe = Arc::new(mpi::initialize().unwrap());
 /// let world = universe.world();
 /// assert!(world.size() == 2, "Run with 2 processors");
 ///
@@ -450,38 +1060,34 @@ pub fn recv_counts_all_to_all<const M: usize, const N: usize>(
 /// }
 /// ```
 #[must_use]
-pub fn recv_counts_gather_axis<const M: usize, const N: usize>(
-    pencil: &Pencil<M, N>,
+pub fn recv_counts_gather_axis<const M: usize>(
+    pencil: &PencilLayout<M>,
     axis: usize,
 ) -> (Vec<Count>, Vec<Count>) {
-    assert!(pencil.axis_contig != axis, "Axis {} is already cont.", axis);
-    assert!(axis < M, "Axis {} outside array dimensions {}.", M, axis);
-
-    // Number of procs along dimension
-    let nprocs = pencil.nprocs_along_axis(axis);
+    counts_and_displs(&gather_counts(pencil, axis))
+}
 
-    let counts = (0..nprocs.try_into().unwrap())
+/// Number of elements gathered from each processor along ``axis``,
+/// see [`recv_counts_gather_axis`]
+///
+/// # Panics
+/// - axis is already merged (``axis == axis_contig ``)
+#[must_use]
+pub fn gather_counts<const M: usize>(pencil: &PencilLayout<M>, axis: usize) -> Vec<usize> {
+    assert!(pencil.axis_contig != axis, "Axis {axis} is already cont.");
+    assert!(axis < M, "Axis {axis} outside array dimensions {M}.");
+    let dists = &pencil.dists;
+    (0..dists[axis].sz_procs.len())
         .map(|np| {
             let mut count = 1;
-            for i in 0..M {
+            for (i, dist) in dists.iter().enumerate() {
                 if i == axis {
-                    count *= pencil.dists[i].sz_procs[np];
+                    count *= dist.sz_procs[np];
                 } else {
-                    count *= pencil.dists[i].sz;
+                    count *= dist.sz;
                 }
             }
-            count.try_into().unwrap()
-        })
-        .collect::<Vec<Count>>();
-
-    let displs: Vec<Count> = counts
-        .iter()
-        .scan(0, |acc, &x| {
-            let tmp = *acc;
-            *acc += x;
-            Some(tmp)
+            count
         })
-        .collect();
-
-    (counts, displs)
+        .collect()
 }
@@ -0,0 +1,322 @@
+//! # Planning of decompositions
+//!
+//! Estimate memory and communication volume of a decomposition
+//! before running it. No communicator is needed, all processors
+//! of the cartesian topology are planned at once.
+//!
+//! # Example
+//! ```
+//! use pencil_decomp::plan::Plan;
+//!
+//! // 4 x 8 processors, double precision
+//! let plan = Plan::new([256, 256, 128], [4, 8], 8);
+//! assert_eq!(plan.nprocs(), 32);
+//! assert_eq!(plan.pencil(0, 0).shape, [256, 64, 16]);
+//!
+//! let largest = plan.largest_pencil();
+//! assert_eq!(largest.bytes, 256 * 64 * 16 * 8);
+//!
+//! // x -> y transpose exchanges data within groups of 4 processors
+//! let x_to_y = plan.transpose(0, 1);
+//! assert_eq!(x_to_y.send_buf[0], largest.bytes);
+//! assert_eq!(x_to_y.messages(0), 3);
+//! ```
+use crate::layout::{rank_to_coords, PencilLayout};
+use crate::pencil::send_counts;
+use std::ops::Range;
+
+/// Decomposition of a grid over a cartesian topology of processors
+///
+/// *M* number of grid dimensions.
+/// *N* specifies number of dimension of the cartesian topology,
+/// Currently restricted to *N* = *M* - 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Plan<const M: usize, const N: usize> {
+    /// Total number of grid points
+    pub n_global: [usize; M],
+    /// Number of processors along each dimension of the cartesian topology
+    pub cart_dims: [i32; N],
+    /// Size of one element in bytes
+    pub elem_size: usize,
+}
+
+/// Pencil of one processor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PencilPlan<const M: usize> {
+    /// Contiguous axis
+    pub axis_contig: usize,
+    /// Rank of processor
+    pub rank: i32,
+    /// Shape of pencil distributed data
+    pub shape: [usize; M],
+    /// Global index ranges of pencil distributed data
+    pub ranges: [Range<usize>; M],
+    /// Size of pencil distributed data in bytes
+    pub bytes: usize,
+}
+
+/// Transpose between two pencils
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransposePlan {
+    /// Contiguous axis of send pencil
+    pub send_axis: usize,
+    /// Contiguous axis of recv pencil
+    pub recv_axis: usize,
+    /// Transpose uses ``mpi_alltoallw`` with subarray datatypes,
+    /// see [`crate::pencil::transpose_w`]
+    pub subarray: bool,
+    /// Bytes of the send buffer of each processor.
+    /// Zero for subarray transposes, unless arrays are not stored
+    /// in the preferred order of the pencil.
+    pub send_buf: Vec<usize>,
+    /// Bytes of the receive buffer of each processor
+    pub recv_buf: Vec<usize>,
+    /// Non-empty messages of processor ``i`` as (destination rank, bytes)
+    /// at ``volume[i]``, ordered by rank, including data which stays on
+    /// the same processor
+    pub volume: Vec<Vec<(i32, usize)>>,
+}
+
+impl TransposePlan {
+    /// Bytes of transpose buffers of processor ``rank``
+    ///
+    /// # Panics
+    /// ``rank`` is outside of the topology
+    #[must_use]
+    pub fn buffer_bytes(&self, rank: i32) -> usize {
+        let rank = usize::try_from(rank).unwrap();
+        self.send_buf[rank] + self.recv_buf[rank]
+    }
+
+    /// Largest bytes of transpose buffers over all processors
+    #[must_use]
+    pub fn max_buffer_bytes(&self) -> usize {
+        (0..self.send_buf.len())
+            .map(|rank| self.send_buf[rank] + self.recv_buf[rank])
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Bytes sent from processor ``rank`` to other processors
+    ///
+    /// # Panics
+    /// ``rank`` is outside of the topology
+    #[must_use]
+    pub fn bytes_sent(&self, rank: i32) -> usize {
+        let row = &self.volume[usize::try_from(rank).unwrap()];
+        row.iter()
+            .filter(|&&(dst, _)| dst != rank)
+            .map(|&(_, bytes)| bytes)
+            .sum()
+    }
+
+    /// Number of non-empty messages from processor ``rank``
+    /// to other processors
+    ///
+    /// # Panics
+    /// ``rank`` is outside of the topology
+    #[must_use]
+    pub fn messages(&self, rank: i32) -> usize {
+        let row = &self.volume[usize::try_from(rank).unwrap()];
+        row.iter().filter(|&&(dst, _)| dst != rank).count()
+    }
+}
+
+impl<const M: usize, const N: usize> Plan<M, N> {
+    /// Plan decomposition
+    ///
+    /// # Arguments
+    /// * `n_global` : Total number of grid points [nx global, ny global, ...]
+    /// * `cart_dims`: Number of processors along each dimension of the cartesian topology
+    /// * `elem_size`: Size of one element in bytes
+    ///
+    /// # Panics
+    /// - Dimensionality mismatch, expect *N* == *M* - 1
+    /// - Less than one processor along a dimension
+    #[must_use]
+    pub fn new(n_global: [usize; M], cart_dims: [i32; N], elem_size: usize) -> Self {
+        assert!(
+            N == M - 1,
+            "Dimensionality mismatch, expect N == M - 1, check cart_dims"
+        );
+        assert!(
+            cart_dims.iter().all(|&d| d > 0),
+            "Expect at least one processor along each dimension, got {cart_dims:?}"
+        );
+        Self {
+            n_global,
+            cart_dims,
+            elem_size,
+        }
+    }
+
+    /// Total number of processors
+    #[must_use]
+    pub fn nprocs(&self) -> i32 {
+        self.cart_dims.iter().product()
+    }
+
+    /// Coordinates of processor ``rank`` in the cartesian topology,
+    /// ranks are ordered row-major
+    ///
+    /// # Panics
+    /// ``rank`` is outside of the cartesian topology
+    #[must_use]
+    pub fn cart_coords(&self, rank: i32) -> [i32; N] {
+        let nprocs = self.nprocs();
+        assert!(
+            0 <= rank && rank < nprocs,
+            "Rank {rank} outside of {nprocs} procs."
+        );
+        rank_to_coords(rank, &self.cart_dims).try_into().unwrap()
+    }
+
+    /// Rank of processor at ``coords`` in the cartesian topology
+    fn rank(&self, coords: &[i32; N]) -> i32 {
+        coords
+            .iter()
+            .zip(self.cart_dims.iter())
+            .fold(0, |rank, (c, d)| rank * d + c)
+    }
+
+    /// Dimension of the cartesian topology which splits ``axis``
+    /// of a pencil with contiguous ``axis_contig``
+    fn cart_dim(axis: usize, axis_contig: usize) -> usize {
+        assert!(axis != axis_contig, "dim must differ from axis_contig");
+        if axis < axis_contig {
+            axis
+        } else {
+            axis - 1
+        }
+    }
+
+    /// Layout of the pencil with contiguous ``axis_contig`` on
+    /// processor ``rank``, in a non-periodic topology
+    ///
+    /// # Panics
+    /// - ``axis_contig`` >= *M*
+    /// - Less grid points than processors along an axis
+    #[must_use]
+    pub fn layout(&self, axis_contig: usize, rank: i32) -> PencilLayout<M> {
+        let coords = self.cart_coords(rank);
+        PencilLayout::new(
+            self.n_global,
+            axis_contig,
+            &self.cart_dims,
+            &coords,
+            &[false; N],
+        )
+    }
+
+    /// Pencil with contiguous ``axis_contig`` on processor ``rank``
+    ///
+    /// # Panics
+    /// See [`Plan::layout`]
+    #[must_use]
+    pub fn pencil(&self, axis_contig: usize, rank: i32) -> PencilPlan<M> {
+        let layout = self.layout(axis_contig, rank);
+        let shape = layout.shape();
+        let ranges = layout.global_ranges(rank);
+        PencilPlan {
+            axis_contig,
+            rank,
+            shape,
+            ranges,
+            bytes: shape.iter().product::<usize>() * self.elem_size,
+        }
+    }
+
+    /// Pencils with contiguous ``axis_contig`` on all processors
+    ///
+    /// # Panics
+    /// See [`Plan::layout`]
+    #[must_use]
+    pub fn pencils(&self, axis_contig: usize) -> Vec<PencilPlan<M>> {
+        (0..self.nprocs())
+            .map(|rank| self.pencil(axis_contig, rank))
+            .collect()
+    }
+
+    /// Largest pencil over all contiguous axes and processors
+    ///
+    /// # Panics
+    /// See [`Plan::layout`]
+    #[must_use]
+    pub fn largest_pencil(&self) -> PencilPlan<M> {
+        (0..M)
+            .flat_map(|axis| self.pencils(axis))
+            .reduce(|a, b| if b.bytes > a.bytes { b } else { a })
+            .unwrap()
+    }
+
+    /// Transpose from pencil with contiguous ``send_axis`` to pencil
+    /// with contiguous ``recv_axis``
+    ///
+    /// Pencils whose contiguous axes are neighbours are transposed
+    /// with ``mpi_alltoallv`` within sub-communicators, others with
+    /// ``mpi_alltoallw``, like in [`crate::Decomp3`].
+    ///
+    /// # Panics
+    /// - ``send_axis`` equals ``recv_axis``
+    /// - See [`Plan::layout`]
+    #[must_use]
+    pub fn transpose(&self, send_axis: usize, recv_axis: usize) -> TransposePlan {
+        assert!(
+            send_axis != recv_axis,
+            "Expect pencils with different contiguous axes."
+        );
+        let nprocs = usize::try_from(self.nprocs()).unwrap();
+        let subarray = send_axis.abs_diff(recv_axis) != 1;
+        let mut volume = Vec::with_capacity(nprocs);
+        let (send_buf, recv_buf) = if subarray {
+            let send = self.pencils(send_axis);
+            let recv = self.pencils(recv_axis);
+            for s in &send {
+                let row = recv.iter().filter_map(|r| {
+                    let len: usize = s
+                        .ranges
+                        .iter()
+                        .zip(r.ranges.iter())
+                        .map(|(a, b)| b.end.min(a.end).saturating_sub(b.start.max(a.start)))
+                        .product();
+                    (len > 0).then_some((r.rank, len * self.elem_size))
+                });
+                volume.push(row.collect());
+            }
+            (vec![0; nprocs], vec![0; nprocs])
+        } else {
+            // Exchange within the sub-communicator along the
+            // cartesian dimension which splits recv_axis
+            let dim = Self::cart_dim(recv_axis, send_axis);
+            let mut send_buf = Vec::with_capacity(nprocs);
+            let mut recv_buf = Vec::with_capacity(nprocs);
+            for rank in 0..self.nprocs() {
+                let send = self.layout(send_axis, rank);
+                let recv = self.layout(recv_axis, rank);
+                let counts = send_counts(&send, &recv);
+                let recv_counts = send_counts(&recv, &send);
+                let mut coords = self.cart_coords(rank);
+                let mut row = Vec::with_capacity(counts.len());
+                for (np, &count) in counts.iter().enumerate() {
+                    coords[dim] = np.try_into().unwrap();
+                    if count > 0 {
+                        row.push((self.rank(&coords), count * self.elem_size));
+                    }
+                }
+                row.sort_unstable();
+                volume.push(row);
+                send_buf.push(counts.iter().sum::<usize>() * self.elem_size);
+                recv_buf.push(recv_counts.iter().sum::<usize>() * self.elem_size);
+            }
+            (send_buf, recv_buf)
+        };
+        TransposePlan {
+            send_axis,
+            recv_axis,
+            subarray,
+            send_buf,
+            recv_buf,
+            volume,
+        }
+    }
+}
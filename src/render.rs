@@ -0,0 +1,283 @@
+//! # Rendering of pencil layouts
+//!
+//! Draw how the pencils of a decomposition are distributed over the
+//! processors, as SVG for two and three dimensions or as ASCII for
+//! two dimensions. Each block is labelled with its rank and its
+//! coordinates in the cartesian topology.
+//!
+//! # Example
+//! ```
+//! use pencil_decomp::plan::Plan;
+//! use pencil_decomp::render;
+//!
+//! let svg = render::svg(&Plan::new([8, 6, 4], [2, 2], 8));
+//! assert!(svg.contains("r3 (1,1)"));
+//!
+//! let ascii = render::ascii(&Plan::new([8, 3], [2], 8));
+//! assert!(ascii.starts_with("x-pencil\n|11111111\n|11111111\n|00000000\n+--------> x\n"));
+//! ```
+#![allow(clippy::cast_precision_loss)]
+use crate::plan::Plan;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Names of pencils and axes
+const AXES: [&str; 3] = ["x", "y", "z"];
+
+/// Extent of the longest axis in pixels
+const SIZE: f64 = 200.;
+
+/// Margin around each pencil in pixels
+const PAD: f64 = 30.;
+
+/// Depth of the oblique projection relative to the other axes
+const DEPTH: f64 = 0.35;
+
+/// Largest ASCII drawing in characters along x and y
+const ASCII_SIZE: [usize; 2] = [64, 32];
+
+/// Symbols of ranks in ASCII drawings, larger ranks are drawn as ``#``
+const SYMBOLS: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+
+/// Block of one processor
+struct Block {
+    rank: i32,
+    coords: String,
+    ranges: Vec<Range<usize>>,
+}
+
+impl Block {
+    fn label(&self) -> String {
+        format!("r{} ({})", self.rank, self.coords)
+    }
+}
+
+/// Blocks of all processors of the pencil with contiguous ``axis``
+fn blocks<const M: usize, const N: usize>(plan: &Plan<M, N>, axis: usize) -> Vec<Block> {
+    (0..plan.nprocs())
+        .map(|rank| {
+            let coords: Vec<String> = plan
+                .cart_coords(rank)
+                .iter()
+                .map(ToString::to_string)
+                .collect();
+            Block {
+                rank,
+                coords: coords.join(","),
+                ranges: plan.pencil(axis, rank).ranges.to_vec(),
+            }
+        })
+        .collect()
+}
+
+/// Fill color of ``rank``, with ``lightness`` in percent
+fn color(rank: i32, lightness: u32) -> String {
+    // Golden angle separates colors of neighbouring ranks
+    let hue = (f64::from(rank) * 137.508) % 360.;
+    format!("hsl({hue:.0},65%,{lightness}%)")
+}
+
+/// Render all pencils of ``plan`` as SVG
+///
+/// Two dimensional pencils are drawn as rectangles with x to the
+/// right and y upwards. Three dimensional pencils are drawn in an
+/// oblique projection with x to the right, y into the depth and z
+/// upwards.
+///
+/// # Panics
+/// - Grid is not two or three dimensional
+/// - See [`Plan::layout`]
+#[must_use]
+pub fn svg<const M: usize, const N: usize>(plan: &Plan<M, N>) -> String {
+    assert!(
+        M == 2 || M == 3,
+        "Can only render two or three dimensional grids, got {M}."
+    );
+    let n: Vec<f64> = plan.n_global.iter().map(|&n| n as f64).collect();
+    let scale = SIZE / n.iter().copied().fold(1., f64::max);
+    // Extent of one pencil in pixels
+    let (width, height) = if M == 2 {
+        (n[0] * scale, n[1] * scale)
+    } else {
+        ((n[0] + DEPTH * n[1]) * scale, (n[2] + DEPTH * n[1]) * scale)
+    };
+    let (panel_w, panel_h) = (width + 2. * PAD, height + 2. * PAD);
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{:.0}" height="{panel_h:.0}" font-family="sans-serif" font-size="10">"#,
+        panel_w * M as f64
+    )
+    .unwrap();
+    for (axis, name) in AXES.iter().enumerate().take(M) {
+        let left = panel_w * axis as f64 + PAD;
+        let bottom = PAD + height;
+        writeln!(
+            out,
+            r#"<text x="{:.1}" y="{:.1}" text-anchor="middle" font-size="14">{}-pencil</text>"#,
+            left + width / 2.,
+            PAD - 10.,
+            name
+        )
+        .unwrap();
+        let mut blocks = blocks(plan, axis);
+        if M == 2 {
+            for block in &blocks {
+                svg_rect(&mut out, block, left, bottom, scale);
+            }
+        } else {
+            // Painter's algorithm: draw from back to front
+            blocks.sort_by_key(|b| {
+                (
+                    std::cmp::Reverse(b.ranges[1].start),
+                    b.ranges[0].start,
+                    b.ranges[2].start,
+                )
+            });
+            for block in &blocks {
+                svg_cuboid(&mut out, block, &plan.n_global, left, bottom, scale);
+            }
+        }
+    }
+    writeln!(out, "</svg>").unwrap();
+    out
+}
+
+/// Draw two dimensional ``block``
+fn svg_rect(out: &mut String, block: &Block, left: f64, bottom: f64, scale: f64) {
+    let [x, y] = [&block.ranges[0], &block.ranges[1]]
+        .map(|r| (r.start as f64 * scale, r.len() as f64 * scale));
+    writeln!(
+        out,
+        r#"<rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" fill="{}" stroke="black"/>"#,
+        left + x.0,
+        bottom - y.0 - y.1,
+        x.1,
+        y.1,
+        color(block.rank, 70)
+    )
+    .unwrap();
+    svg_label(out, block, left + x.0 + x.1 / 2., bottom - y.0 - y.1 / 2.);
+}
+
+/// Draw three dimensional ``block`` in oblique projection
+fn svg_cuboid(
+    out: &mut String,
+    block: &Block,
+    n_global: &[usize],
+    left: f64,
+    bottom: f64,
+    scale: f64,
+) {
+    let project = |[x, y, z]: [usize; 3]| -> (f64, f64) {
+        let (x, y, z) = (x as f64, y as f64, z as f64);
+        (
+            left + (x + DEPTH * y) * scale,
+            bottom - (z + DEPTH * y) * scale,
+        )
+    };
+    let [x, y, z] = [0, 1, 2].map(|i| (block.ranges[i].start, block.ranges[i].end));
+    let front = [
+        [x.0, y.0, z.0],
+        [x.1, y.0, z.0],
+        [x.1, y.0, z.1],
+        [x.0, y.0, z.1],
+    ];
+    let top = [
+        [x.0, y.0, z.1],
+        [x.1, y.0, z.1],
+        [x.1, y.1, z.1],
+        [x.0, y.1, z.1],
+    ];
+    let right = [
+        [x.1, y.0, z.0],
+        [x.1, y.1, z.0],
+        [x.1, y.1, z.1],
+        [x.1, y.0, z.1],
+    ];
+    for (face, lightness) in [(&front, 70), (&top, 82), (&right, 55)] {
+        let points: Vec<String> = face
+            .iter()
+            .map(|&p| {
+                let (px, py) = project(p);
+                format!("{px:.1},{py:.1}")
+            })
+            .collect();
+        writeln!(
+            out,
+            r#"<polygon points="{}" fill="{}" stroke="black"/>"#,
+            points.join(" "),
+            color(block.rank, lightness)
+        )
+        .unwrap();
+    }
+    // Label the first face on the outside of the grid
+    let face = if y.0 == 0 {
+        front
+    } else if z.1 == n_global[2] {
+        top
+    } else {
+        right
+    };
+    let (cx, cy) = face.iter().fold((0., 0.), |(cx, cy), &p| {
+        let (px, py) = project(p);
+        (cx + px / 4., cy + py / 4.)
+    });
+    svg_label(out, block, cx, cy);
+}
+
+/// Label ``block`` centered at ``x``, ``y``
+fn svg_label(out: &mut String, block: &Block, x: f64, y: f64) {
+    writeln!(
+        out,
+        r#"<text x="{x:.1}" y="{y:.1}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+        block.label()
+    )
+    .unwrap();
+}
+
+/// Render all pencils of a two dimensional ``plan`` as ASCII
+///
+/// Each character shows the rank owning the grid point, with x to
+/// the right and y upwards, followed by a legend of ranks and their
+/// coordinates. Grids larger than 64 x 32 points are sampled.
+///
+/// # Panics
+/// See [`Plan::layout`]
+#[must_use]
+pub fn ascii(plan: &Plan<2, 1>) -> String {
+    let [n0, n1] = plan.n_global;
+    let [w, h] = [n0.min(ASCII_SIZE[0]), n1.min(ASCII_SIZE[1])];
+    let mut out = String::new();
+    for (axis, name) in AXES.iter().enumerate().take(2) {
+        writeln!(out, "{name}-pencil").unwrap();
+        let blocks = blocks(plan, axis);
+        for row in (0..h).rev() {
+            let j = row * n1 / h;
+            let line: String = (0..w)
+                .map(|col| {
+                    let i = col * n0 / w;
+                    blocks
+                        .iter()
+                        .find(|b| b.ranges[0].contains(&i) && b.ranges[1].contains(&j))
+                        .map_or('?', |b| symbol(b.rank))
+                })
+                .collect();
+            writeln!(out, "|{line}").unwrap();
+        }
+        writeln!(out, "+{}> x", "-".repeat(w)).unwrap();
+    }
+    for block in blocks(plan, 0) {
+        writeln!(out, "{}: {}", symbol(block.rank), block.label()).unwrap();
+    }
+    out
+}
+
+/// Symbol of ``rank`` in ASCII drawings
+fn symbol(rank: i32) -> char {
+    usize::try_from(rank)
+        .ok()
+        .and_then(|r| SYMBOLS.get(r))
+        .map_or('#', |&s| char::from(s))
+}
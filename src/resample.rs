@@ -0,0 +1,310 @@
+//! # Grid transfer between fine and coarse decompositions
+//!
+//! Restriction averages a field of a fine grid onto a coarse grid,
+//! prolongation interpolates it linearly from a coarse onto a fine
+//! grid, for example in multigrid cycles or to restart a run at a
+//! higher resolution. Grids are vertex centered, their first and last
+//! grid points coincide along every axis.
+//!
+//! Both operators are separable, every output point combines a few
+//! neighbouring input points along each axis, see [`Weights`]. Each
+//! processor first receives the box of input points its stencils
+//! need, including halo points held by neighbouring processors, and
+//! then applies the weights locally. The two pencils may belong to
+//! decompositions of different grid sizes and contiguous axes.
+//!
+//! # Example
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::resample::{prolong, restrict};
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let fine = Decomp3::from_comm(&comm, [17, 9, 9], [2, 2], [false, false]);
+//!     let coarse = Decomp3::from_comm(&comm, [9, 5, 5], [2, 2], [false, false]);
+//!     let x_fine = Array3::<f64>::ones(fine.x_pencil.shape());
+//!     let mut x_coarse = Array3::zeros(coarse.x_pencil.shape());
+//!     restrict(&fine.x_pencil, &x_fine, &coarse.x_pencil, &mut x_coarse);
+//!     let mut x_back = Array3::zeros(fine.x_pencil.shape());
+//!     prolong(&coarse.x_pencil, &x_coarse, &fine.x_pencil, &mut x_back);
+//!     assert!(x_back.iter().all(|x| (x - 1.).abs() < 1e-12));
+//! });
+//! ```
+use crate::comm::{Comm, Element, Subarray};
+use crate::pencil::{as_slice_in_order, axis_name, bytes, intersect, to_vec_in_order, Pencil};
+use crate::stats::{OpStats, Timer};
+use ndarray::{ArrayBase, ArrayD, ArrayViewD, Axis, Data, DataMut, Dimension, IxDyn, Order};
+use ndarray::{ShapeBuilder, Zip};
+use num_traits::Float;
+use std::ops::Range;
+
+/// Weights of a separable grid transfer along one axis
+///
+/// Output point *j* is the sum of ``w * input[i]`` over all
+/// ``(i, w)`` in ``rows[j]``.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Weights {
+    /// Input indices and weights of each output point
+    pub rows: Vec<Vec<(usize, f64)>>,
+}
+
+impl Weights {
+    /// Linear interpolation from ``n_in`` to ``n_out`` grid points
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::resample::Weights;
+    ///
+    /// let weights = Weights::interpolation(3, 5);
+    /// assert_eq!(weights.rows[1], [(0, 0.5), (1, 0.5)]);
+    /// assert_eq!(weights.rows[2], [(1, 1.)]);
+    /// ```
+    ///
+    /// # Panics
+    /// Less than two grid points in or out, unless both have one
+    #[must_use]
+    pub fn interpolation(n_in: usize, n_out: usize) -> Self {
+        if n_in == 1 && n_out == 1 {
+            return Self {
+                rows: vec![vec![(0, 1.)]],
+            };
+        }
+        assert!(
+            n_in > 1 && n_out > 1,
+            "Can't interpolate from {n_in} to {n_out} grid points."
+        );
+        let rows = (0..n_out)
+            .map(|j| {
+                // Position of output point in units of input spacing,
+                // exact for the coinciding grid points
+                let num = j * (n_in - 1);
+                let (i, rest) = (num / (n_out - 1), num % (n_out - 1));
+                if rest == 0 {
+                    vec![(i, 1.)]
+                } else {
+                    #[allow(clippy::cast_precision_loss)]
+                    let w = rest as f64 / (n_out - 1) as f64;
+                    vec![(i, 1. - w), (i + 1, w)]
+                }
+            })
+            .collect();
+        Self { rows }
+    }
+
+    /// Averaging from ``n_in`` to ``n_out`` grid points, the transpose
+    /// of [`Weights::interpolation`] from ``n_out`` to ``n_in`` with
+    /// rows normalised to one
+    ///
+    /// For ``n_in = 2 * n_out - 1`` this is the full weighting
+    /// stencil ``[1/4, 1/2, 1/4]``.
+    ///
+    /// # Example
+    /// ```
+    /// use pencil_decomp::resample::Weights;
+    ///
+    /// let weights = Weights::averaging(5, 3);
+    /// assert_eq!(weights.rows[1], [(1, 0.25), (2, 0.5), (3, 0.25)]);
+    /// ```
+    ///
+    /// # Panics
+    /// See [`Weights::interpolation`]
+    #[must_use]
+    pub fn averaging(n_in: usize, n_out: usize) -> Self {
+        let mut rows = vec![Vec::new(); n_out];
+        for (i, row) in Self::interpolation(n_out, n_in)
+            .rows
+            .into_iter()
+            .enumerate()
+        {
+            for (j, w) in row {
+                rows[j].push((i, w));
+            }
+        }
+        for row in &mut rows {
+            let sum: f64 = row.iter().map(|(_, w)| w).sum();
+            for (_, w) in row.iter_mut() {
+                *w /= sum;
+            }
+        }
+        Self { rows }
+    }
+
+    /// Range of input points needed by the output points ``out``
+    fn support(&self, out: &Range<usize>) -> Range<usize> {
+        let rows = &self.rows[out.clone()];
+        let st = rows.iter().flatten().map(|(i, _)| *i).min().unwrap_or(0);
+        let en = rows.iter().flatten().map(|(i, _)| i + 1).max().unwrap_or(0);
+        st..en
+    }
+}
+
+/// Average data of ``fine_pencil`` onto ``rcv`` of ``coarse_pencil``,
+/// see [`Weights::averaging`]
+///
+/// The communicators of both pencils must hold the same processors
+/// in the same order.
+///
+/// # Panics
+/// - Coarse grid is larger than fine grid along an axis
+/// - Processor counts of pencils differ
+/// - Shape of ``snd`` or ``rcv`` does not match its pencil
+pub fn restrict<S1, S2, T, D, C, const M: usize, const N: usize>(
+    fine_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    coarse_pencil: &Pencil<M, N, C>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Float + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let (fine, coarse) = (fine_pencil.shape_global(), coarse_pencil.shape_global());
+    assert!(
+        fine.iter().zip(coarse.iter()).all(|(f, c)| c <= f),
+        "Can't restrict grid {fine:?} to {coarse:?}."
+    );
+    let weights = std::array::from_fn(|a| Weights::averaging(fine[a], coarse[a]));
+    transfer(fine_pencil, snd, coarse_pencil, rcv, &weights, "restrict");
+}
+
+/// Interpolate data of ``coarse_pencil`` linearly onto ``rcv`` of
+/// ``fine_pencil``, see [`Weights::interpolation`]
+///
+/// The communicators of both pencils must hold the same processors
+/// in the same order.
+///
+/// # Panics
+/// - Fine grid is smaller than coarse grid along an axis
+/// - Processor counts of pencils differ
+/// - Shape of ``snd`` or ``rcv`` does not match its pencil
+pub fn prolong<S1, S2, T, D, C, const M: usize, const N: usize>(
+    coarse_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    fine_pencil: &Pencil<M, N, C>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Float + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let (coarse, fine) = (coarse_pencil.shape_global(), fine_pencil.shape_global());
+    assert!(
+        fine.iter().zip(coarse.iter()).all(|(f, c)| c <= f),
+        "Can't prolong grid {coarse:?} to {fine:?}."
+    );
+    let weights = std::array::from_fn(|a| Weights::interpolation(coarse[a], fine[a]));
+    transfer(coarse_pencil, snd, fine_pencil, rcv, &weights, "prolong");
+}
+
+/// Apply separable ``weights`` to data of ``send_pencil`` and store
+/// the result in ``rcv`` of ``recv_pencil``
+fn transfer<S1, S2, T, D, C, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    recv_pencil: &Pencil<M, N, C>,
+    rcv: &mut ArrayBase<S2, D>,
+    weights: &[Weights; M],
+    op: &str,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Float + Element,
+    D: Dimension,
+    C: Comm,
+{
+    assert!(snd.shape() == send_pencil.shape(), "Shape mismatch of snd.");
+    assert!(rcv.shape() == recv_pencil.shape(), "Shape mismatch of rcv.");
+    let comm = &send_pencil.comm;
+    assert!(
+        comm.size() == recv_pencil.comm.size(),
+        "Size mismatch of comms."
+    );
+    let order = send_pencil.order;
+    let rank = comm.rank();
+
+    // Input points needed by each processor, including halos
+    let needed = |r| -> [Range<usize>; M] {
+        let out = recv_pencil.global_ranges(r);
+        std::array::from_fn(|a| weights[a].support(&out[a]))
+    };
+    let own = send_pencil.global_ranges(rank);
+    let own_needed = needed(rank);
+    let send_blocks: Vec<Option<Subarray>> = (0..comm.size())
+        .map(|r| intersect(&own, &needed(r), order))
+        .collect();
+    let recv_blocks: Vec<Option<Subarray>> = (0..comm.size())
+        .map(|r| intersect(&own_needed, &send_pencil.global_ranges(r), order))
+        .collect();
+
+    let mut timer = Timer::start();
+    let snd_tmp;
+    let snd = if let Some(s) = as_slice_in_order(snd, order) {
+        s
+    } else {
+        snd_tmp = to_vec_in_order(snd, order);
+        &snd_tmp
+    };
+    let shape: Vec<usize> = own_needed.iter().map(ExactSizeIterator::len).collect();
+    let mut patch = vec![T::zero(); shape.iter().product()];
+    let pack = timer.lap();
+    comm.all_to_all_w(snd, &send_blocks, &mut patch, &recv_blocks);
+    let exchange = timer.lap();
+
+    // Apply weights axis by axis
+    let shape = IxDyn(&shape).set_f(order == Order::ColumnMajor);
+    let mut data = ArrayViewD::from_shape(shape, &patch).unwrap().to_owned();
+    let out = recv_pencil.global_ranges(rank);
+    for (axis, w) in weights.iter().enumerate() {
+        data = apply(
+            &data,
+            axis,
+            &w.rows[out[axis].clone()],
+            own_needed[axis].start,
+        );
+    }
+    rcv.view_mut().into_dyn().assign(&data);
+    let unpack = timer.lap();
+
+    let block_len = |b: &Subarray| b.subsizes.iter().product::<usize>();
+    send_pencil.record(
+        || {
+            let names = [send_pencil.axis_contig, recv_pencil.axis_contig].map(axis_name);
+            format!("{op} {}->{}", names[0], names[1])
+        },
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_blocks.iter().flatten().map(block_len).sum()),
+            bytes_recv: bytes::<T>(recv_blocks.iter().flatten().map(block_len).sum()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Apply ``rows`` of weights along ``axis`` of ``data``, whose
+/// first index along ``axis`` is the global index ``offset``
+fn apply<T: Float>(
+    data: &ArrayD<T>,
+    axis: usize,
+    rows: &[Vec<(usize, f64)>],
+    offset: usize,
+) -> ArrayD<T> {
+    let mut shape = data.shape().to_vec();
+    shape[axis] = rows.len();
+    let mut out = ArrayD::zeros(shape);
+    for (j, row) in rows.iter().enumerate() {
+        let mut lane = out.index_axis_mut(Axis(axis), j);
+        for &(i, w) in row {
+            let w = T::from(w).unwrap();
+            Zip::from(&mut lane)
+                .and(data.index_axis(Axis(axis), i - offset))
+                .for_each(|o, &x| *o = *o + w * x);
+        }
+    }
+    out
+}
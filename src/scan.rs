@@ -0,0 +1,119 @@
+//! # Prefix scans along an axis
+//!
+//! Cumulative sums and integrals, e.g. stream functions from velocity,
+//! run along every line of a pencil. Each processor scans its part of
+//! the lines, and along a split axis the totals of the lower
+//! processors are added with an exclusive scan (``mpi_exscan``)
+//! within the sub-communicator of that axis, see [`Comm::exclusive_scan`].
+//!
+//! # Example
+//! Cumulative sum along the split y axis
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::scan::cumsum_along;
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let decomp = Decomp3::from_comm(&comm, [4, 8, 6], [2, 2], [false, false]);
+//!     let pencil = &decomp.x_pencil;
+//!     let mut x_data = Array3::<f64>::ones(pencil.shape());
+//!     cumsum_along(pencil, &mut x_data, 1);
+//!     let st = pencil.dists[1].st;
+//!     for ((_, j, _), x) in x_data.indexed_iter() {
+//!         assert_eq!(*x, (st + j + 1) as f64);
+//!     }
+//! });
+//! ```
+use crate::comm::{Comm, Element};
+use crate::pencil::{axis_name, bytes, Pencil};
+use crate::stats::{OpStats, Timer};
+use ndarray::{ArrayBase, Axis, DataMut, Dimension};
+use std::ops::Add;
+
+/// Inclusive prefix scan of ``data`` of ``pencil`` along ``axis``
+/// with the associative ``op``, in place
+///
+/// Element *i* of each line becomes ``op(...op(x[0], x[1])..., x[i])``
+/// over the global line.
+///
+/// # Panics
+/// - ``axis`` is outside of the array dimensions
+/// - Shape of ``data`` does not match ``pencil``
+pub fn scan_along<S, T, D, C, F, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    data: &mut ArrayBase<S, D>,
+    axis: usize,
+    op: F,
+) where
+    S: DataMut<Elem = T>,
+    T: Element,
+    D: Dimension,
+    C: Comm,
+    F: Fn(T, T) -> T + Sync,
+{
+    assert!(axis < M, "Axis {axis} outside array dimensions {M}.");
+    assert!(data.shape() == pencil.shape(), "Shape mismatch of data.");
+    let mut timer = Timer::start();
+    let mut totals = Vec::with_capacity(data.len() / data.len_of(Axis(axis)).max(1));
+    for mut lane in data.lanes_mut(Axis(axis)) {
+        for i in 1..lane.len() {
+            lane[i] = op(lane[i - 1], lane[i]);
+        }
+        totals.extend(lane.iter().last());
+    }
+    let pack = timer.lap();
+    if axis == pencil.axis_contig {
+        pencil.record(
+            || format!("scan {} (local)", axis_name(axis)),
+            OpStats {
+                calls: 1,
+                pack,
+                ..OpStats::default()
+            },
+        );
+        return;
+    }
+
+    let comm = pencil.subcomm_along_axis(axis);
+    let mut offsets = totals.clone();
+    comm.exclusive_scan(&totals, &mut offsets, &op);
+    let exchange = timer.lap();
+    if comm.rank() > 0 {
+        for (mut lane, &offset) in data.lanes_mut(Axis(axis)).into_iter().zip(&offsets) {
+            for x in &mut lane {
+                *x = op(offset, *x);
+            }
+        }
+    }
+    let unpack = timer.lap();
+
+    pencil.record(
+        || format!("scan {}", axis_name(axis)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(totals.len()),
+            bytes_recv: bytes::<T>(offsets.len()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Cumulative sum of ``data`` of ``pencil`` along ``axis``, in place,
+/// see [`scan_along`]
+///
+/// # Panics
+/// See [`scan_along`]
+pub fn cumsum_along<S, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    data: &mut ArrayBase<S, D>,
+    axis: usize,
+) where
+    S: DataMut<Elem = T>,
+    T: Element + Add<Output = T>,
+    D: Dimension,
+    C: Comm,
+{
+    scan_along(pencil, data, axis, |a, b| a + b);
+}
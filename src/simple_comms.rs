@@ -1,50 +1,82 @@
 //! Collection of simple global mpi routines
-use mpi::collective::CommunicatorCollectives;
-use mpi::collective::Root;
-use mpi::environment::Universe;
-use mpi::topology::Communicator;
-use mpi::traits::Equivalence;
+use crate::comm::{assert_root, Comm, Element};
+use mpi::topology::Rank;
 use num_traits::Zero;
 
-/// Broadcast scalar value from root to all processes
-pub fn broadcast_scalar<T: Zero + Equivalence>(universe: &Universe, data: &mut T) {
-    let world = universe.world();
-    let root_rank = 0;
-    let root_process = world.process_at_rank(root_rank);
-    root_process.broadcast_into(data);
+/// Broadcast scalar value from root 0 to all processes
+pub fn broadcast_scalar<T: Zero + Element, C: Comm>(comm: &C, data: &mut T) {
+    broadcast_scalar_from(comm, 0, data);
 }
 
-/// Gather values on root and apply a closure function
+/// Broadcast scalar value from ``root`` to all processes
+///
+/// # Panics
+/// ``root`` is outside of the communicator
+pub fn broadcast_scalar_from<T: Zero + Element, C: Comm>(comm: &C, root: Rank, data: &mut T) {
+    assert_root(comm, root);
+    comm.broadcast(root, std::slice::from_mut(data));
+}
+
+/// Gather values on root 0 and apply a closure function
 ///
 /// See also [`gather_sum`]
 ///
 /// # Panics
 /// i32 to usize conversion
-pub fn gather_apply<T, F>(universe: &Universe, data: &T, result: &mut T, f: F)
+pub fn gather_apply<T, C, F>(comm: &C, data: &T, result: &mut T, f: F)
 where
-    T: Zero + Equivalence + Clone,
+    T: Zero + Element,
+    C: Comm,
     F: Fn(&[T]) -> T,
 {
-    let world = universe.world();
-    let size = world.size().try_into().unwrap();
-    let root_rank = 0;
-    let root_process = world.process_at_rank(root_rank);
-    if world.rank() == root_rank {
+    gather_apply_to(comm, 0, data, result, f);
+}
+
+/// Gather values on ``root`` and apply a closure function,
+/// ``result`` is only written on ``root``
+///
+/// # Panics
+/// - i32 to usize conversion
+/// - ``root`` is outside of the communicator
+pub fn gather_apply_to<T, C, F>(comm: &C, root: Rank, data: &T, result: &mut T, f: F)
+where
+    T: Zero + Element,
+    C: Comm,
+    F: Fn(&[T]) -> T,
+{
+    assert_root(comm, root);
+    let size = comm.size().try_into().unwrap();
+    let counts = vec![1; size];
+    let displs: Vec<_> = (0..comm.size()).collect();
+    if comm.rank() == root {
         let mut a = vec![T::zero(); size];
-        root_process.gather_into_root(data, &mut a[..]);
+        comm.gather_varcount(root, std::slice::from_ref(data), &mut a, &counts, &displs);
         *result = f(&a);
     } else {
-        root_process.gather_into(data);
+        comm.gather_varcount(root, std::slice::from_ref(data), &mut [], &[], &[]);
     }
 }
 
-/// Gather sum of values on root
-pub fn gather_sum<T>(universe: &Universe, data: &T, result: &mut T)
+/// Gather sum of values on root 0
+pub fn gather_sum<T, C>(comm: &C, data: &T, result: &mut T)
+where
+    T: Zero + Element + std::iter::Sum,
+    C: Comm,
+{
+    gather_sum_to(comm, 0, data, result);
+}
+
+/// Gather sum of values on ``root``
+///
+/// # Panics
+/// ``root`` is outside of the communicator
+pub fn gather_sum_to<T, C>(comm: &C, root: Rank, data: &T, result: &mut T)
 where
-    T: Zero + Equivalence + Clone + Copy + std::iter::Sum,
+    T: Zero + Element + std::iter::Sum,
+    C: Comm,
 {
     let f = |x: &[T]| x.iter().copied().sum();
-    gather_apply(universe, data, result, f);
+    gather_apply_to(comm, root, data, result, f);
 }
 
 /// Gather values on all processes and apply a closure function
@@ -53,23 +85,24 @@ where
 ///
 /// # Panics
 /// i32 to usize conversion
-pub fn all_gather_apply<T, F>(universe: &Universe, data: &T, result: &mut T, f: F)
+pub fn all_gather_apply<T, C, F>(comm: &C, data: &T, result: &mut T, f: F)
 where
-    T: Zero + Equivalence + Clone,
+    T: Zero + Element,
+    C: Comm,
     F: Fn(&[T]) -> T,
 {
-    let world = universe.world();
-    let size = world.size().try_into().unwrap();
+    let size = comm.size().try_into().unwrap();
     let mut a = vec![T::zero(); size];
-    world.all_gather_into(data, &mut a[..]);
+    comm.all_gather(std::slice::from_ref(data), &mut a);
     *result = f(&a);
 }
 
 /// Gather sum of values on all processes
-pub fn all_gather_sum<T>(universe: &Universe, data: &T, result: &mut T)
+pub fn all_gather_sum<T, C>(comm: &C, data: &T, result: &mut T)
 where
-    T: Zero + Equivalence + Clone + Copy + std::iter::Sum,
+    T: Zero + Element + std::iter::Sum,
+    C: Comm,
 {
     let f = |x: &[T]| x.iter().copied().sum();
-    all_gather_apply(universe, data, result, f);
+    all_gather_apply(comm, data, result, f);
 }
@@ -0,0 +1,305 @@
+//! # Spectral padding and truncation
+//!
+//! Pseudo-spectral codes remove aliasing errors of quadratic terms
+//! with the 3/2 rule: spectral fields are padded with zeros from *N*
+//! to *3N/2* modes along every axis, multiplied in physical space on
+//! the larger grid, and truncated back to *N* modes.
+//!
+//! Modes are stored in the order of the discrete Fourier transform,
+//! non-negative wavenumbers first, followed by the negative ones, see
+//! [`pad_index`]. Padding and truncation move data between pencils of
+//! two decompositions with different ``n_global``. The pencils may have
+//! different contiguous axes, so both operations can take the place of
+//! a transpose, and no array of the padded size is needed apart from
+//! the result.
+//!
+//! # Example
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::spectral::{pad, truncate};
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let small = Decomp3::from_comm(&comm, [9, 9, 9], [2, 2], [false, false]);
+//!     let large = Decomp3::from_comm(&comm, [14, 14, 14], [2, 2], [false, false]);
+//!     // Pad z-pencil of the small grid into y-pencil of the large grid
+//!     let z_hat = Array3::<f64>::ones(small.z_pencil.shape());
+//!     let mut y_hat = Array3::zeros(large.y_pencil.shape());
+//!     pad(&small.z_pencil, &z_hat, &large.y_pencil, &mut y_hat);
+//!     // ... and back
+//!     let mut z_back = Array3::zeros(small.z_pencil.shape());
+//!     truncate(&large.y_pencil, &y_hat, &small.z_pencil, &mut z_back);
+//!     assert_eq!(z_back, z_hat);
+//! });
+//! ```
+use crate::comm::{ranges, Comm, Element};
+use crate::pack::{pack_block, unpack_block};
+use crate::pencil::{axis_name, bytes, counts_and_displs, Pencil};
+use crate::stats::{OpStats, Timer};
+use ndarray::{ArrayBase, Data, DataMut, Dimension, Order, Slice};
+use num_traits::Zero;
+use std::ops::Range;
+
+/// Index of mode ``i`` of ``n_small`` modes among ``n_large`` modes,
+/// ``None`` if the mode is removed by padding
+///
+/// The first ``(n_small + 1) / 2`` modes hold the non-negative
+/// wavenumbers and keep their index, the remaining negative
+/// wavenumbers are moved to the end. For even ``n_small`` the
+/// Nyquist mode *n/2* aliases *-n/2*, and only the sum of both is
+/// known. On a larger grid it can be placed at neither without
+/// breaking the Hermitian symmetry of real fields, so it is removed.
+///
+/// # Example
+/// ```
+/// use pencil_decomp::spectral::pad_index;
+///
+/// let padded: Vec<_> = (0..4).map(|i| pad_index(i, 4, 6)).collect();
+/// assert_eq!(padded, [Some(0), Some(1), None, Some(5)]);
+/// ```
+///
+/// # Panics
+/// ``i`` >= ``n_small`` or ``n_small`` > ``n_large``
+#[must_use]
+pub fn pad_index(i: usize, n_small: usize, n_large: usize) -> Option<usize> {
+    assert!(i < n_small, "Mode {i} outside of {n_small} modes.");
+    assert!(
+        n_small <= n_large,
+        "Can't pad {n_small} to {n_large} modes."
+    );
+    if i < n_small.div_ceil(2) {
+        Some(i)
+    } else {
+        (n_small == n_large || 2 * i != n_small).then(|| i + n_large - n_small)
+    }
+}
+
+/// Index of mode ``i`` of ``n_large`` modes among ``n_small`` modes,
+/// ``None`` if the mode is removed by truncation, see [`pad_index`]
+///
+/// For even ``n_small`` both modes ``n_small / 2`` and ``-n_small / 2`` are
+/// removed, so the Nyquist mode of the result is zero.
+///
+/// # Panics
+/// ``i`` >= ``n_large`` or ``n_small`` > ``n_large``
+#[must_use]
+pub fn truncate_index(i: usize, n_large: usize, n_small: usize) -> Option<usize> {
+    assert!(i < n_large, "Mode {i} outside of {n_large} modes.");
+    assert!(
+        n_small <= n_large,
+        "Can't truncate {n_large} to {n_small} modes."
+    );
+    let npos = n_small.div_ceil(2);
+    if i < npos {
+        Some(i)
+    } else {
+        let j = (i >= npos + n_large - n_small).then(|| i + n_small - n_large)?;
+        (n_small == n_large || 2 * j != n_small).then_some(j)
+    }
+}
+
+/// Pad spectral data of ``small_pencil`` with zeros into ``rcv``
+/// of ``large_pencil``
+///
+/// Nyquist modes of even axes are removed, see [`pad_index`].
+///
+/// The communicators of both pencils must hold the same processors
+/// in the same order.
+///
+/// # Panics
+/// - ``large_pencil`` has less grid points than ``small_pencil`` along an axis
+/// - Processor counts of pencils differ
+/// - Shape of ``snd`` or ``rcv`` does not match its pencil
+pub fn pad<S1, S2, T, D, C, const M: usize, const N: usize>(
+    small_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    large_pencil: &Pencil<M, N, C>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let (small, large) = (small_pencil.shape_global(), large_pencil.shape_global());
+    assert!(
+        small.iter().zip(large.iter()).all(|(s, l)| s <= l),
+        "Can't pad grid {small:?} to {large:?}."
+    );
+    let map = |axis: usize, i: usize| pad_index(i, small[axis], large[axis]);
+    exchange_modes(small_pencil, large_pencil, snd, rcv, map, "pad");
+}
+
+/// Truncate spectral data of ``large_pencil`` into ``rcv`` of
+/// ``small_pencil``, the inverse of [`pad`]
+///
+/// Only the modes which are kept are exchanged, Nyquist modes of
+/// even axes of the result are zero, see [`truncate_index`].
+///
+/// # Panics
+/// - ``large_pencil`` has less grid points than ``small_pencil`` along an axis
+/// - Processor counts of pencils differ
+/// - Shape of ``snd`` or ``rcv`` does not match its pencil
+pub fn truncate<S1, S2, T, D, C, const M: usize, const N: usize>(
+    large_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    small_pencil: &Pencil<M, N, C>,
+    rcv: &mut ArrayBase<S2, D>,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let (small, large) = (small_pencil.shape_global(), large_pencil.shape_global());
+    assert!(
+        small.iter().zip(large.iter()).all(|(s, l)| s <= l),
+        "Can't truncate grid {large:?} to {small:?}."
+    );
+    let map = |axis: usize, i: usize| truncate_index(i, large[axis], small[axis]);
+    exchange_modes(large_pencil, small_pencil, snd, rcv, map, "truncate");
+}
+
+/// Send each mode of ``send_pencil`` to the processor which holds
+/// its index ``map(axis, i)`` in ``recv_pencil``, modes of ``rcv``
+/// which receive nothing are zero
+fn exchange_modes<S1, S2, T, D, C, F, const M: usize, const N: usize>(
+    send_pencil: &Pencil<M, N, C>,
+    recv_pencil: &Pencil<M, N, C>,
+    snd: &ArrayBase<S1, D>,
+    rcv: &mut ArrayBase<S2, D>,
+    map: F,
+    op: &str,
+) where
+    S1: Data<Elem = T>,
+    S2: DataMut<Elem = T>,
+    T: Zero + Element,
+    D: Dimension,
+    C: Comm,
+    F: Fn(usize, usize) -> Option<usize>,
+{
+    assert!(snd.shape() == send_pencil.shape(), "Shape mismatch of snd.");
+    assert!(rcv.shape() == recv_pencil.shape(), "Shape mismatch of rcv.");
+    let comm = &send_pencil.comm;
+    assert!(
+        comm.size() == recv_pencil.comm.size(),
+        "Size mismatch of comms."
+    );
+    let rank = comm.rank();
+
+    // Blocks of modes sent from ``send`` to ``recv`` ranges, as local
+    // index ranges in the arrays of sender and receiver. Along each
+    // axis, ``map`` shifts runs of modes, so the modes form a few
+    // contiguous runs, and a block is sent as their cartesian product.
+    let blocks = |send: &[Range<usize>; M], recv: &[Range<usize>; M]| {
+        let mut blocks = vec![(Vec::new(), Vec::new())];
+        for axis in 0..M {
+            let runs = runs(send[axis].clone(), |i| {
+                map(axis, i).filter(|j| recv[axis].contains(j))
+            });
+            blocks = blocks
+                .into_iter()
+                .flat_map(|(src, dst): (Vec<Range<usize>>, Vec<Range<usize>>)| {
+                    runs.iter().map(move |(i, j)| {
+                        let mut src = src.clone();
+                        let mut dst = dst.clone();
+                        src.push(i.start - send[axis].start..i.end - send[axis].start);
+                        dst.push(j.start - recv[axis].start..j.end - recv[axis].start);
+                        (src, dst)
+                    })
+                })
+                .collect();
+        }
+        blocks
+    };
+    let block_len =
+        |ranges: &[Range<usize>]| ranges.iter().map(ExactSizeIterator::len).product::<usize>();
+    let slice = |ranges: &[Range<usize>], axis: usize| Slice::from(ranges[axis].clone());
+
+    let mut timer = Timer::start();
+    let own_send = send_pencil.global_ranges(rank);
+    let own_recv = recv_pencil.global_ranges(rank);
+    let mut send_buf = Vec::new();
+    let mut send_counts = Vec::new();
+    for r in 0..comm.size() {
+        let start = send_buf.len();
+        for (src, _) in blocks(&own_send, &recv_pencil.global_ranges(r)) {
+            let block = snd.slice_each_axis(|ax| slice(&src, ax.axis.index()));
+            let n = send_buf.len();
+            send_buf.resize(n + block.len(), T::zero());
+            pack_block(block, &mut send_buf[n..], Order::RowMajor);
+        }
+        send_counts.push(send_buf.len() - start);
+    }
+    let recv_blocks: Vec<_> = (0..comm.size())
+        .map(|r| blocks(&send_pencil.global_ranges(r), &own_recv))
+        .collect();
+    let recv_counts: Vec<usize> = recv_blocks
+        .iter()
+        .map(|b| b.iter().map(|(_, dst)| block_len(dst)).sum())
+        .collect();
+    let mut recv_buf = vec![T::zero(); recv_counts.iter().sum()];
+    let pack = timer.lap();
+
+    let (send_counts, send_displs) = counts_and_displs(&send_counts);
+    let (recv_counts, recv_displs) = counts_and_displs(&recv_counts);
+    comm.all_to_all_varcount_with(
+        send_pencil.exchange,
+        &send_buf,
+        &send_counts,
+        &send_displs,
+        &mut recv_buf,
+        &recv_counts,
+        &recv_displs,
+    );
+    let exchange = timer.lap();
+
+    rcv.fill(T::zero());
+    for (blocks, buf) in recv_blocks.iter().zip(ranges(&recv_counts, &recv_displs)) {
+        let mut buf = &recv_buf[buf];
+        for (_, dst) in blocks {
+            let block = rcv.slice_each_axis_mut(|ax| slice(dst, ax.axis.index()));
+            let n = unpack_block(buf, block, Order::RowMajor);
+            buf = &buf[n..];
+        }
+    }
+    let unpack = timer.lap();
+
+    send_pencil.record(
+        || {
+            let names = [send_pencil.axis_contig, recv_pencil.axis_contig].map(axis_name);
+            format!("{op} {}->{}", names[0], names[1])
+        },
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(send_buf.len()),
+            bytes_recv: bytes::<T>(recv_buf.len()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Contiguous runs of ``indices`` which ``map`` to contiguous runs,
+/// as pairs of source and destination ranges, skipping indices which
+/// map to ``None``
+fn runs<F>(indices: Range<usize>, map: F) -> Vec<(Range<usize>, Range<usize>)>
+where
+    F: Fn(usize) -> Option<usize>,
+{
+    let mut runs: Vec<(Range<usize>, Range<usize>)> = Vec::new();
+    for i in indices {
+        let Some(j) = map(i) else { continue };
+        match runs.last_mut() {
+            Some((src, dst)) if src.end == i && dst.end == j => {
+                src.end += 1;
+                dst.end += 1;
+            }
+            _ => runs.push((i..i + 1, j..j + 1)),
+        }
+    }
+    runs
+}
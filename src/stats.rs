@@ -0,0 +1,292 @@
+//! # Instrumentation of communication routines
+//!
+//! Opt-in statistics of transposes, gathers and scatters: number of
+//! calls, bytes sent and received, and wall time split into pack,
+//! exchange and unpack. Enable with [`crate::Decomp3::with_stats`],
+//! and summarize over all processors with [`crate::Decomp3::report`].
+//!
+//! Bytes include the data which stays on the same processor.
+//!
+//! # Example
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(2, |comm| {
+//!     let decomp = Decomp3::from_comm(&comm, [6, 5, 4], [2, 1], [false, false]).with_stats();
+//!     let x_data = Array3::<f64>::zeros(decomp.x_pencil.shape());
+//!     let mut y_data = Array3::<f64>::zeros(decomp.y_pencil.shape());
+//!     decomp.transpose_x_to_y(&x_data, &mut y_data);
+//!     let report = decomp.report();
+//!     assert_eq!(report.ops[0].op, "transpose x->y");
+//!     assert_eq!(report.ops[0].calls, 1);
+//! });
+//! ```
+use crate::comm::{ranges, Comm};
+use crate::pencil::counts_and_displs;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+use std::time::Instant;
+
+/// Statistics of communication routines on one processor
+#[derive(Debug, Default)]
+pub struct Stats {
+    ops: Mutex<BTreeMap<String, OpStats>>,
+}
+
+/// Accumulated statistics of one operation on one processor
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OpStats {
+    /// Number of calls
+    pub calls: u64,
+    /// Bytes sent
+    pub bytes_sent: u64,
+    /// Bytes received
+    pub bytes_recv: u64,
+    /// Seconds spent packing send buffers
+    pub pack: f64,
+    /// Seconds spent in communication
+    pub exchange: f64,
+    /// Seconds spent unpacking receive buffers
+    pub unpack: f64,
+}
+
+impl OpStats {
+    /// Values in the order of the report columns
+    fn values(&self) -> [f64; 6] {
+        #[allow(clippy::cast_precision_loss)]
+        [
+            self.calls as f64,
+            self.bytes_sent as f64,
+            self.bytes_recv as f64,
+            self.pack,
+            self.exchange,
+            self.unpack,
+        ]
+    }
+}
+
+impl Stats {
+    /// Empty statistics
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add ``stats`` of one call to operation ``op``
+    pub fn record(&self, op: &str, stats: OpStats) {
+        let mut ops = self.ops.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = ops.entry(op.to_string()).or_default();
+        entry.calls += stats.calls;
+        entry.bytes_sent += stats.bytes_sent;
+        entry.bytes_recv += stats.bytes_recv;
+        entry.pack += stats.pack;
+        entry.exchange += stats.exchange;
+        entry.unpack += stats.unpack;
+    }
+
+    /// Statistics of current processor, ordered by operation
+    #[must_use]
+    pub fn local(&self) -> BTreeMap<String, OpStats> {
+        self.ops
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Discard all statistics
+    pub fn reset(&self) {
+        self.ops
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    /// Summarize statistics over all processors of ``comm``
+    ///
+    /// Must be called by all processors.
+    ///
+    /// # Panics
+    /// Processors recorded different operations
+    pub fn report<C: Comm>(&self, comm: &C) -> Report {
+        let local = self.local();
+        let nprocs = comm.size();
+        let size: usize = nprocs.try_into().unwrap();
+        // All processors must have recorded the same operations,
+        // compare their names separated by newlines
+        let names: Vec<u8> = local
+            .keys()
+            .flat_map(|op| op.bytes().chain([b'\n']))
+            .collect();
+        let mut lens = vec![0; size];
+        comm.all_gather(&[names.len()], &mut lens);
+        let (counts, displs) = counts_and_displs(&lens);
+        let mut all_names = vec![0; lens.iter().sum()];
+        comm.all_gather_varcount(&names, &mut all_names, &counts, &displs);
+        for (rank, range) in ranges(&counts, &displs).into_iter().enumerate() {
+            let other = &all_names[range];
+            assert!(
+                *other == names[..],
+                "Processors recorded different operations: {:?} on processor {rank}, {:?} here",
+                String::from_utf8_lossy(other).lines().collect::<Vec<_>>(),
+                local.keys().collect::<Vec<_>>(),
+            );
+        }
+        let send: Vec<f64> = local.values().flat_map(OpStats::values).collect();
+        let mut recv = vec![0.; send.len() * size];
+        comm.all_gather(&send, &mut recv);
+
+        let ops = local
+            .keys()
+            .enumerate()
+            .map(|(i, op)| {
+                let column = |j: usize| -> Summary {
+                    let values: Vec<f64> = recv.chunks(send.len()).map(|r| r[6 * i + j]).collect();
+                    Summary::of(&values)
+                };
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                OpReport {
+                    op: op.clone(),
+                    calls: column(0).max as u64,
+                    bytes_sent: column(1),
+                    bytes_recv: column(2),
+                    pack: column(3),
+                    exchange: column(4),
+                    unpack: column(5),
+                }
+            })
+            .collect();
+        Report { nprocs, ops }
+    }
+}
+
+/// Measures wall time of successive phases of an operation
+pub(crate) struct Timer(Instant);
+
+impl Timer {
+    pub(crate) fn start() -> Self {
+        Self(Instant::now())
+    }
+
+    /// Seconds since start or last lap
+    pub(crate) fn lap(&mut self) -> f64 {
+        let now = Instant::now();
+        let seconds = (now - self.0).as_secs_f64();
+        self.0 = now;
+        seconds
+    }
+}
+
+/// Minimum, maximum and average over processors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Summary {
+    /// Minimum
+    pub min: f64,
+    /// Maximum
+    pub max: f64,
+    /// Average
+    pub avg: f64,
+}
+
+impl Summary {
+    fn of(values: &[f64]) -> Self {
+        #[allow(clippy::cast_precision_loss)]
+        let avg = values.iter().sum::<f64>() / values.len().max(1) as f64;
+        Self {
+            min: values.iter().copied().fold(f64::INFINITY, f64::min),
+            max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            avg,
+        }
+    }
+}
+
+/// Statistics of one operation over all processors
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OpReport {
+    /// Name of operation
+    pub op: String,
+    /// Number of calls
+    pub calls: u64,
+    /// Bytes sent per processor
+    pub bytes_sent: Summary,
+    /// Bytes received per processor
+    pub bytes_recv: Summary,
+    /// Seconds spent packing per processor
+    pub pack: Summary,
+    /// Seconds spent in communication per processor
+    pub exchange: Summary,
+    /// Seconds spent unpacking per processor
+    pub unpack: Summary,
+}
+
+/// Statistics of all operations over all processors,
+/// see [`Stats::report`]
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    /// Number of processors
+    pub nprocs: i32,
+    /// Statistics per operation, ordered by name
+    pub ops: Vec<OpReport>,
+}
+
+#[cfg(feature = "serde")]
+impl Report {
+    /// Export as JSON
+    ///
+    /// # Panics
+    /// Never, all fields of the report are serializable
+    #[must_use]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+impl fmt::Display for Report {
+    /// Table of min / avg / max over processors,
+    /// data in KiB and times in ms
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kib = |s: Summary| {
+            format!(
+                "{:.1}/{:.1}/{:.1}",
+                s.min / 1024.,
+                s.avg / 1024.,
+                s.max / 1024.
+            )
+        };
+        let ms = |s: Summary| format!("{:.3}/{:.3}/{:.3}", s.min * 1e3, s.avg * 1e3, s.max * 1e3);
+        writeln!(
+            f,
+            "Communication on {} processors (min/avg/max)",
+            self.nprocs
+        )?;
+        writeln!(
+            f,
+            "{:<18} {:>7} {:>24} {:>24} {:>24} {:>24} {:>24}",
+            "operation",
+            "calls",
+            "sent [KiB]",
+            "recv [KiB]",
+            "pack [ms]",
+            "exchange [ms]",
+            "unpack [ms]"
+        )?;
+        for op in &self.ops {
+            writeln!(
+                f,
+                "{:<18} {:>7} {:>24} {:>24} {:>24} {:>24} {:>24}",
+                op.op,
+                op.calls,
+                kib(op.bytes_sent),
+                kib(op.bytes_recv),
+                ms(op.pack),
+                ms(op.exchange),
+                ms(op.unpack)
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,439 @@
+//! # In-process communication backend
+//!
+//! Simulates ``nprocs`` mpi processors with threads, so that pencil
+//! distributions can be run and tested without launching an mpi job.
+//!
+//! # Example
+//! ```
+//! use pencil_decomp::comm::Comm;
+//! use pencil_decomp::ThreadComm;
+//!
+//! let ranks = ThreadComm::run(4, |comm| {
+//!     let mut data = [comm.rank()];
+//!     comm.broadcast(2, &mut data);
+//!     (comm.rank(), data[0])
+//! });
+//! assert_eq!(ranks, [(0, 2), (1, 2), (2, 2), (3, 2)]);
+//! ```
+use crate::comm::{
+    pairwise_schedule, ranges, split_disjoint_mut, Comm, Element, Exchange, Subarray,
+};
+use mpi::topology::Rank;
+use mpi::Count;
+use std::any::Any;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+/// Message exchanged between threads
+type Message = Box<dyn Any + Send>;
+
+/// Panic payload of processors which are aborted, because
+/// another processor panicked
+struct Aborted;
+
+/// In-process communicator
+///
+/// Each simulated processor runs on its own thread, see [`ThreadComm::run`].
+pub struct ThreadComm {
+    /// Rank of current processor
+    rank: usize,
+    /// State shared by all processors of the communicator
+    shared: Arc<Shared>,
+}
+
+/// State shared by all processors of a communicator
+struct Shared {
+    /// Number of processors
+    size: usize,
+    /// Mailbox, message from processor *src* to *dst* is
+    /// stored in ``slots[dst][src]``
+    slots: Mutex<Vec<Vec<Option<Message>>>>,
+    /// Synchronizes all processors of the communicator
+    barrier: Barrier,
+}
+
+impl Shared {
+    fn new(size: usize, abort: Arc<AtomicBool>) -> Self {
+        let slots = (0..size)
+            .map(|_| (0..size).map(|_| None).collect())
+            .collect();
+        Self {
+            size,
+            slots: Mutex::new(slots),
+            barrier: Barrier::new(size, abort),
+        }
+    }
+
+    fn slots(&self) -> MutexGuard<'_, Vec<Vec<Option<Message>>>> {
+        self.slots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Reusable barrier, which is released when any processor
+/// of the same run panics
+struct Barrier {
+    /// Number of processors
+    n: usize,
+    /// Number of waiting processors and generation
+    state: Mutex<(usize, usize)>,
+    cvar: Condvar,
+    /// Shared by all communicators of the same run
+    abort: Arc<AtomicBool>,
+}
+
+impl Barrier {
+    fn new(n: usize, abort: Arc<AtomicBool>) -> Self {
+        Self {
+            n,
+            state: Mutex::new((0, 0)),
+            cvar: Condvar::new(),
+            abort,
+        }
+    }
+
+    /// Block until all processors have reached the barrier
+    fn wait(&self) {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let generation = state.1;
+        state.0 += 1;
+        if state.0 == self.n {
+            *state = (0, generation.wrapping_add(1));
+            self.cvar.notify_all();
+            return;
+        }
+        while state.1 == generation {
+            if self.abort.load(Ordering::SeqCst) {
+                drop(state);
+                panic::panic_any(Aborted);
+            }
+            state = self
+                .cvar
+                .wait_timeout(state, Duration::from_millis(10))
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+}
+
+impl ThreadComm {
+    /// Run ``f`` on ``nprocs`` threads, each with its own processor
+    /// of a communicator of size ``nprocs``
+    ///
+    /// Returns the results of all processors ordered by rank.
+    ///
+    /// # Panics
+    /// - ``nprocs`` is zero
+    /// - ``f`` panics on any processor
+    pub fn run<F, R>(nprocs: usize, f: F) -> Vec<R>
+    where
+        F: Fn(ThreadComm) -> R + Sync,
+        R: Send,
+    {
+        assert!(nprocs > 0, "Expect at least one processor");
+        let abort = Arc::new(AtomicBool::new(false));
+        let shared = Arc::new(Shared::new(nprocs, Arc::clone(&abort)));
+        let results = thread::scope(|s| {
+            let handles: Vec<_> = (0..nprocs)
+                .map(|rank| {
+                    let comm = ThreadComm {
+                        rank,
+                        shared: Arc::clone(&shared),
+                    };
+                    let (f, abort) = (&f, &abort);
+                    s.spawn(move || {
+                        let result = panic::catch_unwind(AssertUnwindSafe(|| f(comm)));
+                        if result.is_err() {
+                            abort.store(true, Ordering::SeqCst);
+                        }
+                        result
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().unwrap_or_else(Err))
+                .collect::<Vec<_>>()
+        });
+        // Propagate the original panic rather than an aborted processor
+        let mut out = Vec::with_capacity(nprocs);
+        let mut errors = Vec::new();
+        for result in results {
+            match result {
+                Ok(r) => out.push(r),
+                Err(e) => errors.push(e),
+            }
+        }
+        if let Some(e) = errors.into_iter().min_by_key(|e| e.is::<Aborted>()) {
+            panic::resume_unwind(e);
+        }
+        out
+    }
+
+    /// Deliver ``outgoing[dst]`` to processor *dst* and return
+    /// the messages sent to current processor, ordered by source
+    fn exchange(&self, outgoing: Vec<Option<Message>>) -> Vec<Option<Message>> {
+        assert_eq!(outgoing.len(), self.shared.size);
+        {
+            let mut slots = self.shared.slots();
+            for (dst, msg) in outgoing.into_iter().enumerate() {
+                slots[dst][self.rank] = msg;
+            }
+        }
+        self.shared.barrier.wait();
+        let incoming = self.shared.slots()[self.rank]
+            .iter_mut()
+            .map(Option::take)
+            .collect();
+        self.shared.barrier.wait();
+        incoming
+    }
+
+    /// Send ``f(dst)`` to each processor *dst* and return
+    /// the received data, ordered by source
+    fn exchange_with<T, F>(&self, f: F) -> Vec<Option<Message>>
+    where
+        T: Element,
+        F: Fn(usize) -> Option<Vec<T>>,
+    {
+        let outgoing = (0..self.shared.size)
+            .map(|dst| f(dst).map(|v| Box::new(v) as Message))
+            .collect();
+        self.exchange(outgoing)
+    }
+}
+
+/// Downcast message to data vector
+///
+/// # Panics
+/// Message is missing or has a different data type
+fn unpack<T: Element>(msg: Option<Message>) -> Vec<T> {
+    *msg.expect("Expected a message.")
+        .downcast::<Vec<T>>()
+        .expect("Data type mismatch between processors.")
+}
+
+/// Range of ``count`` elements starting at ``displ``
+///
+/// # Panics
+/// i32 to usize conversion fails
+fn range(count: Count, displ: Count) -> std::ops::Range<usize> {
+    let displ: usize = displ.try_into().unwrap();
+    let count: usize = count.try_into().unwrap();
+    displ..displ + count
+}
+
+/// Convert rank to index
+///
+/// # Panics
+/// i32 to usize conversion fails
+fn to_usize(rank: Rank) -> usize {
+    rank.try_into().unwrap()
+}
+
+impl Comm for ThreadComm {
+    fn rank(&self) -> Rank {
+        self.rank.try_into().unwrap()
+    }
+
+    fn size(&self) -> Rank {
+        self.shared.size.try_into().unwrap()
+    }
+
+    fn split(&self, color: Rank, key: Rank) -> Self {
+        let mut all = vec![0; 2 * self.shared.size];
+        self.all_gather(&[color, key], &mut all);
+        // Members of the new group, ordered by key
+        let mut members: Vec<(Rank, usize)> = all
+            .chunks(2)
+            .enumerate()
+            .filter(|(_, x)| x[0] == color)
+            .map(|(p, x)| (x[1], p))
+            .collect();
+        members.sort_unstable();
+        let rank = members.iter().position(|m| m.1 == self.rank).unwrap();
+        // First member creates the state of the new group
+        let leader = members[0].1;
+        let mut outgoing: Vec<Option<Message>> = (0..self.shared.size).map(|_| None).collect();
+        if self.rank == leader {
+            let abort = Arc::clone(&self.shared.barrier.abort);
+            let shared = Arc::new(Shared::new(members.len(), abort));
+            for m in &members {
+                outgoing[m.1] = Some(Box::new(Arc::clone(&shared)));
+            }
+        }
+        let mut incoming = self.exchange(outgoing);
+        let shared = *incoming[leader]
+            .take()
+            .and_then(|msg| msg.downcast::<Arc<Shared>>().ok())
+            .expect("Expected state of new group.");
+        Self { rank, shared }
+    }
+
+    fn all_to_all_varcount<T: Element>(
+        &self,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        let incoming = self
+            .exchange_with(|dst| Some(send[range(send_counts[dst], send_displs[dst])].to_vec()));
+        for (src, msg) in incoming.into_iter().enumerate() {
+            let data = unpack::<T>(msg);
+            recv[range(recv_counts[src], recv_displs[src])].copy_from_slice(&data);
+        }
+    }
+
+    fn supported_exchanges(&self) -> &'static [Exchange] {
+        &Exchange::ALL[..3]
+    }
+
+    fn all_to_all_varcount_with<T: Element>(
+        &self,
+        exchange: Exchange,
+        send: &[T],
+        send_counts: &[Count],
+        send_displs: &[Count],
+        recv: &mut [T],
+        recv_counts: &[Count],
+        recv_displs: &[Count],
+    ) {
+        let block = |dst: usize| send[range(send_counts[dst], send_displs[dst])].to_vec();
+        match exchange {
+            Exchange::Collective => {
+                self.all_to_all_varcount(
+                    send,
+                    send_counts,
+                    send_displs,
+                    recv,
+                    recv_counts,
+                    recv_displs,
+                );
+            }
+            Exchange::Pairwise => {
+                // One message per round, rounds synchronise all processors
+                for (dst, src) in pairwise_schedule(Comm::rank(self), Comm::size(self)) {
+                    let (dst, src) = (to_usize(dst), to_usize(src));
+                    let mut incoming = self.exchange_with(|d| (d == dst).then(|| block(d)));
+                    let data = unpack::<T>(incoming[src].take());
+                    recv[range(recv_counts[src], recv_displs[src])].copy_from_slice(&data);
+                }
+            }
+            Exchange::Nonblocking => {
+                // Only non-empty blocks are posted
+                let chunks = split_disjoint_mut(recv, &ranges(recv_counts, recv_displs));
+                let incoming = self.exchange_with(|d| (send_counts[d] > 0).then(|| block(d)));
+                for (chunk, msg) in chunks.into_iter().zip(incoming) {
+                    if !chunk.is_empty() {
+                        chunk.copy_from_slice(&unpack::<T>(msg));
+                    }
+                }
+            }
+            Exchange::Neighbor => panic!("Neighbor exchange is not supported by ThreadComm."),
+        }
+    }
+
+    fn all_to_all_w<T: Element>(
+        &self,
+        send: &[T],
+        send_blocks: &[Option<Subarray>],
+        recv: &mut [T],
+        recv_blocks: &[Option<Subarray>],
+    ) {
+        let incoming = self.exchange_with(|dst| {
+            send_blocks[dst]
+                .as_ref()
+                .map(|block| block.view(send).iter().copied().collect())
+        });
+        for (msg, block) in incoming.into_iter().zip(recv_blocks) {
+            if let Some(block) = block {
+                let data = unpack::<T>(msg);
+                let mut view = block.view_mut(recv);
+                assert_eq!(view.len(), data.len(), "Block size mismatch.");
+                view.iter_mut().zip(data).for_each(|(x, y)| *x = y);
+            }
+        }
+    }
+
+    fn gather_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        let root = to_usize(root);
+        let incoming = self.exchange_with(|dst| (dst == root).then(|| send.to_vec()));
+        if self.rank == root {
+            for (src, msg) in incoming.into_iter().enumerate() {
+                let data = unpack::<T>(msg);
+                recv[range(counts[src], displs[src])].copy_from_slice(&data);
+            }
+        }
+    }
+
+    fn scatter_varcount<T: Element>(
+        &self,
+        root: Rank,
+        send: &[T],
+        counts: &[Count],
+        displs: &[Count],
+        recv: &mut [T],
+    ) {
+        let root = to_usize(root);
+        let mut incoming = self.exchange_with(|dst| {
+            (self.rank == root).then(|| send[range(counts[dst], displs[dst])].to_vec())
+        });
+        recv.copy_from_slice(&unpack::<T>(incoming[root].take()));
+    }
+
+    fn broadcast<T: Element>(&self, root: Rank, data: &mut [T]) {
+        let root = to_usize(root);
+        let mut incoming = self.exchange_with(|_| (self.rank == root).then(|| data.to_vec()));
+        data.copy_from_slice(&unpack::<T>(incoming[root].take()));
+    }
+
+    fn all_gather<T: Element>(&self, send: &[T], recv: &mut [T]) {
+        let incoming = self.exchange_with(|_| Some(send.to_vec()));
+        for (msg, chunk) in incoming.into_iter().zip(recv.chunks_mut(send.len().max(1))) {
+            chunk.copy_from_slice(&unpack::<T>(msg));
+        }
+    }
+
+    fn all_gather_varcount<T: Element>(
+        &self,
+        send: &[T],
+        recv: &mut [T],
+        counts: &[Count],
+        displs: &[Count],
+    ) {
+        let incoming = self.exchange_with(|_| Some(send.to_vec()));
+        for (src, msg) in incoming.into_iter().enumerate() {
+            let data = unpack::<T>(msg);
+            recv[range(counts[src], displs[src])].copy_from_slice(&data);
+        }
+    }
+
+    fn exclusive_scan<T, F>(&self, send: &[T], recv: &mut [T], op: F)
+    where
+        T: Element,
+        F: Fn(T, T) -> T + Sync,
+    {
+        let incoming = self.exchange_with(|dst| (dst > self.rank).then(|| send.to_vec()));
+        let mut lower = incoming.into_iter().take(self.rank).map(unpack::<T>);
+        if let Some(first) = lower.next() {
+            recv.copy_from_slice(&first);
+            for data in lower {
+                for (r, x) in recv.iter_mut().zip(data) {
+                    *r = op(*r, x);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,370 @@
+//! # Tridiagonal solves along any axis
+//!
+//! Implicit diffusion and compact finite differences solve the same
+//! tridiagonal system along every line of a pencil. Along the
+//! contiguous axis the lines are local. Along a split axis [`solve`]
+//! uses a partitioned Thomas algorithm within the sub-communicator of
+//! that axis:
+//!
+//! 1. Each processor solves its block of the system, and the two spike
+//!    vectors which couple the block to the last unknown of the
+//!    previous and the first unknown of the next block.
+//! 2. The first and last unknowns of all blocks form a reduced system
+//!    of two rows per processor, which is all-gathered and solved on
+//!    every processor.
+//! 3. Each processor corrects its block with the unknowns of its
+//!    neighbours.
+//!
+//! Only two values per line and processor are exchanged. For
+//! comparison, [`solve_transposed`] transposes the data such that the
+//! axis is contiguous, solves locally and transposes back.
+//!
+//! # Example
+//! Implicit diffusion step along the split y axis
+//! ```
+//! use ndarray::Array3;
+//! use pencil_decomp::tridiag::{solve, Tridiagonal};
+//! use pencil_decomp::{Decomp3, ThreadComm};
+//!
+//! ThreadComm::run(4, |comm| {
+//!     let decomp = Decomp3::from_comm(&comm, [8, 16, 6], [4, 1], [false, false]);
+//!     let matrix = Tridiagonal::constant(16, -0.1, 1.2, -0.1);
+//!     let mut x_data = Array3::<f64>::ones(decomp.x_pencil.shape());
+//!     solve(&decomp.x_pencil, &matrix, 1, &mut x_data);
+//! });
+//! ```
+use crate::comm::{Comm, Element};
+use crate::pencil::{axis_name, bytes, transpose_w, Pencil};
+use crate::stats::{OpStats, Timer};
+use ndarray::{Array, ArrayBase, ArrayD, ArrayViewMut1, Axis, DataMut, Dimension, IxDyn};
+use num_traits::Float;
+
+/// Tridiagonal matrix of a system along one axis
+///
+/// Row *i* reads ``lower[i] * x[i-1] + diag[i] * x[i] + upper[i] * x[i+1]``,
+/// ``lower[0]`` and ``upper[n-1]`` are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tridiagonal {
+    /// Sub-diagonal
+    pub lower: Vec<f64>,
+    /// Main diagonal
+    pub diag: Vec<f64>,
+    /// Super-diagonal
+    pub upper: Vec<f64>,
+}
+
+impl Tridiagonal {
+    /// Matrix from its three diagonals, all of length *n*
+    ///
+    /// # Panics
+    /// Diagonals differ in length
+    #[must_use]
+    pub fn new(lower: Vec<f64>, diag: Vec<f64>, upper: Vec<f64>) -> Self {
+        assert!(
+            lower.len() == diag.len() && upper.len() == diag.len(),
+            "Diagonals must have the same length."
+        );
+        Self { lower, diag, upper }
+    }
+
+    /// Matrix of size *n* with constant diagonals
+    #[must_use]
+    pub fn constant(n: usize, lower: f64, diag: f64, upper: f64) -> Self {
+        Self::new(vec![lower; n], vec![diag; n], vec![upper; n])
+    }
+
+    /// Size of the system
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.diag.len()
+    }
+
+    /// Returns true if the system is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.diag.is_empty()
+    }
+
+    /// Thomas factorisation of rows ``st..st + m`` as a system of its own
+    fn factorize<T: Float>(&self, st: usize, m: usize) -> Thomas<T> {
+        let coef = |v: &[f64], i: usize| T::from(v[st + i]).unwrap();
+        let mut lower = Vec::with_capacity(m);
+        let mut upper = Vec::with_capacity(m);
+        let mut inv = Vec::with_capacity(m);
+        for i in 0..m {
+            let a = if i == 0 {
+                T::zero()
+            } else {
+                coef(&self.lower, i)
+            };
+            let c = if i + 1 == m {
+                T::zero()
+            } else {
+                coef(&self.upper, i)
+            };
+            let den = if i == 0 {
+                coef(&self.diag, i)
+            } else {
+                coef(&self.diag, i) - a * upper[i - 1]
+            };
+            let r = T::one() / den;
+            lower.push(a);
+            upper.push(c * r);
+            inv.push(r);
+        }
+        Thomas { lower, upper, inv }
+    }
+}
+
+/// Factorised tridiagonal system of the Thomas algorithm
+struct Thomas<T> {
+    lower: Vec<T>,
+    /// Modified super-diagonal
+    upper: Vec<T>,
+    /// Inverse of modified diagonal
+    inv: Vec<T>,
+}
+
+impl<T: Float> Thomas<T> {
+    /// Solve system in place
+    fn solve(&self, mut x: ArrayViewMut1<T>) {
+        let m = self.inv.len();
+        x[0] = x[0] * self.inv[0];
+        for i in 1..m {
+            x[i] = (x[i] - self.lower[i] * x[i - 1]) * self.inv[i];
+        }
+        for i in (0..m - 1).rev() {
+            x[i] = x[i] - self.upper[i] * x[i + 1];
+        }
+    }
+}
+
+/// Solve ``matrix`` along ``axis`` for every line of ``data`` of
+/// ``pencil``, in place
+///
+/// Split axes are solved with a partitioned Thomas algorithm within
+/// the sub-communicator along ``axis``, see [`crate::tridiag`]. The
+/// algorithm does not pivot, the system should be diagonally dominant.
+///
+/// # Panics
+/// - Size of ``matrix`` differs from the global grid along ``axis``
+/// - Shape of ``data`` does not match ``pencil``
+pub fn solve<S, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    matrix: &Tridiagonal,
+    axis: usize,
+    data: &mut ArrayBase<S, D>,
+) where
+    S: DataMut<Elem = T>,
+    T: Float + Element,
+    D: Dimension,
+    C: Comm,
+{
+    assert!(axis < M, "Axis {axis} outside array dimensions {M}.");
+    assert!(
+        matrix.len() == pencil.shape_global()[axis],
+        "Matrix of size {} along axis of {} grid points.",
+        matrix.len(),
+        pencil.shape_global()[axis]
+    );
+    assert!(data.shape() == pencil.shape(), "Shape mismatch of data.");
+    let mut timer = Timer::start();
+    if axis == pencil.axis_contig {
+        let thomas = matrix.factorize::<T>(0, matrix.len());
+        for lane in data.lanes_mut(Axis(axis)) {
+            thomas.solve(lane);
+        }
+        let unpack = timer.lap();
+        pencil.record(
+            || format!("tridiagonal {} (local)", axis_name(axis)),
+            OpStats {
+                calls: 1,
+                unpack,
+                ..OpStats::default()
+            },
+        );
+        return;
+    }
+
+    let comm = pencil.subcomm_along_axis(axis);
+    let (p, nprocs) = (to_usize(comm.rank()), to_usize(comm.size()));
+    let dist = &pencil.dists[axis];
+    let (st, m) = (dist.st, dist.sz);
+
+    // 1. Local solves and spikes, which are the same for all lines
+    let thomas = matrix.factorize::<T>(st, m);
+    for lane in data.lanes_mut(Axis(axis)) {
+        thomas.solve(lane);
+    }
+    let mut v = Array::zeros(m);
+    let mut w = Array::zeros(m);
+    if p > 0 {
+        v[0] = T::from(matrix.lower[st]).unwrap();
+        thomas.solve(v.view_mut());
+    }
+    if p + 1 < nprocs {
+        w[m - 1] = T::from(matrix.upper[st + m - 1]).unwrap();
+        thomas.solve(w.view_mut());
+    }
+    let pack = timer.lap();
+
+    // 2. Reduced system of first and last unknowns of all blocks
+    let spikes = [v[0], v[m - 1], w[0], w[m - 1]];
+    let mut all_spikes = vec![T::zero(); 4 * nprocs];
+    comm.all_gather(&spikes, &mut all_spikes);
+    let mut ends = Vec::with_capacity(2 * data.len() / m);
+    for lane in data.lanes(Axis(axis)) {
+        ends.push(lane[0]);
+        ends.push(lane[m - 1]);
+    }
+    let mut all_ends = vec![T::zero(); ends.len() * nprocs];
+    comm.all_gather(&ends, &mut all_ends);
+    let exchange = timer.lap();
+
+    let reduced = Banded::reduced(&all_spikes, &dist.sz_procs);
+    let mut rhs = vec![T::zero(); 2 * nprocs];
+
+    // 3. Correct blocks with the unknowns of the neighbours
+    let nlines = ends.len() / 2;
+    for (l, mut lane) in data.lanes_mut(Axis(axis)).into_iter().enumerate() {
+        for (q, r) in rhs.chunks_mut(2).enumerate() {
+            let off = q * 2 * nlines + 2 * l;
+            r.copy_from_slice(&all_ends[off..off + 2]);
+            if dist.sz_procs[q] == 1 {
+                // Block of one row, whose first and last unknowns coincide
+                r[1] = T::zero();
+            }
+        }
+        reduced.solve(&mut rhs);
+        let prev = if p > 0 { rhs[2 * p - 1] } else { T::zero() };
+        let next = if p + 1 < nprocs {
+            rhs[2 * p + 2]
+        } else {
+            T::zero()
+        };
+        for i in 0..m {
+            lane[i] = lane[i] - v[i] * prev - w[i] * next;
+        }
+    }
+    let unpack = timer.lap();
+
+    pencil.record(
+        || format!("tridiagonal {}", axis_name(axis)),
+        OpStats {
+            calls: 1,
+            bytes_sent: bytes::<T>(spikes.len() + ends.len()),
+            bytes_recv: bytes::<T>(all_spikes.len() + all_ends.len()),
+            pack,
+            exchange,
+            unpack,
+        },
+    );
+}
+
+/// Solve ``matrix`` along the contiguous axis of ``solve_pencil``
+/// for every line of ``data`` of ``pencil``, in place
+///
+/// Transposes ``data`` into ``solve_pencil``, solves locally and
+/// transposes back, see [`transpose_w`]. Gives the same result as
+/// [`solve`] and serves as reference.
+///
+/// # Panics
+/// - See [`solve`] and [`transpose_w`]
+pub fn solve_transposed<S, T, D, C, const M: usize, const N: usize>(
+    pencil: &Pencil<M, N, C>,
+    solve_pencil: &Pencil<M, N, C>,
+    matrix: &Tridiagonal,
+    data: &mut ArrayBase<S, D>,
+) where
+    S: DataMut<Elem = T>,
+    T: Float + Element,
+    D: Dimension,
+    C: Comm,
+{
+    let mut data = data.view_mut().into_dyn();
+    let mut tmp = ArrayD::zeros(IxDyn(&solve_pencil.shape()));
+    transpose_w(pencil, solve_pencil, &data, &mut tmp);
+    solve(solve_pencil, matrix, solve_pencil.axis_contig, &mut tmp);
+    transpose_w(solve_pencil, pencil, &tmp, &mut data);
+}
+
+/// Reduced system of a partitioned Thomas algorithm, factorised
+/// without pivoting. Row *i* holds the entries of columns *i - 2*
+/// to *i + 2*.
+struct Banded<T> {
+    rows: Vec<[T; 5]>,
+}
+
+impl<T: Float> Banded<T> {
+    /// Reduced system from ``[v first, v last, w first, w last]``
+    /// of each block of ``sizes`` rows
+    ///
+    /// Row ``2q`` reads ``v_first * last(q-1) + first(q) + w_first * first(q+1)``,
+    /// row ``2q + 1`` likewise with the last entries of the spikes.
+    /// Blocks of one row couple first and last unknown instead.
+    fn reduced(spikes: &[T], sizes: &[usize]) -> Self {
+        let nprocs = sizes.len();
+        let mut rows = vec![[T::zero(); 5]; 2 * nprocs];
+        for q in 0..nprocs {
+            let s = &spikes[4 * q..4 * q + 4];
+            // Column offset k is stored at index k + 2
+            rows[2 * q][2] = T::one();
+            rows[2 * q][1] = s[0];
+            rows[2 * q][4] = s[2];
+            if sizes[q] == 1 {
+                rows[2 * q + 1][1] = -T::one();
+                rows[2 * q + 1][2] = T::one();
+            } else {
+                rows[2 * q + 1][0] = s[1];
+                rows[2 * q + 1][2] = T::one();
+                rows[2 * q + 1][3] = s[3];
+            }
+        }
+        let mut banded = Self { rows };
+        banded.factorize();
+        banded
+    }
+
+    /// Entry at ``row``, ``col``
+    fn at(&mut self, row: usize, col: usize) -> &mut T {
+        &mut self.rows[row][col + 2 - row]
+    }
+
+    /// LU factorisation in place, multipliers are stored below the diagonal
+    fn factorize(&mut self) {
+        let n = self.rows.len();
+        for k in 0..n {
+            let pivot = *self.at(k, k);
+            for i in k + 1..n.min(k + 3) {
+                let l = *self.at(i, k) / pivot;
+                *self.at(i, k) = l;
+                for j in k + 1..n.min(k + 3) {
+                    let u = *self.at(k, j);
+                    *self.at(i, j) = *self.at(i, j) - l * u;
+                }
+            }
+        }
+    }
+
+    /// Solve factorised system in place
+    fn solve(&self, x: &mut [T]) {
+        let n = self.rows.len();
+        let at = |row: usize, col: usize| self.rows[row][col + 2 - row];
+        for i in 0..n {
+            for j in i.saturating_sub(2)..i {
+                x[i] = x[i] - at(i, j) * x[j];
+            }
+        }
+        for i in (0..n).rev() {
+            for j in i + 1..n.min(i + 3) {
+                x[i] = x[i] - at(i, j) * x[j];
+            }
+            x[i] = x[i] / at(i, i);
+        }
+    }
+}
+
+/// Convert rank to index
+fn to_usize(rank: i32) -> usize {
+    rank.try_into().unwrap()
+}
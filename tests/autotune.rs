@@ -0,0 +1,65 @@
+//! Autotuning on the in-process backend
+use pencil_decomp::autotune::autotune;
+use pencil_decomp::comm::Comm;
+use pencil_decomp::ThreadComm;
+
+#[test]
+fn test_autotune_same_on_all_ranks() {
+    let tunings = ThreadComm::run(6, |comm| {
+        autotune::<f64, _>(&comm, [6, 7, 9], [false, false], 1, None)
+    });
+    let [p0, p1] = tunings[0].cart_dims;
+    assert_eq!(p0 * p1, 6);
+    assert!(tunings.iter().all(|t| *t == tunings[0]));
+}
+
+#[test]
+fn test_autotune_skips_grids_which_do_not_fit() {
+    // Only one processor fits along z
+    ThreadComm::run(4, |comm| {
+        let tuning = autotune::<f64, _>(&comm, [8, 8, 1], [false, false], 1, None);
+        assert_eq!(tuning.cart_dims, [4, 1]);
+    });
+}
+
+#[test]
+fn test_autotune_cache() {
+    let path = std::env::temp_dir().join(format!("pencil_autotune_{}.txt", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let first = ThreadComm::run(4, |comm| {
+        autotune::<f64, _>(&comm, [8, 6, 4], [false, false], 1, Some(&path))
+    });
+    // Second run is read from cache and reproduces the first one exactly
+    let second = ThreadComm::run(4, |comm| {
+        autotune::<f64, _>(&comm, [8, 6, 4], [false, false], 1, Some(&path))
+    });
+    assert_eq!(first[0].cart_dims, second[0].cart_dims);
+    assert_eq!(first[0].exchange, second[0].exchange);
+    assert!((first[0].seconds - second[0].seconds).abs() <= 1e-9 * first[0].seconds);
+    assert!(second.iter().all(|t| *t == second[0]));
+    // Other data types are tuned separately
+    ThreadComm::run(4, |comm| {
+        autotune::<f32, _>(&comm, [8, 6, 4], [false, false], 1, Some(&path))
+    });
+    let lines = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(lines.lines().count(), 2);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_autotune_ignores_invalid_cache() {
+    let path = std::env::temp_dir().join(format!("pencil_autotune_bad_{}.txt", std::process::id()));
+    let dtype = std::any::type_name::<f64>();
+    // Neighbor exchange needs mpi and grid [1, 4] does not fit along z
+    let lines = format!("4 8 8 2 {dtype} 2 2 neighbor 1e-9\n4 8 8 2 {dtype} 1 4 collective 1e-9\n");
+    std::fs::write(&path, lines).unwrap();
+    ThreadComm::run(4, |comm| {
+        let tuning = autotune::<f64, _>(&comm, [8, 8, 2], [false, false], 1, Some(&path));
+        assert!(comm.supported_exchanges().contains(&tuning.exchange));
+        assert_ne!(tuning.cart_dims, [1, 4]);
+    });
+    // Tuned again and appended
+    let lines = std::fs::read_to_string(&path).unwrap();
+    assert_eq!(lines.lines().count(), 3);
+    std::fs::remove_file(&path).unwrap();
+}
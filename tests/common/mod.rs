@@ -0,0 +1,30 @@
+//! Helpers shared by the integration tests
+#![allow(dead_code)]
+use ndarray::Array3;
+use pencil_decomp::{Pencil, ThreadComm};
+
+/// Part of ``global`` held by ``pencil``
+pub fn local<C>(global: &Array3<f64>, pencil: &Pencil<3, 2, C>) -> Array3<f64> {
+    let st = [pencil.dists[0].st, pencil.dists[1].st, pencil.dists[2].st];
+    Array3::from_shape_fn(pencil.shape(), |(i, j, k)| {
+        global[[i + st[0], j + st[1], k + st[2]]]
+    })
+}
+
+/// Assert equal shapes and elementwise differences below ``tol``
+pub fn assert_close(a: &Array3<f64>, b: &Array3<f64>, tol: f64) {
+    assert_eq!(a.shape(), b.shape());
+    for (x, y) in a.iter().zip(b.iter()) {
+        assert!((x - y).abs() < tol, "{x} != {y}");
+    }
+}
+
+/// Run ``f`` on as many threads as processors of ``cart_dims``
+pub fn run_on<F, R>(cart_dims: [i32; 2], f: F) -> Vec<R>
+where
+    F: Fn(ThreadComm) -> R + Sync,
+    R: Send,
+{
+    let nprocs = (cart_dims[0] * cart_dims[1]).try_into().unwrap();
+    ThreadComm::run(nprocs, f)
+}
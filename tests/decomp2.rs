@@ -0,0 +1,226 @@
+//! Transposes, gathers and scatters of ``Decomp2`` on the in-process backend
+use ndarray::{s, Array2, Order, ShapeBuilder};
+use pencil_decomp::comm::Comm;
+use pencil_decomp::{Decomp2, Pencil, ThreadComm};
+
+const GRIDS: [[usize; 2]; 4] = [[6, 5], [7, 9], [16, 16], [13, 4]];
+
+fn test_array_from_pencil<C>(pencil: &Pencil<2, 1, C>) -> Array2<f64> {
+    test_array(pencil.shape(), [pencil.dists[0].st, pencil.dists[1].st])
+}
+
+fn test_array(shape: [usize; 2], displs: [usize; 2]) -> Array2<f64> {
+    Array2::from_shape_fn(shape, |(i, j)| {
+        ((i + displs[0]) + (j + displs[1]) * 100) as f64
+    })
+}
+
+#[test]
+fn test_transpose() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false]);
+
+                let x_data = test_array_from_pencil(&decomp2.x_pencil);
+                let mut y_data = Array2::zeros(decomp2.y_pencil.shape());
+                decomp2.transpose_x_to_y(&x_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(&decomp2.y_pencil));
+
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape());
+                decomp2.transpose_y_to_x(&y_data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp2.x_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_transpose_column_major() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false])
+                    .with_order(Order::ColumnMajor);
+
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape().f());
+                x_data.assign(&test_array_from_pencil(&decomp2.x_pencil));
+                let mut y_data = Array2::zeros(decomp2.y_pencil.shape().f());
+                decomp2.transpose_x_to_y(&x_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(&decomp2.y_pencil));
+
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape().f());
+                decomp2.transpose_y_to_x(&y_data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp2.x_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_gather() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false]);
+                let root = decomp2.x_pencil.comm.rank() == 0;
+
+                let x_data = test_array_from_pencil(&decomp2.x_pencil);
+                let mut data = Array2::zeros(n_global);
+                decomp2.gather_x(&x_data, &mut data);
+                if root {
+                    assert_eq!(data, test_array(n_global, [0, 0]));
+                }
+
+                let y_data = test_array_from_pencil(&decomp2.y_pencil);
+                let mut data = Array2::zeros(n_global);
+                decomp2.gather_y(&y_data, &mut data);
+                if root {
+                    assert_eq!(data, test_array(n_global, [0, 0]));
+                }
+            });
+        }
+    }
+}
+
+#[test]
+fn test_scatter() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false]);
+                let data = test_array(n_global, [0, 0]);
+
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape());
+                decomp2.scatter_x(&data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp2.x_pencil));
+
+                let mut y_data = Array2::zeros(decomp2.y_pencil.shape());
+                decomp2.scatter_y(&data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(&decomp2.y_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_gather_scatter_root() {
+    for nprocs in 1..5 {
+        for root in 0..nprocs {
+            ThreadComm::run(nprocs.try_into().unwrap(), |comm| {
+                let n_global = GRIDS[0];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, [nprocs], [false]);
+                let is_root = decomp2.x_pencil.comm.rank() == root;
+
+                // Only root holds the global array, and receives it
+                let data = if is_root {
+                    test_array(n_global, [0, 0])
+                } else {
+                    Array2::zeros(n_global)
+                };
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape());
+                decomp2.scatter_x_from(root, &data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp2.x_pencil));
+                let mut y_data = Array2::zeros(decomp2.y_pencil.shape());
+                decomp2.scatter_y_from(root, &data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(&decomp2.y_pencil));
+
+                let mut gathered = Array2::zeros(n_global);
+                decomp2.gather_x_to(root, &x_data, &mut gathered);
+                assert_eq!(gathered, data);
+                let mut gathered = Array2::zeros(n_global);
+                decomp2.gather_y_to(root, &y_data, &mut gathered);
+                assert_eq!(gathered, data);
+            });
+        }
+    }
+}
+
+#[test]
+fn test_gather_scatter_root_empty() {
+    for nprocs in 1..5 {
+        for root in 0..nprocs {
+            ThreadComm::run(nprocs.try_into().unwrap(), |comm| {
+                let n_global = GRIDS[1];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, [nprocs], [false]);
+                let is_root = decomp2.x_pencil.comm.rank() == root;
+
+                // Non-root processors pass empty global arrays
+                let global = |data: Array2<f64>| if is_root { data } else { Array2::zeros([0, 0]) };
+                let data = global(test_array(n_global, [0, 0]));
+                let mut x_data = Array2::zeros(decomp2.x_pencil.shape());
+                decomp2.scatter_x_from(root, &data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp2.x_pencil));
+                let mut y_data = Array2::zeros(decomp2.y_pencil.shape());
+                decomp2.scatter_y_from(root, &data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(&decomp2.y_pencil));
+
+                let mut gathered = global(Array2::zeros(n_global));
+                decomp2.gather_x_to(root, &x_data, &mut gathered);
+                assert_eq!(gathered, data);
+                let mut gathered = global(Array2::zeros(n_global));
+                decomp2.gather_y_to(root, &y_data, &mut gathered);
+                assert_eq!(gathered, data);
+            });
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Root rank 2 outside of communicator of size 2")]
+fn test_gather_invalid_root() {
+    ThreadComm::run(2, |comm| {
+        let decomp2 = Decomp2::from_comm(&comm, GRIDS[0], [2], [false]);
+        let x_data = Array2::<f64>::zeros(decomp2.x_pencil.shape());
+        let mut data = Array2::zeros(GRIDS[0]);
+        decomp2.gather_x_to(2, &x_data, &mut data);
+    });
+}
+
+#[test]
+fn test_all_gather() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false]);
+
+                let x_data = test_array_from_pencil(&decomp2.x_pencil);
+                let mut data = Array2::zeros(n_global);
+                decomp2.allgather_x(&x_data, &mut data);
+                assert_eq!(data, test_array(n_global, [0, 0]));
+
+                let y_data = test_array_from_pencil(&decomp2.y_pencil);
+                let mut data = Array2::zeros(n_global);
+                decomp2.allgather_y(&y_data, &mut data);
+                assert_eq!(data, test_array(n_global, [0, 0]));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_gather_strided() {
+    for nprocs in 1..5 {
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let cart_dims = [nprocs.try_into().unwrap()];
+                let decomp2 = Decomp2::from_comm(&comm, n_global, cart_dims, [false]);
+                let root = i32::try_from(nprocs).unwrap() - 1;
+                let global = test_array(n_global, [0, 0]);
+                let expected = global.slice(s![..;3, ..;2]);
+
+                let x_data = test_array_from_pencil(&decomp2.x_pencil);
+                let mut coarse = Array2::zeros(expected.raw_dim());
+                decomp2.gather_strided(root, &decomp2.x_pencil, &x_data, [3, 2], &mut coarse);
+                if decomp2.x_pencil.comm.rank() == root {
+                    assert_eq!(coarse, expected);
+                }
+            });
+        }
+    }
+}
@@ -0,0 +1,372 @@
+//! Transposes of ``Decomp3`` on the in-process backend
+use ndarray::{s, Array2, Array3, Axis, Order, ShapeBuilder};
+use pencil_decomp::comm::{Comm, Exchange};
+use pencil_decomp::pencil::{redistribute, transpose_w};
+use pencil_decomp::{Decomp3, Pencil, ThreadComm};
+
+const GRIDS: [[usize; 3]; 4] = [[6, 7, 9], [8, 8, 8], [5, 12, 7], [9, 4, 6]];
+
+const CART_DIMS: [[i32; 2]; 6] = [[1, 1], [1, 2], [2, 1], [2, 2], [2, 3], [3, 2]];
+
+fn test_array_from_pencil<C>(pencil: &Pencil<3, 2, C>) -> Array3<f64> {
+    let st = [pencil.dists[0].st, pencil.dists[1].st, pencil.dists[2].st];
+    Array3::from_shape_fn(pencil.shape(), |(i, j, k)| {
+        ((i + st[0]) + (j + st[1]) * 10 + (k + st[2]) * 100) as f64
+    })
+}
+
+#[test]
+fn test_transpose() {
+    for cart_dims in CART_DIMS {
+        let nprocs = (cart_dims[0] * cart_dims[1]).try_into().unwrap();
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+                let x_pencil = &decomp3.x_pencil;
+                let y_pencil = &decomp3.y_pencil;
+                let z_pencil = &decomp3.z_pencil;
+
+                // Transpose x -> y
+                let x_data = test_array_from_pencil(x_pencil);
+                let mut y_data = Array3::zeros(y_pencil.shape());
+                decomp3.transpose_x_to_y(&x_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(y_pencil));
+
+                // Transpose y -> z
+                let mut z_data = Array3::zeros(z_pencil.shape());
+                decomp3.transpose_y_to_z(&y_data, &mut z_data);
+                assert_eq!(z_data, test_array_from_pencil(z_pencil));
+
+                // Transpose z -> y
+                let mut y_data = Array3::zeros(y_pencil.shape());
+                decomp3.transpose_z_to_y(&z_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(y_pencil));
+
+                // Transpose y -> x
+                let mut x_data = Array3::zeros(x_pencil.shape());
+                decomp3.transpose_y_to_x(&y_data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(x_pencil));
+            });
+        }
+    }
+}
+
+/// Copy of ``data`` with given memory layout
+fn with_layout(data: &Array3<f64>, layout: &str) -> Array3<f64> {
+    let shape = data.raw_dim();
+    let mut out = match layout {
+        "c" => Array3::zeros(shape),
+        "f" => Array3::zeros(shape.f()),
+        // y axis fastest, then z, then x
+        _ => {
+            let (n0, n1, n2) = data.dim();
+            Array3::zeros((n0, n2, n1)).permuted_axes([0, 2, 1])
+        }
+    };
+    out.assign(data);
+    out
+}
+
+#[test]
+fn test_transpose_memory_layouts() {
+    let n_global = [6, 7, 9];
+    let cart_dims = [2, 3];
+    for order in [Order::RowMajor, Order::ColumnMajor] {
+        for layout in ["c", "f", "permuted"] {
+            ThreadComm::run(6, |comm| {
+                let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false])
+                    .with_order(order);
+                let x_pencil = &decomp3.x_pencil;
+                let y_pencil = &decomp3.y_pencil;
+                let z_pencil = &decomp3.z_pencil;
+
+                let x_data = with_layout(&test_array_from_pencil(x_pencil), layout);
+                let mut y_data = with_layout(&Array3::zeros(y_pencil.shape()), layout);
+                decomp3.transpose_x_to_y(&x_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(y_pencil));
+
+                let mut z_data = with_layout(&Array3::zeros(z_pencil.shape()), layout);
+                decomp3.transpose_y_to_z(&y_data, &mut z_data);
+                assert_eq!(z_data, test_array_from_pencil(z_pencil));
+
+                let mut y_data = with_layout(&Array3::zeros(y_pencil.shape()), layout);
+                decomp3.transpose_z_to_y(&z_data, &mut y_data);
+                assert_eq!(y_data, test_array_from_pencil(y_pencil));
+
+                let mut x_data = with_layout(&Array3::zeros(x_pencil.shape()), layout);
+                decomp3.transpose_y_to_x(&y_data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(x_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_transpose_mixed_pencil_orders() {
+    // Contiguous axis is fastest varying in x- and z-pencil
+    ThreadComm::run(4, |comm| {
+        let mut decomp3 = Decomp3::from_comm(&comm, [8, 5, 7], [2, 2], [false, false]);
+        decomp3.x_pencil.order = Order::ColumnMajor;
+        let x_data = with_layout(&test_array_from_pencil(&decomp3.x_pencil), "f");
+        let mut y_data = Array3::zeros(decomp3.y_pencil.shape());
+        decomp3.transpose_x_to_y(&x_data, &mut y_data);
+        assert_eq!(y_data, test_array_from_pencil(&decomp3.y_pencil));
+
+        let mut x_data = Array3::zeros(decomp3.x_pencil.shape().f());
+        decomp3.transpose_y_to_x(&y_data, &mut x_data);
+        assert_eq!(x_data, test_array_from_pencil(&decomp3.x_pencil));
+    });
+}
+
+#[test]
+fn test_transpose_x_to_z() {
+    for cart_dims in CART_DIMS {
+        let nprocs = (cart_dims[0] * cart_dims[1]).try_into().unwrap();
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+                let x_data = test_array_from_pencil(&decomp3.x_pencil);
+                let mut z_data = Array3::zeros(decomp3.z_pencil.shape());
+                decomp3.transpose_x_to_z(&x_data, &mut z_data);
+                assert_eq!(z_data, test_array_from_pencil(&decomp3.z_pencil));
+
+                let mut x_data = Array3::zeros(decomp3.x_pencil.shape());
+                decomp3.transpose_z_to_x(&z_data, &mut x_data);
+                assert_eq!(x_data, test_array_from_pencil(&decomp3.x_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_transpose_x_to_z_memory_layouts() {
+    for order in [Order::RowMajor, Order::ColumnMajor] {
+        for layout in ["c", "f", "permuted"] {
+            ThreadComm::run(6, |comm| {
+                let decomp3 =
+                    Decomp3::from_comm(&comm, [6, 7, 9], [2, 3], [false, false]).with_order(order);
+                let x_data = with_layout(&test_array_from_pencil(&decomp3.x_pencil), layout);
+                let mut z_data = with_layout(&Array3::zeros(decomp3.z_pencil.shape()), layout);
+                decomp3.transpose_x_to_z(&x_data, &mut z_data);
+                assert_eq!(z_data, test_array_from_pencil(&decomp3.z_pencil));
+            });
+        }
+    }
+}
+
+#[test]
+fn test_transpose_w_between_decompositions() {
+    // Same grid on a 2 x 3 and a 3 x 2 processor grid
+    for n_global in GRIDS {
+        ThreadComm::run(6, |comm| {
+            let a = Decomp3::from_comm(&comm, n_global, [2, 3], [false, false]);
+            let b = Decomp3::from_comm(&comm, n_global, [3, 2], [false, false]);
+            let x_data = test_array_from_pencil(&a.x_pencil);
+            let mut x_other = Array3::zeros(b.x_pencil.shape());
+            transpose_w(&a.x_pencil, &b.x_pencil, &x_data, &mut x_other);
+            assert_eq!(x_other, test_array_from_pencil(&b.x_pencil));
+
+            let mut y_data = Array3::zeros(b.y_pencil.shape());
+            transpose_w(&a.x_pencil, &b.y_pencil, &x_data, &mut y_data);
+            assert_eq!(y_data, test_array_from_pencil(&b.y_pencil));
+        });
+    }
+}
+
+#[test]
+fn test_transpose_exchanges() {
+    // Neighbor exchange needs mpi
+    for exchange in [
+        Exchange::Collective,
+        Exchange::Pairwise,
+        Exchange::Nonblocking,
+    ] {
+        ThreadComm::run(6, |comm| {
+            let decomp3 = Decomp3::from_comm(&comm, [6, 7, 9], [2, 3], [false, false])
+                .with_exchange(exchange);
+            let x_data = test_array_from_pencil(&decomp3.x_pencil);
+            let mut y_data = Array3::zeros(decomp3.y_pencil.shape());
+            decomp3.transpose_x_to_y(&x_data, &mut y_data);
+            assert_eq!(y_data, test_array_from_pencil(&decomp3.y_pencil));
+
+            let mut z_data = Array3::zeros(decomp3.z_pencil.shape());
+            decomp3.transpose_y_to_z(&y_data, &mut z_data);
+            assert_eq!(z_data, test_array_from_pencil(&decomp3.z_pencil));
+        });
+    }
+}
+
+/// Owns its decomposition, no lifetime of the communicator
+struct Solver {
+    decomp: Decomp3<ThreadComm>,
+    x_data: Array3<f64>,
+}
+
+impl Solver {
+    fn new(comm: &ThreadComm, n_global: [usize; 3]) -> Self {
+        let decomp = Decomp3::from_comm(comm, n_global, [2, 1], [false, false]);
+        let x_data = test_array_from_pencil(&decomp.x_pencil);
+        Self { decomp, x_data }
+    }
+}
+
+#[test]
+fn test_owned_decomposition() {
+    ThreadComm::run(2, |comm| {
+        let solvers: Vec<Solver> = GRIDS.iter().map(|&n| Solver::new(&comm, n)).collect();
+        drop(comm);
+        for solver in &solvers {
+            let mut y_data = Array3::zeros(solver.decomp.y_pencil.shape());
+            solver.decomp.transpose_x_to_y(&solver.x_data, &mut y_data);
+            assert_eq!(y_data, test_array_from_pencil(&solver.decomp.y_pencil));
+        }
+    });
+}
+
+#[test]
+fn test_all_gather() {
+    for cart_dims in CART_DIMS {
+        let nprocs = (cart_dims[0] * cart_dims[1]).try_into().unwrap();
+        for n_global in GRIDS {
+            ThreadComm::run(nprocs, |comm| {
+                let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false])
+                    .with_order(Order::ColumnMajor);
+                let global =
+                    Array3::from_shape_fn(n_global, |(i, j, k)| (i + j * 10 + k * 100) as f64);
+                for pencil in [&decomp3.x_pencil, &decomp3.y_pencil, &decomp3.z_pencil] {
+                    let data = test_array_from_pencil(pencil);
+                    let mut gathered = Array3::zeros(n_global.f());
+                    match pencil.axis_contig {
+                        0 => decomp3.allgather_x(&data, &mut gathered),
+                        1 => decomp3.allgather_y(&data, &mut gathered),
+                        _ => decomp3.allgather_z(&data, &mut gathered),
+                    }
+                    assert_eq!(gathered, global);
+                }
+            });
+        }
+    }
+}
+
+#[test]
+fn test_gather_slice() {
+    let n_global = [6, 7, 9];
+    let global = Array3::from_shape_fn(n_global, |(i, j, k)| (i + j * 10 + k * 100) as f64);
+    for root in 0..6 {
+        ThreadComm::run(6, |comm| {
+            let decomp3 = Decomp3::from_comm(&comm, n_global, [2, 3], [false, false]);
+            let is_root = decomp3.x_pencil.comm.rank() == root;
+            for pencil in [&decomp3.x_pencil, &decomp3.y_pencil, &decomp3.z_pencil] {
+                let data = test_array_from_pencil(pencil);
+                for (axis, index) in [(0, 4), (1, 0), (2, 8)] {
+                    let mut shape = n_global.to_vec();
+                    shape.remove(axis);
+                    let mut plane = Array2::zeros((shape[0], shape[1]));
+                    decomp3.gather_slice(root, pencil, &data, axis, index, &mut plane);
+                    if is_root {
+                        assert_eq!(plane, global.index_axis(Axis(axis), index));
+                    } else {
+                        assert!(plane.iter().all(|&x| x == 0.));
+                    }
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_gather_subvolume() {
+    let n_global = [6, 7, 9];
+    let global = Array3::from_shape_fn(n_global, |(i, j, k)| (i + j * 10 + k * 100) as f64);
+    let ranges = [2..4, 2..3, 4..7];
+    let sent = ThreadComm::run(4, |comm| {
+        let decomp3 = Decomp3::from_comm(&comm, n_global, [2, 2], [false, false]).with_stats();
+        let data = test_array_from_pencil(&decomp3.y_pencil);
+        let is_root = decomp3.y_pencil.comm.rank() == 3;
+        // Other processors need not allocate the box
+        let mut sub = Array3::zeros(if is_root { [2, 1, 3] } else { [0; 3] }.f());
+        decomp3.gather_subvolume(3, &decomp3.y_pencil, &data, &ranges, &mut sub);
+        if is_root {
+            let expected = global.slice(s![2..4, 2..3, 4..7]);
+            assert_eq!(sub, expected);
+        }
+        let owner = decomp3.y_pencil.owners(&ranges).contains(&comm.rank());
+        let bytes = decomp3.stats().unwrap().local()["gather subvolume y"].bytes_sent;
+        (owner, bytes)
+    });
+    // Only the owners of the box send data
+    assert_eq!(sent.iter().filter(|(owner, _)| *owner).count(), 2);
+    for (owner, bytes) in &sent {
+        assert_eq!(*owner, *bytes > 0);
+    }
+    assert_eq!(sent.iter().map(|(_, b)| b).sum::<u64>(), 6 * 8);
+}
+
+#[test]
+#[should_panic(expected = "Pencil does not belong to this decomposition.")]
+fn test_gather_subvolume_other_decomposition() {
+    // Same grid, other processor grid
+    ThreadComm::run(4, |comm| {
+        let decomp3 = Decomp3::from_comm(&comm, [6, 7, 9], [2, 2], [false, false]);
+        let other = Decomp3::from_comm(&comm, [6, 7, 9], [4, 1], [false, false]);
+        let data = Array3::<f64>::zeros(other.x_pencil.shape());
+        let mut sub = Array3::zeros([2, 2, 2]);
+        decomp3.gather_subvolume(0, &other.x_pencil, &data, &[0..2, 0..2, 0..2], &mut sub);
+    });
+}
+
+#[test]
+fn test_gather_strided() {
+    let global = |n_global: [usize; 3]| {
+        Array3::from_shape_fn(n_global, |(i, j, k)| (i + j * 10 + k * 100) as f64)
+    };
+    for cart_dims in CART_DIMS {
+        let nprocs = (cart_dims[0] * cart_dims[1]).try_into().unwrap();
+        for n_global in GRIDS {
+            for strides in [[1, 1, 1], [2, 3, 2], [4, 1, 5], [7, 7, 7]] {
+                ThreadComm::run(nprocs, |comm| {
+                    let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+                    let is_root = decomp3.x_pencil.comm.rank() == 0;
+                    let expected = global(n_global);
+                    let expected = expected.slice(s![
+                        ..;strides[0],
+                        ..;strides[1],
+                        ..;strides[2]
+                    ]);
+                    for pencil in [&decomp3.x_pencil, &decomp3.y_pencil, &decomp3.z_pencil] {
+                        let data = test_array_from_pencil(pencil);
+                        let mut coarse = Array3::zeros(expected.raw_dim());
+                        decomp3.gather_strided(0, pencil, &data, strides, &mut coarse);
+                        if is_root {
+                            assert_eq!(coarse, expected);
+                        }
+                    }
+                });
+            }
+        }
+    }
+}
+
+#[test]
+fn test_redistribute() {
+    // Same grid on processor grids of different shapes
+    for (a_dims, b_dims) in [([2, 3], [3, 2]), ([1, 6], [6, 1]), ([2, 3], [2, 3])] {
+        for n_global in [[6, 7, 9], [8, 8, 8], [7, 6, 12]] {
+            ThreadComm::run(6, |comm| {
+                let a = Decomp3::from_comm(&comm, n_global, a_dims, [false, false]).with_stats();
+                let b = Decomp3::from_comm(&comm, n_global, b_dims, [false, false])
+                    .with_order(Order::ColumnMajor);
+                for src_pencil in [&a.x_pencil, &a.y_pencil, &a.z_pencil] {
+                    let src = test_array_from_pencil(src_pencil);
+                    for dst_pencil in [&b.x_pencil, &b.y_pencil, &b.z_pencil] {
+                        let mut dst = Array3::zeros(dst_pencil.shape().f());
+                        redistribute(src_pencil, &src, dst_pencil, &mut dst);
+                        assert_eq!(dst, test_array_from_pencil(dst_pencil));
+                    }
+                }
+                let stats = a.stats().unwrap().local();
+                assert_eq!(stats["redistribute z->y"].calls, 1);
+            });
+        }
+    }
+}
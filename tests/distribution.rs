@@ -0,0 +1,180 @@
+//! Property-based tests of ``Distribution`` and the count/displacement routines
+use pencil_decomp::comm::Comm;
+use pencil_decomp::distribution::Distribution;
+use pencil_decomp::pencil::{
+    recv_counts_all_to_all, recv_counts_gather_axis, send_counts_all_to_all,
+};
+use pencil_decomp::{Decomp3, Pencil, ThreadComm};
+use proptest::prelude::*;
+
+/// Displacements must be the exclusive prefix sums of counts
+fn assert_prefix_sums(counts: &[i32], displs: &[i32]) {
+    assert_eq!(counts.len(), displs.len());
+    let mut acc = 0;
+    for (c, d) in counts.iter().zip(displs) {
+        assert_eq!(*d, acc);
+        acc += c;
+    }
+}
+
+fn to_usize(x: i32) -> usize {
+    x.try_into().unwrap()
+}
+
+/// Send and recv counts of a transpose, gathered from all processors
+struct Counts {
+    coords: Vec<i32>,
+    send: (Vec<i32>, Vec<i32>),
+    recv: (Vec<i32>, Vec<i32>),
+}
+
+fn counts<C>(send: &Pencil<3, 2, C>, recv: &Pencil<3, 2, C>) -> Counts {
+    let counts = Counts {
+        coords: send.cart_coords(),
+        send: send_counts_all_to_all(send, recv),
+        recv: recv_counts_all_to_all(send, recv),
+    };
+    assert_prefix_sums(&counts.send.0, &counts.send.1);
+    assert_prefix_sums(&counts.recv.0, &counts.recv.1);
+    assert_eq!(to_usize(counts.send.0.iter().sum()), send.len());
+    assert_eq!(to_usize(counts.recv.0.iter().sum()), recv.len());
+    counts
+}
+
+/// What processor *a* sends to *b* must be what *b* receives from *a*
+///
+/// Processors exchange data along ``cart_dim``, i.e. they share the
+/// coordinate of the other cartesian dimension
+fn assert_counts_match(counts: &[Counts], cart_dim: usize) {
+    let other = 1 - cart_dim;
+    for a in counts {
+        for b in counts.iter().filter(|b| b.coords[other] == a.coords[other]) {
+            let (ca, cb) = (to_usize(a.coords[cart_dim]), to_usize(b.coords[cart_dim]));
+            assert_eq!(a.send.0[cb], b.recv.0[ca]);
+        }
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(256))]
+
+    #[test]
+    fn test_distribute((n_global, nprocs) in (1..1000_usize).prop_flat_map(|n| (Just(n), 1..=n.min(64)))) {
+        let dist = Distribution::split(n_global, nprocs, 0);
+        // Sizes sum to n_global
+        prop_assert_eq!(dist.sz_procs.iter().sum::<usize>(), n_global);
+        // Ranges are contiguous and disjoint
+        prop_assert_eq!(dist.st_procs[0], 0);
+        prop_assert_eq!(dist.en_procs[nprocs - 1], n_global - 1);
+        for p in 0..nprocs {
+            prop_assert_eq!(dist.en_procs[p] + 1 - dist.st_procs[p], dist.sz_procs[p]);
+            if p > 0 {
+                prop_assert_eq!(dist.st_procs[p], dist.en_procs[p - 1] + 1);
+            }
+        }
+        // Load is balanced
+        let max = dist.sz_procs.iter().max().unwrap();
+        let min = dist.sz_procs.iter().min().unwrap();
+        prop_assert!(max - min <= 1);
+    }
+
+    #[test]
+    fn test_split_rank((n_global, nprocs) in (1..1000_usize).prop_flat_map(|n| (Just(n), 1..=n.min(64)))) {
+        for nrank in 0..nprocs {
+            let dist = Distribution::split(n_global, nprocs, nrank);
+            prop_assert_eq!(dist.st, dist.st_procs[nrank]);
+            prop_assert_eq!(dist.en, dist.en_procs[nrank]);
+            prop_assert_eq!(dist.sz, dist.sz_procs[nrank]);
+        }
+    }
+
+    #[test]
+    fn test_contiguous(n_global in 1..1000_usize) {
+        let dist = Distribution::contiguous(n_global);
+        prop_assert_eq!((dist.st, dist.en, dist.sz), (0, n_global - 1, n_global));
+    }
+
+    #[test]
+    fn test_strided(
+        (n_global, nprocs) in (1..1000_usize).prop_flat_map(|n| (Just(n), 1..=n.min(64))),
+        stride in 1..50_usize,
+    ) {
+        // Strided ranges of all processors tile the downsampled grid
+        let mut next = 0;
+        for nrank in 0..nprocs {
+            let coarse = Distribution::split(n_global, nprocs, nrank).strided(stride);
+            prop_assert_eq!(coarse.start, next);
+            next = coarse.end;
+            let dist = Distribution::split(n_global, nprocs, nrank);
+            for i in coarse {
+                prop_assert!(dist.st <= i * stride && i * stride <= dist.en);
+            }
+        }
+        prop_assert_eq!(next, n_global.div_ceil(stride));
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn test_counts_all_to_all(
+        cart_dims in [1..4_i32, 1..4_i32],
+        n_offset in [0..12_usize, 0..12_usize, 0..12_usize],
+    ) {
+        // Each split axis holds at least as many points as processors
+        let max_procs = to_usize(cart_dims[0].max(cart_dims[1]));
+        let n_global = n_offset.map(|n| n + max_procs);
+        let nprocs = to_usize(cart_dims[0] * cart_dims[1]);
+        let results = ThreadComm::run(nprocs, |comm| {
+            let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+            let xy = counts(&decomp3.x_pencil, &decomp3.y_pencil);
+            let yz = counts(&decomp3.y_pencil, &decomp3.z_pencil);
+            let zy = counts(&decomp3.z_pencil, &decomp3.y_pencil);
+            let yx = counts(&decomp3.y_pencil, &decomp3.x_pencil);
+            (xy, yz, zy, yx)
+        });
+        let (xy, yz, zy, yx): (Vec<_>, Vec<_>, Vec<_>, Vec<_>) = results.into_iter().fold(
+            (vec![], vec![], vec![], vec![]),
+            |mut acc, r| {
+                acc.0.push(r.0);
+                acc.1.push(r.1);
+                acc.2.push(r.2);
+                acc.3.push(r.3);
+                acc
+            },
+        );
+        // x <-> y exchanges along the first, y <-> z along the second cartesian dimension
+        assert_counts_match(&xy, 0);
+        assert_counts_match(&yx, 0);
+        assert_counts_match(&yz, 1);
+        assert_counts_match(&zy, 1);
+    }
+
+    #[test]
+    fn test_counts_gather_axis(
+        cart_dims in [1..4_i32, 1..4_i32],
+        n_offset in [0..12_usize, 0..12_usize, 0..12_usize],
+    ) {
+        let max_procs = to_usize(cart_dims[0].max(cart_dims[1]));
+        let n_global = n_offset.map(|n| n + max_procs);
+        let nprocs = to_usize(cart_dims[0] * cart_dims[1]);
+        ThreadComm::run(nprocs, |comm| {
+            let decomp3 = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+            for pencil in [&decomp3.x_pencil, &decomp3.y_pencil, &decomp3.z_pencil] {
+                for axis in (0..3).filter(|&a| a != pencil.axis_contig) {
+                    let (counts, displs) = recv_counts_gather_axis(pencil, axis);
+                    assert_prefix_sums(&counts, &displs);
+                    // Gathered data spans the whole axis
+                    let mut shape = pencil.shape();
+                    shape[axis] = n_global[axis];
+                    let total: i32 = counts.iter().sum();
+                    assert_eq!(to_usize(total), shape.iter().product::<usize>());
+                    // Own count matches size of local data
+                    let rank = to_usize(pencil.subcomm_along_axis(axis).rank());
+                    assert_eq!(to_usize(counts[rank]), pencil.len());
+                }
+            }
+        });
+    }
+}
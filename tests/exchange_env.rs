@@ -0,0 +1,33 @@
+//! Exchange algorithm from the environment
+//!
+//! Kept in its own test binary, as the variable applies to every
+//! pencil built while it is set.
+use pencil_decomp::comm::{Exchange, EXCHANGE_ENV};
+use pencil_decomp::{Decomp3, ThreadComm};
+use std::panic::catch_unwind;
+
+#[test]
+fn test_exchange_from_env() {
+    std::env::set_var(EXCHANGE_ENV, "pairwise");
+    let exchanges = ThreadComm::run(2, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [4, 4, 4], [2, 1], [false, false]);
+        decomp.x_pencil.exchange
+    });
+    assert_eq!(exchanges, [Exchange::Pairwise; 2]);
+
+    // Neighbor exchange needs mpi, rejected when the pencil is built
+    std::env::set_var(EXCHANGE_ENV, "neighbor");
+    let result = catch_unwind(|| {
+        ThreadComm::run(2, |comm| {
+            let _ = Decomp3::from_comm(&comm, [4, 4, 4], [2, 1], [false, false]);
+        });
+    });
+    std::env::remove_var(EXCHANGE_ENV);
+    let err = result.unwrap_err();
+    let msg = err
+        .downcast_ref::<String>()
+        .map(String::as_str)
+        .or_else(|| err.downcast_ref::<&str>().copied())
+        .unwrap();
+    assert!(msg.contains("neighbor exchange is not supported"), "{msg}");
+}
@@ -0,0 +1,182 @@
+//! Distributed Fourier transforms on the in-process backend
+#![cfg(feature = "fft")]
+mod common;
+
+use common::{assert_close, run_on};
+use ndarray::Array3;
+use num_complex::Complex64;
+use pencil_decomp::fourier::{
+    backward, derivative, forward, local_wavenumbers, poisson, wavenumbers,
+};
+use pencil_decomp::spectral::pad;
+use pencil_decomp::{Decomp3, Pencil, ThreadComm};
+use std::f64::consts::TAU;
+
+const CART_DIMS: [[i32; 2]; 4] = [[1, 1], [2, 2], [1, 3], [3, 2]];
+
+/// Field ``f`` sampled on the local grid points of ``pencil`` of a
+/// periodic domain of ``lengths``
+fn sample<C, F>(pencil: &Pencil<3, 2, C>, lengths: [f64; 3], f: F) -> Array3<f64>
+where
+    F: Fn(f64, f64, f64) -> f64,
+{
+    let n = pencil.shape_global();
+    let st = [pencil.dists[0].st, pencil.dists[1].st, pencil.dists[2].st];
+    let x = |a: usize, i: usize| lengths[a] * (i + st[a]) as f64 / n[a] as f64;
+    Array3::from_shape_fn(pencil.shape(), |(i, j, k)| f(x(0, i), x(1, j), x(2, k)))
+}
+
+#[test]
+fn test_wavenumbers() {
+    assert_eq!(wavenumbers(5, TAU), [0., 1., 2., -2., -1.]);
+    assert_eq!(wavenumbers(6, TAU / 2.), [0., 2., 4., -6., -4., -2.]);
+    ThreadComm::run(3, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [4, 6, 5], [1, 3], [false, false]);
+        let pencil = &decomp.z_pencil;
+        let all = wavenumbers(6, TAU);
+        let st = pencil.dists[1].st;
+        assert_eq!(
+            local_wavenumbers(pencil, 1, TAU),
+            all[st..st + pencil.shape()[1]]
+        );
+    });
+}
+
+#[test]
+fn test_forward_single_mode() {
+    // exp(i (2 x - y + 3 z)) has a single mode of amplitude n
+    let n_global = [8, 6, 7];
+    let lengths = [TAU; 3];
+    ThreadComm::run(4, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, [2, 2], [false, false]);
+        let phase = sample(&decomp.x_pencil, lengths, |x, y, z| 2. * x - y + 3. * z);
+        let data = phase.mapv(|p| Complex64::from_polar(1., p));
+        let mut z_hat = Array3::zeros(decomp.z_pencil.shape());
+        forward(&decomp, &data, &mut z_hat);
+        let k = [0, 1, 2].map(|a| local_wavenumbers(&decomp.z_pencil, a, lengths[a]));
+        let amplitude = n_global.iter().product::<usize>() as f64;
+        for ((i, j, l), v) in z_hat.indexed_iter() {
+            let expected = if [k[0][i], k[1][j], k[2][l]] == [2., -1., 3.] {
+                amplitude
+            } else {
+                0.
+            };
+            assert!((v - expected).norm() < 1e-9, "{v} != {expected}");
+        }
+    });
+}
+
+#[test]
+fn test_forward_backward() {
+    let n_global = [9, 10, 12];
+    let global = Array3::from_shape_fn(n_global, |(i, j, k)| {
+        Complex64::new(((i * 7 + j * 3 + k * 11) % 13) as f64, (i + j * k) as f64)
+    });
+    for cart_dims in CART_DIMS {
+        run_on(cart_dims, |comm| {
+            let decomp = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+            let st = [0, 1, 2].map(|a| decomp.x_pencil.dists[a].st);
+            let data = Array3::from_shape_fn(decomp.x_pencil.shape(), |(i, j, k)| {
+                global[[i + st[0], j + st[1], k + st[2]]]
+            });
+            let mut z_hat = Array3::zeros(decomp.z_pencil.shape());
+            forward(&decomp, &data, &mut z_hat);
+            let mut back = Array3::zeros(decomp.x_pencil.shape());
+            backward(&decomp, &z_hat, &mut back);
+            for (x, y) in back.iter().zip(data.iter()) {
+                assert!((x - y).norm() < 1e-10);
+            }
+        });
+    }
+}
+
+#[test]
+fn test_derivative() {
+    let n_global = [8, 9, 6];
+    let lengths = [TAU, 2., 3.];
+    ThreadComm::run(4, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, [2, 2], [false, false]);
+        let ky = TAU / lengths[1];
+        let f = sample(&decomp.x_pencil, lengths, |x, y, _| {
+            x.sin() * (ky * y).cos()
+        });
+        let mut z_hat = Array3::zeros(decomp.z_pencil.shape());
+        forward(&decomp, &f.mapv(Complex64::from), &mut z_hat);
+        derivative(&decomp.z_pencil, &mut z_hat, 1, lengths[1], 1);
+        let mut dfdy = Array3::zeros(decomp.x_pencil.shape());
+        backward(&decomp, &z_hat, &mut dfdy);
+        let expected = sample(&decomp.x_pencil, lengths, |x, y, _| {
+            -ky * x.sin() * (ky * y).sin()
+        });
+        assert_close(&dfdy.mapv(|v| v.re), &expected, 1e-10);
+    });
+}
+
+#[test]
+fn test_poisson_analytic() {
+    // laplace(u) = f for u = sin(x) cos(2 y) sin(kz z) + cos(kw z)
+    let n_global = [12, 10, 9];
+    let lengths = [TAU, TAU, 1.5];
+    let kz = 3. * TAU / lengths[2];
+    let kw = TAU / lengths[2];
+    let u = |x: f64, y: f64, z: f64| x.sin() * (2. * y).cos() * (kz * z).sin() + (kw * z).cos();
+    let f = |x: f64, y: f64, z: f64| {
+        -(1. + 4. + kz * kz) * x.sin() * (2. * y).cos() * (kz * z).sin() - kw * kw * (kw * z).cos()
+    };
+    for cart_dims in CART_DIMS {
+        run_on(cart_dims, |comm| {
+            let decomp = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]);
+            // Constant offset of rhs is ignored
+            let rhs = sample(&decomp.x_pencil, lengths, f) + 2.;
+            let mut sol = Array3::zeros(decomp.x_pencil.shape());
+            poisson(&decomp, lengths, &rhs, &mut sol);
+            assert_close(&sol, &sample(&decomp.x_pencil, lengths, u), 1e-10);
+        });
+    }
+}
+
+#[test]
+fn test_pad_real_field() {
+    // Even grids, so every axis has a Nyquist mode
+    let small = [8, 6, 10];
+    let large = [12, 9, 15];
+    let lengths = [TAU; 3];
+    let scale = (small.iter().product::<usize>() as f64) / (large.iter().product::<usize>() as f64);
+    // Band-limited part is interpolated exactly, the rest has energy
+    // in all modes including Nyquist
+    let smooth = |x: f64, y: f64, z: f64| x.cos() + (2. * y - z).sin();
+    for cart_dims in CART_DIMS {
+        run_on(cart_dims, |comm| {
+            let s = Decomp3::from_comm(&comm, small, cart_dims, [false, false]);
+            let l = Decomp3::from_comm(&comm, large, cart_dims, [false, false]);
+            let st = [0, 1, 2].map(|a| s.x_pencil.dists[a].st);
+            let rough = Array3::from_shape_fn(s.x_pencil.shape(), |(i, j, k)| {
+                (((i + st[0]) * 7 + (j + st[1]) * 3 + (k + st[2]) * 11) % 13) as f64
+            });
+            for (f, band_limited) in [(sample(&s.x_pencil, lengths, smooth), true), (rough, false)]
+            {
+                let mut z_hat = Array3::zeros(s.z_pencil.shape());
+                forward(&s, &f.mapv(Complex64::from), &mut z_hat);
+                let mut z_pad = Array3::zeros(l.z_pencil.shape());
+                pad(&s.z_pencil, &z_hat, &l.z_pencil, &mut z_pad);
+                let mut padded = Array3::zeros(l.x_pencil.shape());
+                backward(&l, &z_pad, &mut padded);
+                assert_close(&padded.mapv(|v| v.im), &Array3::zeros(padded.dim()), 1e-10);
+                if band_limited {
+                    let expected = sample(&l.x_pencil, lengths, smooth) * scale;
+                    assert_close(&padded.mapv(|v| v.re), &expected, 1e-10);
+                }
+            }
+        });
+    }
+}
+
+#[test]
+#[should_panic(expected = "Axis 3 outside array dimensions 3.")]
+fn test_derivative_invalid_axis() {
+    ThreadComm::run(1, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [4, 4, 4], [1, 1], [false, false]);
+        let mut z_hat = Array3::zeros(decomp.z_pencil.shape());
+        derivative(&decomp.z_pencil, &mut z_hat, 3, TAU, 1);
+    });
+}
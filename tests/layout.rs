@@ -0,0 +1,100 @@
+//! Layouts of decompositions and restart checks
+use pencil_decomp::comm::Comm;
+use pencil_decomp::layout::PencilLayout;
+use pencil_decomp::pencil::{send_counts, send_counts_all_to_all};
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+#[test]
+fn test_pencil_layout_without_comm() {
+    let n_global = [7, 9, 5];
+    let cart_dims = [2, 3];
+    let periodic = [false, true];
+    ThreadComm::run(6, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, cart_dims, periodic);
+        let coords = decomp.x_pencil.cart_coords();
+        let pencils = [&decomp.x_pencil, &decomp.y_pencil, &decomp.z_pencil];
+        for (axis, pencil) in pencils.iter().enumerate() {
+            let layout = PencilLayout::new(n_global, axis, &cart_dims, &coords, &periodic);
+            assert_eq!(&layout, pencil.layout());
+            for rank in 0..comm.size() {
+                assert_eq!(layout.global_ranges(rank), pencil.global_ranges(rank));
+            }
+        }
+    });
+
+    // Counts of a transpose from layouts of a single processor
+    let x = PencilLayout::new(n_global, 0, &cart_dims, &[1, 2], &periodic);
+    let y = PencilLayout::new(n_global, 1, &cart_dims, &[1, 2], &periodic);
+    // x-pencil holds 7 x 5 x 2 points, y-pencils split x in 3 + 4
+    assert_eq!(send_counts(&x, &y), [3 * 5 * 2, 4 * 5 * 2]);
+    // y-pencil holds 4 x 9 x 2 points, x-pencils split y in 4 + 5
+    assert_eq!(send_counts_all_to_all(&y, &x).0, [4 * 4 * 2, 4 * 5 * 2]);
+}
+
+#[test]
+fn test_layout_round_trip() {
+    ThreadComm::run(6, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [7, 9, 5], [2, 3], [true, false]);
+        let layout = decomp.layout();
+        assert_eq!(layout.pencils.len(), 3);
+        assert_eq!(layout.pencils[1].dists, decomp.y_pencil.dists);
+        assert_eq!(layout.pencils[2].cart_coords, decomp.z_pencil.cart_coords());
+
+        let rebuilt = Decomp3::from_layout(&comm, &layout);
+        assert_eq!(rebuilt.layout(), layout);
+    });
+}
+
+#[test]
+fn test_layout_from_other_rank_matches() {
+    let layouts = ThreadComm::run(3, |comm| {
+        Decomp2::from_comm(&comm, [7, 9], [3], [false]).layout()
+    });
+    assert_ne!(layouts[0], layouts[2]);
+    assert_eq!(layouts[0].mismatch(&layouts[2]), None);
+    ThreadComm::run(3, |comm| {
+        Decomp2::from_comm(&comm, [7, 9], [3], [false]).check_layout(&layouts[0]);
+    });
+}
+
+#[test]
+fn test_layout_mismatch() {
+    let layouts = ThreadComm::run(4, |comm| {
+        let a = Decomp3::from_comm(&comm, [8, 6, 4], [2, 2], [false, false]).layout();
+        let b = Decomp3::from_comm(&comm, [8, 6, 4], [4, 1], [false, false]).layout();
+        let c = Decomp3::from_comm(&comm, [8, 6, 5], [2, 2], [false, false]).layout();
+        let d = Decomp3::from_comm(&comm, [8, 6, 4], [2, 2], [false, true]).layout();
+        (a, b, c, d)
+    });
+    let (a, b, c, d) = &layouts[0];
+    assert!(a.mismatch(b).unwrap().starts_with("processor grid"));
+    assert!(a.mismatch(c).unwrap().starts_with("grid size"));
+    assert!(a.mismatch(d).unwrap().starts_with("periodicity"));
+    let result = std::panic::catch_unwind(|| a.assert_matches(b));
+    assert!(result.is_err());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_layout_serde() {
+    use pencil_decomp::distribution::Distribution;
+    use pencil_decomp::layout::DecompLayout;
+
+    let dist = Distribution::split(10, 3, 1);
+    let json = serde_json::to_string(&dist).unwrap();
+    assert_eq!(
+        json,
+        r#"{"sz":3,"st":3,"en":5,"sz_procs":[3,3,4],"st_procs":[0,3,6],"en_procs":[2,5,9]}"#
+    );
+    assert_eq!(serde_json::from_str::<Distribution>(&json).unwrap(), dist);
+
+    ThreadComm::run(4, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [8, 6, 4], [2, 2], [false, false]);
+        let json = serde_json::to_string(&decomp.layout()).unwrap();
+        let stored: DecompLayout<3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(stored, decomp.layout());
+        let _ = Decomp3::from_layout(&comm, &stored);
+        // Arrays of wrong length are rejected
+        assert!(serde_json::from_str::<DecompLayout<2>>(&json).is_err());
+    });
+}
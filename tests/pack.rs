@@ -0,0 +1,54 @@
+use ndarray::{s, Array3, Order};
+use pencil_decomp::pack::{pack_block, unpack_block};
+
+const PERMUTATIONS: [[usize; 3]; 6] = [
+    [0, 1, 2],
+    [0, 2, 1],
+    [1, 0, 2],
+    [1, 2, 0],
+    [2, 0, 1],
+    [2, 1, 0],
+];
+
+/// Packed buffer must match logical iteration order for any memory layout
+#[test]
+fn test_pack_unpack_block() {
+    let shape = [37, 5, 70];
+    for perm in PERMUTATIONS {
+        for order in [Order::RowMajor, Order::ColumnMajor] {
+            // Array with memory order given by perm
+            let base = Array3::from_shape_fn(
+                (shape[perm[0]], shape[perm[1]], shape[perm[2]]),
+                |(i, j, k)| (i * 10_000 + j * 100 + k) as f64,
+            );
+            let data = base.view().permuted_axes(inverse(perm));
+            let block = data.slice(s![3..20, .., 1..]);
+            let expected: Vec<f64> = if order == Order::RowMajor {
+                block.iter().copied().collect()
+            } else {
+                block.t().iter().copied().collect()
+            };
+
+            let mut buf = vec![0.; block.len() + 3];
+            assert_eq!(pack_block(block, &mut buf, order), block.len());
+            assert_eq!(buf[..block.len()], expected, "{perm:?} {order:?}");
+
+            let mut out = Array3::<f64>::zeros(base.raw_dim());
+            let mut out_view = out.view_mut().permuted_axes(inverse(perm));
+            unpack_block(&buf, out_view.slice_mut(s![3..20, .., 1..]), order);
+            assert_eq!(
+                out_view.slice(s![3..20, .., 1..]),
+                block,
+                "{perm:?} {order:?}"
+            );
+        }
+    }
+}
+
+fn inverse(perm: [usize; 3]) -> [usize; 3] {
+    let mut inv = [0; 3];
+    for (i, p) in perm.iter().enumerate() {
+        inv[*p] = i;
+    }
+    inv
+}
@@ -0,0 +1,72 @@
+//! Command line tool to inspect decompositions
+use std::process::Command;
+
+fn inspect(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_pencil_inspect"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_inspect_csv() {
+    let output = inspect(&["--n", "7,9,5", "--procs", "2x3", "--format", "csv"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 1 + 6);
+    assert!(lines[0].starts_with("rank,coords,x extent,x shape,y extent,y shape"));
+    assert!(lines[0].ends_with("y->z msgs,y->z bytes"));
+    // Rank 0: x-pencil holds y in 0..4 and z in 0..1 (5 = 1 + 2 + 2)
+    assert!(lines[1].starts_with("0,0x0,0..7 0..4 0..1,7x4x1,"));
+    assert!(lines[6].starts_with("5,1x2,0..7 4..9 3..5,7x5x2,"));
+}
+
+#[test]
+#[cfg(feature = "serde")]
+fn test_inspect_json() {
+    let output = inspect(&["--n", "10,3", "--procs", "3", "--format", "json"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+    assert_eq!(value["n_global"], serde_json::json!([10, 3]));
+    assert_eq!(value["cart_dims"], serde_json::json!([3]));
+    assert_eq!(value["imbalance"]["x"], 1.);
+    assert_eq!(
+        value["ranks"][2],
+        serde_json::json!({
+            "rank": 2,
+            "coords": [2],
+            "pencils": {
+                "x": {"ranges": [[0, 10], [2, 3]], "shape": [10, 1]},
+                "y": {"ranges": [[6, 10], [0, 3]], "shape": [4, 3]},
+            },
+            "transposes": {"x->y": {"messages": 2, "bytes_sent": 48}},
+        })
+    );
+}
+
+#[test]
+fn test_inspect_table() {
+    let output = inspect(&["--n", "10,3", "--procs", "3"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.starts_with("Grid 10 x 3 on 3 = 3 processors"));
+    assert!(stdout.contains("y-pencil: 1.200"));
+}
+
+#[test]
+fn test_inspect_invalid() {
+    for args in [
+        &["--n", "8,8,8", "--procs", "2"][..],
+        &["--n", "8,8,2", "--procs", "2x4"],
+        &["--n", "8,0,8", "--procs", "2x2"],
+        &["--procs", "2x2"],
+        &["--n", "8,8,8", "--procs", "2x2", "--format", "xml"],
+    ] {
+        let output = inspect(args);
+        assert_eq!(output.status.code(), Some(2), "{args:?}");
+        assert!(String::from_utf8(output.stderr)
+            .unwrap()
+            .starts_with("error:"));
+    }
+}
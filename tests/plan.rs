@@ -0,0 +1,72 @@
+//! Planning compared against decompositions on the in-process backend
+use ndarray::Array3;
+use pencil_decomp::comm::Comm;
+use pencil_decomp::plan::Plan;
+use pencil_decomp::{Decomp3, ThreadComm};
+
+#[test]
+fn test_plan_matches_decomp3() {
+    let n_global = [7, 9, 5];
+    let cart_dims = [2, 3];
+    let plan = Plan::new(n_global, cart_dims, 8);
+    let x_to_y = plan.transpose(0, 1);
+    let y_to_z = plan.transpose(1, 2);
+    let x_to_z = plan.transpose(0, 2);
+    ThreadComm::run(6, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]).with_stats();
+        let rank = comm.rank();
+        let pencils = [&decomp.x_pencil, &decomp.y_pencil, &decomp.z_pencil];
+        for (axis, pencil) in pencils.iter().enumerate() {
+            let planned = plan.pencil(axis, rank);
+            assert_eq!(planned.shape, pencil.shape());
+            assert_eq!(planned.ranges, pencil.global_ranges(rank));
+        }
+
+        let x = Array3::<f64>::zeros(decomp.x_pencil.shape());
+        let mut y = Array3::<f64>::zeros(decomp.y_pencil.shape());
+        let mut z = Array3::<f64>::zeros(decomp.z_pencil.shape());
+        decomp.transpose_x_to_y(&x, &mut y);
+        decomp.transpose_y_to_z(&y, &mut z);
+        let stats = decomp.stats().unwrap().local();
+        let r = usize::try_from(rank).unwrap();
+        for (op, transpose) in [("transpose x->y", &x_to_y), ("transpose y->z", &y_to_z)] {
+            let sent = stats[op].bytes_sent;
+            assert_eq!(sent, transpose.send_buf[r] as u64);
+            let row = &transpose.volume[r];
+            assert_eq!(sent, row.iter().map(|&(_, b)| b).sum::<usize>() as u64);
+            assert!(row.iter().all(|&(_, b)| b > 0));
+            assert!(row.windows(2).all(|w| w[0].0 < w[1].0));
+            let received: usize = transpose
+                .volume
+                .iter()
+                .flatten()
+                .filter(|&&(dst, _)| dst == rank)
+                .map(|&(_, b)| b)
+                .sum();
+            assert_eq!(stats[op].bytes_recv, received as u64);
+        }
+    });
+
+    // Every grid point moves exactly once
+    let total = n_global.iter().product::<usize>() * 8;
+    for transpose in [&x_to_y, &y_to_z, &x_to_z] {
+        let moved: usize = transpose.volume.iter().flatten().map(|&(_, b)| b).sum();
+        assert_eq!(moved, total);
+    }
+    assert!(!x_to_y.subarray && !y_to_z.subarray && x_to_z.subarray);
+    assert_eq!(x_to_z.max_buffer_bytes(), 0);
+    // x -> y exchanges along the first dimension of the topology only
+    assert_eq!(x_to_y.messages(0), 1);
+    assert_eq!(y_to_z.messages(0), 2);
+}
+
+#[test]
+fn test_plan_largest_pencil() {
+    let plan = Plan::new([10, 3], [3], 4);
+    let largest = plan.largest_pencil();
+    // y-pencils hold 4 of 10 points on the last processor
+    assert_eq!(largest.axis_contig, 1);
+    assert_eq!(largest.rank, 2);
+    assert_eq!(largest.shape, [4, 3]);
+    assert_eq!(largest.bytes, 4 * 3 * 4);
+}
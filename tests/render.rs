@@ -0,0 +1,57 @@
+//! Rendering of pencil layouts
+use pencil_decomp::plan::Plan;
+use pencil_decomp::render;
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+#[test]
+fn test_svg_labels_every_block() {
+    let plan = Plan::new([7, 9, 5], [2, 3], 8);
+    let svg = render::svg(&plan);
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    // Three faces and one label per rank and pencil
+    assert_eq!(svg.matches("<polygon").count(), 3 * 6 * 3);
+    for rank in 0..6 {
+        let [c0, c1] = plan.cart_coords(rank);
+        let label = format!(">r{rank} ({c0},{c1})</text>");
+        assert_eq!(svg.matches(&label).count(), 3, "{label}");
+    }
+    for name in ["x-pencil", "y-pencil", "z-pencil"] {
+        assert!(svg.contains(name));
+    }
+}
+
+#[test]
+fn test_svg_and_ascii_of_decomp() {
+    ThreadComm::run(2, |comm| {
+        let decomp = Decomp2::from_comm(&comm, [4, 2], [2], [false]);
+        assert_eq!(decomp.to_svg().matches("<rect").count(), 4);
+        let expected = "\
+x-pencil
+|1111
+|0000
++----> x
+y-pencil
+|0011
+|0011
++----> x
+0: r0 (0)
+1: r1 (1)
+";
+        assert_eq!(decomp.to_ascii(), expected);
+
+        let decomp = Decomp3::from_comm(&comm, [4, 3, 2], [2, 1], [false, false]);
+        assert_eq!(decomp.to_svg(), render::svg(&decomp.plan(8)));
+    });
+}
+
+#[test]
+fn test_ascii_samples_large_grids() {
+    let ascii = render::ascii(&Plan::new([1000, 100], [4], 8));
+    let lines: Vec<&str> = ascii.lines().collect();
+    // 32 rows and an axis per pencil, and a legend of 4 ranks
+    assert_eq!(lines.len(), 2 * (1 + 32 + 1) + 4);
+    assert!(lines[1..33].iter().all(|l| l.len() == 1 + 64));
+    let row: String = "0123".chars().flat_map(|c| [c; 16]).collect();
+    assert_eq!(lines[34 + 1], format!("|{row}"));
+}
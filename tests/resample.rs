@@ -0,0 +1,96 @@
+//! Restriction and prolongation on the in-process backend
+mod common;
+
+use common::{assert_close, local, run_on};
+use ndarray::{Array3, Order, ShapeBuilder};
+use pencil_decomp::resample::{prolong, restrict, Weights};
+use pencil_decomp::Decomp3;
+
+const CART_DIMS: [[i32; 2]; 4] = [[1, 1], [2, 2], [1, 3], [3, 2]];
+
+/// Serial application of separable weights
+fn apply(data: &Array3<f64>, weights: &[Weights; 3]) -> Array3<f64> {
+    let shape = [0, 1, 2].map(|a| weights[a].rows.len());
+    Array3::from_shape_fn(shape, |(i, j, k)| {
+        let mut sum = 0.;
+        for &(ii, wi) in &weights[0].rows[i] {
+            for &(jj, wj) in &weights[1].rows[j] {
+                for &(kk, wk) in &weights[2].rows[k] {
+                    sum += wi * wj * wk * data[[ii, jj, kk]];
+                }
+            }
+        }
+        sum
+    })
+}
+
+/// Linear function on the unit cube, sampled at ``n`` grid points
+fn linear(n: [usize; 3]) -> Array3<f64> {
+    let h = n.map(|n| 1. / (n - 1) as f64);
+    Array3::from_shape_fn(n, |(i, j, k)| {
+        1. + 2. * i as f64 * h[0] - 3. * j as f64 * h[1] + 0.5 * k as f64 * h[2]
+    })
+}
+
+#[test]
+fn test_prolong_is_exact_for_linear_fields() {
+    let (coarse, fine) = ([5, 7, 6], [9, 13, 16]);
+    for cart_dims in CART_DIMS {
+        run_on(cart_dims, |comm| {
+            let c = Decomp3::from_comm(&comm, coarse, cart_dims, [false, false]);
+            let f = Decomp3::from_comm(&comm, fine, cart_dims, [false, false])
+                .with_order(Order::ColumnMajor);
+            for c_pencil in [&c.x_pencil, &c.y_pencil, &c.z_pencil] {
+                for f_pencil in [&f.x_pencil, &f.z_pencil] {
+                    let data = local(&linear(coarse), c_pencil);
+                    let mut fine_data = Array3::zeros(f_pencil.shape().f());
+                    prolong(c_pencil, &data, f_pencil, &mut fine_data);
+                    assert_close(&fine_data, &local(&linear(fine), f_pencil), 1e-12);
+                }
+            }
+        });
+    }
+}
+
+#[test]
+fn test_restrict_matches_serial() {
+    let (fine, coarse) = ([9, 13, 8], [5, 7, 4]);
+    let global = Array3::from_shape_fn(fine, |(i, j, k)| ((i * 7 + j * 3 + k * 11) % 13) as f64);
+    let weights = [0, 1, 2].map(|a| Weights::averaging(fine[a], coarse[a]));
+    let expected = apply(&global, &weights);
+    for cart_dims in CART_DIMS {
+        run_on(cart_dims, |comm| {
+            let f = Decomp3::from_comm(&comm, fine, cart_dims, [false, false]).with_stats();
+            let c = Decomp3::from_comm(&comm, coarse, cart_dims, [false, false]);
+            for f_pencil in [&f.x_pencil, &f.y_pencil] {
+                for c_pencil in [&c.x_pencil, &c.y_pencil, &c.z_pencil] {
+                    let data = local(&global, f_pencil);
+                    let mut coarse_data = Array3::zeros(c_pencil.shape());
+                    restrict(f_pencil, &data, c_pencil, &mut coarse_data);
+                    assert_close(&coarse_data, &local(&expected, c_pencil), 1e-12);
+                }
+            }
+            assert_eq!(f.stats().unwrap().local()["restrict y->z"].calls, 1);
+        });
+    }
+}
+
+#[test]
+fn test_weights() {
+    // Full weighting and its normalisation at the boundaries
+    let w = Weights::averaging(5, 3);
+    assert_eq!(w.rows[0], [(0, 2. / 3.), (1, 1. / 3.)]);
+    assert_eq!(w.rows[2], [(3, 1. / 3.), (4, 2. / 3.)]);
+    // Rows of both operators sum to one
+    for (n_in, n_out) in [(3, 7), (4, 9), (5, 5), (6, 11)] {
+        for w in [
+            Weights::interpolation(n_in, n_out),
+            Weights::averaging(n_out, n_in),
+        ] {
+            for row in &w.rows {
+                let sum: f64 = row.iter().map(|(_, w)| w).sum();
+                assert!((sum - 1.).abs() < 1e-12);
+            }
+        }
+    }
+}
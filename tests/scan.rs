@@ -0,0 +1,58 @@
+//! Prefix scans on the in-process backend
+mod common;
+
+use common::{local, run_on};
+use ndarray::{Array2, Array3, Axis, ShapeBuilder};
+use pencil_decomp::scan::{cumsum_along, scan_along};
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+#[test]
+fn test_cumsum_along_all_axes() {
+    let n_global = [5, 7, 6];
+    let global =
+        Array3::from_shape_fn(n_global, |(i, j, k)| ((i * 7 + j * 3 + k * 11) % 13) as f64);
+    for cart_dims in [[1, 1], [2, 2], [1, 4], [3, 2]] {
+        run_on(cart_dims, |comm| {
+            let decomp =
+                Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]).with_stats();
+            for axis in 0..3 {
+                let mut expected = global.clone();
+                expected.accumulate_axis_inplace(Axis(axis), |&prev, x| *x += prev);
+                for pencil in [&decomp.x_pencil, &decomp.y_pencil, &decomp.z_pencil] {
+                    let mut data = Array3::zeros(pencil.shape().f());
+                    data.assign(&local(&global, pencil));
+                    cumsum_along(pencil, &mut data, axis);
+                    assert_eq!(data, local(&expected, pencil));
+                }
+            }
+            let stats = decomp.stats().unwrap().local();
+            assert_eq!(stats["scan x (local)"].calls, 1);
+            assert_eq!(stats["scan x"].calls, 2);
+        });
+    }
+}
+
+#[test]
+fn test_scan_along_closure() {
+    // Running maximum and forward fill
+    ThreadComm::run(3, |comm| {
+        let decomp = Decomp2::from_comm(&comm, [4, 9], [3], [false]);
+        let pencil = &decomp.x_pencil;
+        let st = pencil.dists[1].st;
+        let value = |j: usize| ((j * 5) % 7) as i64;
+
+        let mut data = Array2::from_shape_fn(pencil.shape(), |(_, j)| value(j + st));
+        scan_along(pencil, &mut data, 1, i64::max);
+        for ((_, j), x) in data.indexed_iter() {
+            assert_eq!(*x, (0..=j + st).map(value).max().unwrap());
+        }
+
+        // Forward fill of zeros, which is associative but not commutative
+        let fill = |a: i64, b: i64| if b == 0 { a } else { b };
+        let mut data = Array2::from_shape_fn(pencil.shape(), |(_, j)| value(j + st));
+        scan_along(pencil, &mut data, 1, fill);
+        for ((_, j), x) in data.indexed_iter() {
+            assert_eq!(*x, (0..=j + st).map(value).reduce(fill).unwrap());
+        }
+    });
+}
@@ -0,0 +1,147 @@
+//! Spectral padding and truncation on the in-process backend
+mod common;
+
+use common::{local, run_on};
+use ndarray::{Array2, Array3, ShapeBuilder};
+use pencil_decomp::spectral::{pad, pad_index, truncate, truncate_index};
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+fn value(i: usize, j: usize, k: usize) -> f64 {
+    (1 + i + j * 10 + k * 100) as f64
+}
+
+#[test]
+fn test_index() {
+    assert_eq!(
+        (0..5).map(|i| pad_index(i, 5, 8)).collect::<Vec<_>>(),
+        [Some(0), Some(1), Some(2), Some(6), Some(7)]
+    );
+    // Nyquist mode of even grids is removed
+    assert_eq!(
+        (0..4).map(|i| pad_index(i, 4, 6)).collect::<Vec<_>>(),
+        [Some(0), Some(1), None, Some(5)]
+    );
+    assert_eq!(
+        (0..6).map(|i| truncate_index(i, 6, 4)).collect::<Vec<_>>(),
+        [Some(0), Some(1), None, None, None, Some(3)]
+    );
+    assert_eq!(pad_index(2, 4, 4), Some(2));
+    assert_eq!(truncate_index(2, 4, 4), Some(2));
+    let kept: Vec<_> = (0..8).map(|i| truncate_index(i, 8, 5)).collect();
+    assert_eq!(
+        kept,
+        [
+            Some(0),
+            Some(1),
+            Some(2),
+            None,
+            None,
+            None,
+            Some(3),
+            Some(4)
+        ]
+    );
+    for (n_small, n_large) in [(4, 6), (5, 8), (7, 7), (6, 6), (1, 3)] {
+        for i in 0..n_small {
+            match pad_index(i, n_small, n_large) {
+                Some(j) => assert_eq!(truncate_index(j, n_large, n_small), Some(i)),
+                None => assert_eq!(2 * i, n_small),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_pad_truncate() {
+    let small = [6, 5, 8];
+    let large = [9, 7, 12];
+    let global_small = Array3::from_shape_fn(small, |(i, j, k)| value(i, j, k));
+    let mut global_large = Array3::zeros(large);
+    // Modes of ``global_small`` which survive padding
+    let mut global_kept = Array3::zeros(small);
+    for ((i, j, k), v) in global_small.indexed_iter() {
+        let ijk = [i, j, k];
+        let p = |a: usize| pad_index(ijk[a], small[a], large[a]);
+        if let (Some(pi), Some(pj), Some(pk)) = (p(0), p(1), p(2)) {
+            global_large[[pi, pj, pk]] = *v;
+            global_kept[[i, j, k]] = *v;
+        }
+    }
+    for cart_dims in [[1, 1], [2, 2], [1, 4], [4, 1]] {
+        run_on(cart_dims, |comm| {
+            let s = Decomp3::from_comm(&comm, small, cart_dims, [false, false]).with_stats();
+            let l = Decomp3::from_comm(&comm, large, cart_dims, [false, false]);
+            for s_pencil in [&s.x_pencil, &s.y_pencil, &s.z_pencil] {
+                for l_pencil in [&l.x_pencil, &l.y_pencil, &l.z_pencil] {
+                    let data = local(&global_small, s_pencil);
+                    let mut padded = Array3::from_elem(l_pencil.shape().f(), -1.);
+                    pad(s_pencil, &data, l_pencil, &mut padded);
+                    assert_eq!(padded, local(&global_large, l_pencil));
+
+                    let mut back = Array3::from_elem(s_pencil.shape(), -1.);
+                    truncate(l_pencil, &padded, s_pencil, &mut back);
+                    assert_eq!(back, local(&global_kept, s_pencil));
+                }
+            }
+            assert_eq!(s.stats().unwrap().local()["pad x->z"].calls, 1);
+        });
+    }
+}
+
+#[test]
+fn test_truncate_discards_high_modes() {
+    let small = [4, 4, 4];
+    let large = [6, 6, 6];
+    ThreadComm::run(4, |comm| {
+        let s = Decomp3::from_comm(&comm, small, [2, 2], [false, false]);
+        let l = Decomp3::from_comm(&comm, large, [2, 2], [false, false]);
+        let global_large = Array3::from_shape_fn(large, |(i, j, k)| value(i, j, k));
+        let data = local(&global_large, &l.y_pencil);
+        let mut truncated = Array3::zeros(s.x_pencil.shape());
+        l.truncate(1, &data, &s, 0, &mut truncated);
+        let expected = Array3::from_shape_fn(small, |(i, j, k)| {
+            let p = |i| pad_index(i, 4, 6);
+            match (p(i), p(j), p(k)) {
+                (Some(i), Some(j), Some(k)) => value(i, j, k),
+                _ => 0.,
+            }
+        });
+        assert_eq!(truncated, local(&expected, &s.x_pencil));
+    });
+}
+
+#[test]
+fn test_pad_decomp2() {
+    // 3/2 rule
+    let small = [8, 6];
+    let large = [12, 9];
+    ThreadComm::run(3, |comm| {
+        let s = Decomp2::from_comm(&comm, small, [3], [false]);
+        let l = Decomp2::from_comm(&comm, large, [3], [false]);
+        let st = [s.y_pencil.dists[0].st, s.y_pencil.dists[1].st];
+        let data =
+            Array2::from_shape_fn(s.y_pencil.shape(), |(i, j)| value(i + st[0], j + st[1], 0));
+        // Nyquist modes are removed
+        let kept = Array2::from_shape_fn(s.y_pencil.shape(), |(i, j)| {
+            if i + st[0] == 4 || j + st[1] == 3 {
+                0.
+            } else {
+                data[[i, j]]
+            }
+        });
+        let mut padded = Array2::zeros(l.x_pencil.shape());
+        s.pad(1, &data, &l, 0, &mut padded);
+        let sum: f64 = padded.sum();
+        let mut total = 0.;
+        pencil_decomp::simple_comms::all_gather_sum(&comm, &sum, &mut total);
+        let expected: f64 = (0..8)
+            .filter(|&i| i != 4)
+            .flat_map(|i| (0..6).filter(|&j| j != 3).map(move |j| value(i, j, 0)))
+            .sum();
+        assert!((total - expected).abs() < 1e-9);
+
+        let mut back = Array2::zeros(s.y_pencil.shape());
+        l.truncate(0, &padded, &s, 1, &mut back);
+        assert_eq!(back, kept);
+    });
+}
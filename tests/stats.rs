@@ -0,0 +1,94 @@
+//! Statistics of communication routines on the in-process backend
+use ndarray::{Array2, Array3};
+use pencil_decomp::comm::Comm;
+use pencil_decomp::stats::{OpStats, Stats};
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+#[test]
+fn test_stats_transpose() {
+    let n_global = [6, 5, 4];
+    let reports = ThreadComm::run(4, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, [2, 2], [false, false]).with_stats();
+        let x_data = Array3::<f64>::zeros(decomp.x_pencil.shape());
+        let mut y_data = Array3::<f64>::zeros(decomp.y_pencil.shape());
+        let mut z_data = Array3::<f64>::zeros(decomp.z_pencil.shape());
+        for _ in 0..3 {
+            decomp.transpose_x_to_y(&x_data, &mut y_data);
+        }
+        decomp.transpose_y_to_z(&y_data, &mut z_data);
+        decomp.report()
+    });
+    let report = &reports[0];
+    assert!(reports.iter().all(|r| r == report));
+    assert_eq!(report.nprocs, 4);
+    let ops: Vec<&str> = report.ops.iter().map(|op| op.op.as_str()).collect();
+    assert_eq!(ops, ["transpose x->y", "transpose y->z"]);
+    assert_eq!(report.ops[0].calls, 3);
+    assert_eq!(report.ops[1].calls, 1);
+
+    // Every element is sent and received once per call
+    let total = (n_global.iter().product::<usize>() * 8) as f64;
+    for op in &report.ops {
+        let calls = op.calls as f64;
+        assert_eq!(op.bytes_sent.avg * 4., total * calls);
+        assert_eq!(op.bytes_recv.avg * 4., total * calls);
+        assert!(op.bytes_sent.min <= op.bytes_sent.avg);
+        assert!(op.bytes_sent.avg <= op.bytes_sent.max);
+        assert!(op.exchange.min >= 0.);
+    }
+    #[cfg(feature = "serde")]
+    {
+        let json = report.to_json();
+        assert!(json.starts_with(r#"{"nprocs":4,"ops":[{"op":"transpose x->y","calls":3,"#));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            value["ops"][1]["bytes_sent"]["avg"],
+            report.ops[1].bytes_sent.avg
+        );
+    }
+}
+
+#[test]
+fn test_stats_gather_scatter() {
+    let n_global = [7, 9];
+    let reports = ThreadComm::run(3, |comm| {
+        let decomp = Decomp2::from_comm(&comm, n_global, [3], [false]).with_stats();
+        let mut data = Array2::<f64>::zeros(n_global);
+        let mut x_data = Array2::<f64>::zeros(decomp.x_pencil.shape());
+        decomp.scatter_x(&data, &mut x_data);
+        decomp.gather_x(&x_data, &mut data);
+        decomp.stats().unwrap().local()
+    });
+    // Root sends and receives the whole array
+    let total = (n_global.iter().product::<usize>() * 8) as u64;
+    assert_eq!(reports[0]["scatter x"].bytes_sent, total);
+    assert_eq!(reports[0]["gather x"].bytes_recv, total);
+    assert_eq!(reports[1]["scatter x"].bytes_sent, 0);
+    assert_eq!(reports[1]["gather x"].bytes_recv, 0);
+    let sent: u64 = reports.iter().map(|r| r["gather x"].bytes_sent).sum();
+    assert_eq!(sent, total);
+}
+
+#[test]
+fn test_stats_disabled() {
+    ThreadComm::run(2, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [4, 4, 4], [2, 1], [false, false]);
+        assert!(decomp.stats().is_none());
+    });
+}
+
+#[test]
+#[should_panic(expected = "Processors recorded different operations")]
+fn test_report_different_operations() {
+    // Same number of operations, but different names
+    ThreadComm::run(2, |comm| {
+        let stats = Stats::new();
+        let op = if comm.rank() == 0 {
+            "gather x"
+        } else {
+            "scatter x"
+        };
+        stats.record(op, OpStats::default());
+        stats.report(&comm)
+    });
+}
@@ -0,0 +1,231 @@
+//! Collective routines of the in-process backend
+use pencil_decomp::comm::{Comm, Exchange};
+use pencil_decomp::simple_comms::{
+    all_gather_sum, broadcast_scalar, broadcast_scalar_from, gather_apply_to, gather_sum,
+    gather_sum_to,
+};
+use pencil_decomp::ThreadComm;
+
+#[test]
+fn test_all_to_all_varcount() {
+    for nprocs in 1..6 {
+        ThreadComm::run(nprocs, |comm| {
+            let (rank, size) = (comm.rank(), comm.size());
+            // processor p sends p + 1 copies of its rank to every processor
+            let send = vec![rank; (size * (rank + 1)).try_into().unwrap()];
+            let send_counts = vec![rank + 1; nprocs];
+            let send_displs: Vec<i32> = (0..size).map(|p| p * (rank + 1)).collect();
+            let recv_counts: Vec<i32> = (1..=size).collect();
+            let recv_displs: Vec<i32> = (0..size).map(|p| p * (p + 1) / 2).collect();
+            let mut recv = vec![-1; recv_counts.iter().sum::<i32>().try_into().unwrap()];
+            comm.all_to_all_varcount(
+                &send,
+                &send_counts,
+                &send_displs,
+                &mut recv,
+                &recv_counts,
+                &recv_displs,
+            );
+            let expected: Vec<i32> = (0..size)
+                .flat_map(|p| std::iter::repeat_n(p, (p + 1).try_into().unwrap()))
+                .collect();
+            assert_eq!(recv, expected);
+        });
+    }
+}
+
+#[test]
+fn test_all_to_all_varcount_with() {
+    // Irregular blocks, some empty, received in reversed order
+    let count = |src: i32, dst: i32| {
+        if (src + dst) % 3 == 0 {
+            0
+        } else {
+            src + dst + 1
+        }
+    };
+    for exchange in [
+        Exchange::Collective,
+        Exchange::Pairwise,
+        Exchange::Nonblocking,
+    ] {
+        for nprocs in 1..6 {
+            ThreadComm::run(nprocs, |comm| {
+                let (rank, size) = (comm.rank(), comm.size());
+                let send_counts: Vec<i32> = (0..size).map(|p| count(rank, p)).collect();
+                let send_displs: Vec<i32> = (0..size)
+                    .map(|p| send_counts[..p as usize].iter().sum())
+                    .collect();
+                let send: Vec<i32> = (0..size)
+                    .flat_map(|p| std::iter::repeat_n(rank * 10 + p, count(rank, p) as usize))
+                    .collect();
+                let recv_counts: Vec<i32> = (0..size).map(|p| count(p, rank)).collect();
+                let recv_displs: Vec<i32> = (0..size)
+                    .map(|p| recv_counts[p as usize + 1..].iter().sum())
+                    .collect();
+                let mut recv = vec![-1; recv_counts.iter().sum::<i32>() as usize];
+                comm.all_to_all_varcount_with(
+                    exchange,
+                    &send,
+                    &send_counts,
+                    &send_displs,
+                    &mut recv,
+                    &recv_counts,
+                    &recv_displs,
+                );
+                let expected: Vec<i32> = (0..size)
+                    .rev()
+                    .flat_map(|p| std::iter::repeat_n(p * 10 + rank, count(p, rank) as usize))
+                    .collect();
+                assert_eq!(recv, expected, "{exchange}");
+            });
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Neighbor exchange is not supported")]
+fn test_neighbor_exchange_rejected() {
+    ThreadComm::run(2, |comm| {
+        let mut recv = [0; 2];
+        let counts = [1, 1];
+        let displs = [0, 1];
+        comm.all_to_all_varcount_with(
+            Exchange::Neighbor,
+            &[1, 2],
+            &counts,
+            &displs,
+            &mut recv,
+            &counts,
+            &displs,
+        );
+    });
+}
+
+#[test]
+fn test_gather_scatter_varcount() {
+    for nprocs in 1..6 {
+        for root in 0..nprocs {
+            let root: i32 = root.try_into().unwrap();
+            ThreadComm::run(nprocs, |comm| {
+                let (rank, size) = (comm.rank(), comm.size());
+                let counts: Vec<i32> = (1..=size).collect();
+                let displs: Vec<i32> = (0..size).map(|p| p * (p + 1) / 2).collect();
+                let global: Vec<i32> = (0..size)
+                    .flat_map(|p| std::iter::repeat_n(p, (p + 1).try_into().unwrap()))
+                    .collect();
+                // Gather
+                let send = vec![rank; (rank + 1).try_into().unwrap()];
+                let mut recv = vec![-1; global.len()];
+                comm.gather_varcount(root, &send, &mut recv, &counts, &displs);
+                if rank == root {
+                    assert_eq!(recv, global);
+                }
+                // Scatter
+                let mut recv = vec![-1; send.len()];
+                comm.scatter_varcount(root, &global, &counts, &displs, &mut recv);
+                assert_eq!(recv, send);
+            });
+        }
+    }
+}
+
+#[test]
+fn test_all_gather_varcount() {
+    for nprocs in 1..6 {
+        ThreadComm::run(nprocs, |comm| {
+            let (rank, size) = (comm.rank(), comm.size());
+            let counts: Vec<i32> = (1..=size).collect();
+            let displs: Vec<i32> = (0..size).map(|p| p * (p + 1) / 2).collect();
+            let send = vec![rank; (rank + 1).try_into().unwrap()];
+            let mut recv = vec![-1; (size * (size + 1) / 2).try_into().unwrap()];
+            comm.all_gather_varcount(&send, &mut recv, &counts, &displs);
+            let expected: Vec<i32> = (0..size)
+                .flat_map(|p| std::iter::repeat_n(p, (p + 1).try_into().unwrap()))
+                .collect();
+            assert_eq!(recv, expected);
+        });
+    }
+}
+
+#[test]
+fn test_exclusive_scan() {
+    for nprocs in 1..6 {
+        ThreadComm::run(nprocs, |comm| {
+            let rank = comm.rank();
+            let mut recv = [-1, -1];
+            comm.exclusive_scan(&[rank + 1, 2], &mut recv, |a, b| a + b);
+            if rank == 0 {
+                assert_eq!(recv, [-1, -1]);
+            } else {
+                assert_eq!(recv, [rank * (rank + 1) / 2, 2 * rank]);
+            }
+            // Not commutative: operands of lower ranks come first
+            let mut recv = [0];
+            comm.exclusive_scan(&[rank], &mut recv, |_, b| b);
+            assert_eq!(recv[0], (rank - 1).max(0));
+        });
+    }
+}
+
+#[test]
+fn test_split() {
+    ThreadComm::run(6, |comm| {
+        // Two groups of even and odd ranks, in reversed order
+        let sub = comm.split(comm.rank() % 2, -comm.rank());
+        assert_eq!(sub.size(), 3);
+        assert_eq!(sub.rank(), 2 - comm.rank() / 2);
+        let mut recv = [0; 3];
+        sub.all_gather(&[comm.rank()], &mut recv);
+        if comm.rank() % 2 == 0 {
+            assert_eq!(recv, [4, 2, 0]);
+        } else {
+            assert_eq!(recv, [5, 3, 1]);
+        }
+    });
+}
+
+#[test]
+fn test_simple_comms() {
+    let results = ThreadComm::run(4, |comm| {
+        let mut x: f64 = if comm.rank() == 0 { 3.5 } else { 0. };
+        broadcast_scalar(&comm, &mut x);
+        assert!((x - 3.5).abs() < f64::EPSILON);
+
+        let mut sum = 0;
+        gather_sum(&comm, &(comm.rank() + 1), &mut sum);
+        let mut all_sum = 0;
+        all_gather_sum(&comm, &(comm.rank() + 1), &mut all_sum);
+        (sum, all_sum)
+    });
+    assert_eq!(results, [(10, 10), (0, 10), (0, 10), (0, 10)]);
+}
+
+#[test]
+fn test_simple_comms_root() {
+    let results = ThreadComm::run(3, |comm| {
+        let root = 2;
+        let mut x: i32 = if comm.rank() == root { 7 } else { 0 };
+        broadcast_scalar_from(&comm, root, &mut x);
+        assert_eq!(x, 7);
+
+        let mut sum = 0;
+        gather_sum_to(&comm, root, &(comm.rank() + 1), &mut sum);
+        let mut max = 0;
+        gather_apply_to(&comm, root, &comm.rank(), &mut max, |v| {
+            *v.iter().max().unwrap()
+        });
+        (sum, max)
+    });
+    assert_eq!(results, [(0, 0), (0, 0), (6, 2)]);
+}
+
+#[test]
+#[should_panic(expected = "rank 1 failed")]
+fn test_panic_is_propagated() {
+    ThreadComm::run(3, |comm| {
+        assert!(comm.rank() != 1, "rank 1 failed");
+        let mut data = [0];
+        comm.broadcast(0, &mut data);
+    });
+}
@@ -0,0 +1,117 @@
+//! Tridiagonal solves on the in-process backend
+mod common;
+
+use common::{assert_close, local, run_on};
+use ndarray::{Array2, Array3, Axis, ShapeBuilder};
+use pencil_decomp::tridiag::{solve, solve_transposed, Tridiagonal};
+use pencil_decomp::{Decomp2, Decomp3, ThreadComm};
+
+/// Diagonally dominant matrix with varying coefficients
+fn matrix(n: usize) -> Tridiagonal {
+    let lower = (0..n).map(|i| -0.3 - 0.01 * i as f64).collect();
+    let diag = (0..n).map(|i| 2. + 0.1 * (i % 3) as f64).collect();
+    let upper = (0..n).map(|i| -0.5 + 0.02 * i as f64).collect();
+    Tridiagonal::new(lower, diag, upper)
+}
+
+/// Product of ``matrix`` with ``x`` along ``axis``
+fn mul(matrix: &Tridiagonal, x: &Array3<f64>, axis: usize) -> Array3<f64> {
+    let mut y = x.clone();
+    for (src, mut dst) in x.lanes(Axis(axis)).into_iter().zip(y.lanes_mut(Axis(axis))) {
+        let n = src.len();
+        for i in 0..n {
+            dst[i] = matrix.diag[i] * src[i];
+            if i > 0 {
+                dst[i] += matrix.lower[i] * src[i - 1];
+            }
+            if i + 1 < n {
+                dst[i] += matrix.upper[i] * src[i + 1];
+            }
+        }
+    }
+    y
+}
+
+#[test]
+fn test_solve_along_all_axes() {
+    // Includes blocks of a single row along split axes
+    let n_global = [7, 4, 9];
+    let x = Array3::from_shape_fn(n_global, |(i, j, k)| ((i * 7 + j * 3 + k * 11) % 13) as f64);
+    for cart_dims in [[1, 1], [2, 2], [1, 4], [4, 1], [3, 2]] {
+        run_on(cart_dims, |comm| {
+            let decomp =
+                Decomp3::from_comm(&comm, n_global, cart_dims, [false, false]).with_stats();
+            for (axis, &n) in n_global.iter().enumerate() {
+                let matrix = matrix(n);
+                let rhs = mul(&matrix, &x, axis);
+                for pencil in [&decomp.x_pencil, &decomp.y_pencil, &decomp.z_pencil] {
+                    let mut data = local(&rhs, pencil);
+                    solve(pencil, &matrix, axis, &mut data);
+                    assert_close(&data, &local(&x, pencil), 1e-12);
+                }
+            }
+            let stats = decomp.stats().unwrap().local();
+            assert_eq!(stats["tridiagonal x (local)"].calls, 1);
+            assert_eq!(stats["tridiagonal x"].calls, 2);
+            // Four spike entries and two ends per line, for y and z pencil
+            let lines = |shape: [usize; 3]| shape[1] * shape[2];
+            let sent =
+                4 * 2 + 2 * (lines(decomp.y_pencil.shape()) + lines(decomp.z_pencil.shape()));
+            assert_eq!(stats["tridiagonal x"].bytes_sent, 8 * sent as u64);
+        });
+    }
+}
+
+#[test]
+fn test_solve_transposed() {
+    let n_global = [6, 8, 5];
+    let x = Array3::from_shape_fn(n_global, |(i, j, k)| (i + 2 * j) as f64 - 0.5 * k as f64);
+    ThreadComm::run(4, |comm| {
+        let decomp = Decomp3::from_comm(&comm, n_global, [2, 2], [false, false]);
+        let matrix = matrix(8);
+        let rhs = mul(&matrix, &x, 1);
+        let pencil = &decomp.x_pencil;
+        let mut direct = local(&rhs, pencil);
+        solve(pencil, &matrix, 1, &mut direct);
+        let mut transposed = Array3::zeros(pencil.shape().f());
+        transposed.assign(&local(&rhs, pencil));
+        solve_transposed(pencil, &decomp.y_pencil, &matrix, &mut transposed);
+        assert_close(&direct, &transposed, 1e-12);
+        assert_close(&direct, &local(&x, pencil), 1e-12);
+    });
+}
+
+#[test]
+fn test_solve_decomp2() {
+    ThreadComm::run(3, |comm| {
+        let decomp = Decomp2::from_comm(&comm, [5, 9], [3], [false]);
+        let matrix = Tridiagonal::constant(9, -1., 4., -1.);
+        let pencil = &decomp.x_pencil;
+        let st = pencil.dists[1].st;
+        // Solution is one everywhere
+        let mut data = Array2::from_shape_fn(pencil.shape(), |(_, j)| {
+            if j + st == 0 || j + st == 8 {
+                3_f64
+            } else {
+                2.
+            }
+        });
+        solve(pencil, &matrix, 1, &mut data);
+        assert!(data.iter().all(|x| (x - 1.).abs() < 1e-12));
+    });
+}
+
+#[test]
+#[should_panic(expected = "Matrix of size")]
+fn test_solve_size_mismatch() {
+    ThreadComm::run(1, |comm| {
+        let decomp = Decomp3::from_comm(&comm, [4, 4, 4], [1, 1], [false, false]);
+        let mut data = Array3::<f64>::zeros(decomp.x_pencil.shape());
+        solve(
+            &decomp.x_pencil,
+            &Tridiagonal::constant(5, 0., 1., 0.),
+            0,
+            &mut data,
+        );
+    });
+}